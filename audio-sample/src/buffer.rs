@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use super::error::*;
+use super::Sample;
+
+#[cfg(test)]
+mod tests {
+    use super::AudioBuffer;
+    use super::super::AudioBufferError;
+
+    #[test]
+    fn test_audio_buffer() {
+        let buffer = AudioBuffer::new(vec![vec![0.0, 0.1, 0.2, 0.3], vec![1.0, 1.1, 1.2, 1.3]], 8000).unwrap();
+        assert_eq!(buffer.channel_num(), 2);
+        assert_eq!(buffer.frame_num(), 4);
+        assert_eq!(buffer.sampling_rate(), 8000);
+        assert_eq!(buffer.to_interleaved(), vec![0.0, 1.0, 0.1, 1.1, 0.2, 1.2, 0.3, 1.3]);
+        assert_eq!(AudioBuffer::from_interleaved(&buffer.to_interleaved(), 2, 8000).unwrap(), buffer);
+
+        // Views share the data.
+        let view = buffer.view(1, 3);
+        assert_eq!(view.frame_num(), 2);
+        assert_eq!(view.frame_pos(), 1);
+        assert_eq!(view.channel(1), &[1.1, 1.2]);
+        assert_eq!(view.view(1, 10).channel(0), &[0.2]);
+        assert_eq!(view.view(1, 10).frame_pos(), 2);
+        assert!(buffer.view(5, 10).is_empty());
+        assert_eq!(view.to_channel_vec(), vec![vec![0.1, 0.2], vec![1.1, 1.2]]);
+        assert_eq!(view.convert::<f32>().channel(0), &[0.1_f32, 0.2]);
+    }
+
+    #[test]
+    fn test_audio_buffer_shape() {
+        assert_eq!(AudioBuffer::<f64>::new(Vec::new(), 8000), Err(AudioBufferError::NoChannel));
+        assert_eq!(AudioBuffer::new(vec![vec![0.0]], 0), Err(AudioBufferError::SamplingRate { sampling_rate: 0 }));
+        assert_eq!(AudioBuffer::new(vec![vec![0.0; 3], vec![0.0; 2]], 8000), Err(AudioBufferError::DataLength { ch_idx: 1, expected: 3, actual: 2 }));
+        assert_eq!(AudioBuffer::from_interleaved(&[0_i16; 5], 2, 8000), Err(AudioBufferError::DataLength { ch_idx: 1, expected: 3, actual: 2 }));
+    }
+}
+
+// Planar (channel<data>) audio data with its sampling rate.
+// Cloning and view() share the data, only the frame range differs.
+#[derive(Debug, Clone)]
+pub struct AudioBuffer<S: Sample = f64> {
+    ch_vec: Arc<Vec<Vec<S>>>,
+    sampling_rate: usize,
+    stt: usize,
+    end: usize,
+}
+
+impl<S: Sample> AudioBuffer<S> {
+    // All channels must have the same length.
+    pub fn new(ch_vec: Vec<Vec<S>>, sampling_rate: usize) -> Result<AudioBuffer<S>> {
+        // -- Check Parameter --
+        if ch_vec.is_empty() {
+            return Err(AudioBufferError::NoChannel);
+        }
+        if sampling_rate == 0 {
+            return Err(AudioBufferError::SamplingRate { sampling_rate });
+        }
+        for (ch_idx, ch) in ch_vec.iter().enumerate() {
+            if ch.len() != ch_vec[0].len() {
+                return Err(AudioBufferError::DataLength { ch_idx, expected: ch_vec[0].len(), actual: ch.len() });
+            }
+        }
+        let end = ch_vec[0].len();
+        Ok(AudioBuffer { ch_vec: Arc::new(ch_vec), sampling_rate, stt: 0, end })
+    }
+
+    // Interleaved (frame<channel>) data.
+    pub fn from_interleaved(data: &[S], channel_num: usize, sampling_rate: usize) -> Result<AudioBuffer<S>> {
+        if channel_num == 0 {
+            return Err(AudioBufferError::NoChannel);
+        }
+        let mut ch_vec: Vec<Vec<S>> = vec![Vec::with_capacity(data.len() / channel_num); channel_num];
+        for (idx, sample) in data.iter().enumerate() {
+            ch_vec[idx % channel_num].push(*sample);
+        }
+        AudioBuffer::new(ch_vec, sampling_rate)
+    }
+
+    pub fn channel_num(&self) -> usize {
+        self.ch_vec.len()
+    }
+
+    pub fn sampling_rate(&self) -> usize {
+        self.sampling_rate
+    }
+
+    pub fn frame_num(&self) -> usize {
+        self.end - self.stt
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stt == self.end
+    }
+
+    // Position of the first frame in the whole data.
+    pub fn frame_pos(&self) -> usize {
+        self.stt
+    }
+
+    pub fn channel(&self, ch_idx: usize) -> &[S] {
+        &self.ch_vec[ch_idx][self.stt..self.end]
+    }
+
+    pub fn channels(&self) -> impl Iterator<Item = &[S]> {
+        self.ch_vec.iter().map(move |ch| &ch[self.stt..self.end])
+    }
+
+    // Frames "stt".."end" of this view without copying. The range is clamped to the view.
+    pub fn view(&self, stt: usize, end: usize) -> AudioBuffer<S> {
+        let end = self.stt + end.min(self.frame_num());
+        let stt = (self.stt + stt).min(end);
+        AudioBuffer { ch_vec: self.ch_vec.clone(), sampling_rate: self.sampling_rate, stt, end }
+    }
+
+    pub fn to_channel_vec(&self) -> Vec<Vec<S>> {
+        self.channels().map(|ch| ch.to_vec()).collect()
+    }
+
+    pub fn to_interleaved(&self) -> Vec<S> {
+        let mut data: Vec<S> = Vec::with_capacity(self.frame_num() * self.channel_num());
+        for frame_idx in self.stt..self.end {
+            for ch in self.ch_vec.iter() {
+                data.push(ch[frame_idx]);
+            }
+        }
+        data
+    }
+
+    // Copy of the view in another sample type.
+    pub fn convert<T: Sample>(&self) -> AudioBuffer<T> {
+        let ch_vec: Vec<Vec<T>> = self.channels().map(|ch| ch.iter().map(|sample| T::from_sample(*sample)).collect()).collect();
+        let end = ch_vec[0].len();
+        AudioBuffer { ch_vec: Arc::new(ch_vec), sampling_rate: self.sampling_rate, stt: 0, end }
+    }
+}
+
+// Same samples and sampling rate. Views of different data can be equal.
+impl<S: Sample> PartialEq for AudioBuffer<S> {
+    fn eq(&self, other: &AudioBuffer<S>) -> bool {
+        self.sampling_rate == other.sampling_rate
+            && self.channel_num() == other.channel_num()
+            && self.channels().zip(other.channels()).all(|(ch, other_ch)| ch == other_ch)
+    }
+}
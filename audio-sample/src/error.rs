@@ -0,0 +1,23 @@
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, AudioBufferError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioBufferError {
+    NoChannel,
+    SamplingRate { sampling_rate: usize },
+    DataLength { ch_idx: usize, expected: usize, actual: usize },
+}
+
+impl fmt::Display for AudioBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> std::result::Result<(), fmt::Error> {
+        match self {
+            AudioBufferError::NoChannel => write!(f, "Irregal Audio Buffer! No Channel"),
+            AudioBufferError::SamplingRate { sampling_rate } => write!(f, "Irregal Audio Buffer! Sampling Rate:{}", sampling_rate),
+            AudioBufferError::DataLength { ch_idx, expected, actual } =>
+                write!(f, "Irregal Audio Buffer! Channel {} Length:{} Expected:{}", ch_idx, actual, expected),
+        }
+    }
+}
+
+impl std::error::Error for AudioBufferError {}
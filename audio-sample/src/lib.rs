@@ -0,0 +1,172 @@
+use std::ops::{Add, Sub, Mul, Div, Neg, AddAssign};
+
+mod error;
+pub use error::AudioBufferError;
+
+mod buffer;
+pub use buffer::AudioBuffer;
+
+#[cfg(test)]
+mod tests {
+    use super::{Sample, Float, I24};
+
+    #[test]
+    fn test_integer_round_trip() {
+        for code in 0..=u8::MAX {
+            assert_eq!(u8::from_f64(code.to_f64()), code);
+        }
+        for code in i16::MIN..=i16::MAX {
+            assert_eq!(i16::from_f64(code.to_f64()), code);
+        }
+        for code in [I24::MIN, -1, 0, 1, 0x12345, I24::MAX].iter() {
+            let sample = I24::new(*code);
+            assert_eq!(I24::from_f64(sample.to_f64()), sample);
+        }
+        for code in [i32::MIN, -1, 0, 1, 0x12345678, i32::MAX].iter() {
+            assert_eq!(i32::from_f64(code.to_f64()), *code);
+        }
+    }
+
+    #[test]
+    fn test_full_scale() {
+        assert_eq!(i16::MIN.to_f64(), -1.0);
+        assert_eq!(0x80_u8.to_f64(), 0.0);
+        assert_eq!(i16::from_f64(2.0), i16::MAX);
+        assert_eq!(i16::from_f64(-2.0), i16::MIN);
+        assert_eq!(I24::from_f64(1.0), I24::new(I24::MAX));
+        assert_eq!(I24::new(0x7FFF_FFFF).get(), I24::MAX);
+        assert_eq!(f32::from_sample(i16::from_f64(0.5)), 0.5_f32);
+        assert_eq!(i16::from_sample(-0.25_f32), -0x2000);
+        assert_eq!(Float::sqrt(0.25_f32), 0.5);
+        assert_eq!(Float::powi(-0.5_f64, 2), 0.25);
+    }
+}
+
+// Sample value of audio data. Full scale is -1.0..1.0 in f64.
+// Integer samples are converted like WAV PCM: the value is truncated to the bits and clamped.
+pub trait Sample: Copy + Default + PartialOrd + Send + Sync + std::fmt::Debug + 'static {
+    const BITS: usize;
+    fn from_f64(val: f64) -> Self;
+    fn to_f64(self) -> f64;
+
+    fn from_sample<S: Sample>(sample: S) -> Self {
+        Self::from_f64(sample.to_f64())
+    }
+}
+
+// Sample type used for calculation.
+pub trait Float: Sample + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self> + AddAssign {
+    fn sqrt(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+}
+
+const I32_SCALE: f64 = 2147483648.0;
+
+fn f64_to_i32(val: f64) -> i32 {
+    if val < -1.0 {
+        i32::MIN
+    }
+    else if 1.0 < val {
+        i32::MAX
+    }
+    else {
+        // 1.0 is saturated to i32::MAX.
+        (val * I32_SCALE) as i32
+    }
+}
+
+// 24bit signed integer in the lower bits of i32.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct I24(i32);
+
+impl I24 {
+    pub const MIN: i32 = -0x80_0000;
+    pub const MAX: i32 = 0x7F_FFFF;
+
+    // Clamped to 24bit.
+    pub fn new(val: i32) -> I24 {
+        I24(val.clamp(I24::MIN, I24::MAX))
+    }
+
+    pub fn get(self) -> i32 {
+        self.0
+    }
+}
+
+impl Sample for u8 {
+    const BITS: usize = 8;
+    fn from_f64(val: f64) -> u8 {
+        ((f64_to_i32(val) >> 24) as u8) ^ 0x80
+    }
+    fn to_f64(self) -> f64 {
+        f64::from(i32::from(self ^ 0x80) << 24 >> 24) / 128.0
+    }
+}
+
+impl Sample for i16 {
+    const BITS: usize = 16;
+    fn from_f64(val: f64) -> i16 {
+        (f64_to_i32(val) >> 16) as i16
+    }
+    fn to_f64(self) -> f64 {
+        f64::from(self) / 32768.0
+    }
+}
+
+impl Sample for I24 {
+    const BITS: usize = 24;
+    fn from_f64(val: f64) -> I24 {
+        I24(f64_to_i32(val) >> 8)
+    }
+    fn to_f64(self) -> f64 {
+        f64::from(self.0) / 8388608.0
+    }
+}
+
+impl Sample for i32 {
+    const BITS: usize = 32;
+    fn from_f64(val: f64) -> i32 {
+        f64_to_i32(val)
+    }
+    fn to_f64(self) -> f64 {
+        f64::from(self) / I32_SCALE
+    }
+}
+
+impl Sample for f32 {
+    const BITS: usize = 32;
+    fn from_f64(val: f64) -> f32 {
+        val as f32
+    }
+    fn to_f64(self) -> f64 {
+        f64::from(self)
+    }
+}
+
+impl Sample for f64 {
+    const BITS: usize = 64;
+    fn from_f64(val: f64) -> f64 {
+        val
+    }
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+impl Float for f32 {
+    fn sqrt(self) -> f32 {
+        f32::sqrt(self)
+    }
+    fn powi(self, n: i32) -> f32 {
+        f32::powi(self, n)
+    }
+}
+
+impl Float for f64 {
+    fn sqrt(self) -> f64 {
+        f64::sqrt(self)
+    }
+    fn powi(self, n: i32) -> f64 {
+        f64::powi(self, n)
+    }
+}
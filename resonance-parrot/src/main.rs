@@ -22,6 +22,66 @@ use keyhit_input::*;
 
 #[cfg(test)]
 mod tests {
+    use super::{parse_args, Args, Track, wav_to_track};
+    use wavfile::{WavFile, Fmt, InfoTags, INFO_TITLE, CuePoint, AudioBuffer, to_wav_audio};
+
+    fn args(arg_vec: &[&str]) -> super::Result<Args> {
+        parse_args(arg_vec.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_track_resample() {
+        let track = Track {
+            file_path: std::path::PathBuf::from("a.wav"), format_id: 1, channel: 1, sampling_rate: 8000, bits: 16, valid_bits: 16, channel_mask: 0,
+            info_tags: InfoTags::default(), cue_vec: vec![CuePoint::new(1, 100)], op_smpl: None,
+            buffer: AudioBuffer::new(vec![vec![0.0; 8000]], 8000).unwrap(),
+        };
+        let track = track.resample(16000).unwrap();
+        assert_eq!((track.sampling_rate, track.buffer.sampling_rate(), track.buffer.frame_num()), (16000, 16000, 16000));
+        assert_eq!(track.cue_vec[0].position, 200);
+    }
+
+    #[test]
+    fn test_wav_to_track() {
+        let mut wav_file = WavFile::new();
+        wav_file.update_wav_audio(&to_wav_audio(&vec![vec![0.5; 3000], vec![-0.5; 3000]], &Fmt::new(1, 2, 2000, 16)).unwrap()).unwrap();
+        let mut info_tags = InfoTags::new();
+        info_tags.set(INFO_TITLE, "Take 1");
+        wav_file.set_info_tags(&info_tags).unwrap();
+        wav_file.set_cue_points(&[CuePoint::new(1, 2500)]).unwrap();
+        let path = std::env::temp_dir().join("resonance_parrot_track_test.wav");
+        wav_file.save_as(&path).unwrap();
+        let track = wav_to_track(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((track.channel, track.sampling_rate, track.bits), (2, 2000, 16));
+        assert_eq!(track.buffer.frame_num(), 3000);
+        assert_eq!((track.buffer.channel(0)[2999], track.buffer.channel(1)[0]), (0.5, -0.5));
+        assert_eq!(track.info_tags, info_tags);
+        assert_eq!(track.cue_vec[0].position, 2500);
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(args(&[]).unwrap(), Args { file_path: "./test.wav".to_string(), pitch_standard_frequency: 440.0, op_estimate_seconds: None, op_sampling_rate: None });
+        let baroque = args(&["--a4", "415", "--estimate-a4", "10", "bach.flac"]).unwrap();
+        assert_eq!(baroque, Args { file_path: "bach.flac".to_string(), pitch_standard_frequency: 415.0, op_estimate_seconds: Some(10.0), op_sampling_rate: None });
+        assert_eq!(args(&["--a4=442", "a.wav"]).unwrap().pitch_standard_frequency, 442.0);
+        assert!(args(&["--a4"]).is_err());
+        assert!(args(&["--a4", "44"]).is_err());
+        assert!(args(&["--estimate-a4", "0"]).is_err());
+        assert_eq!(args(&["--rate", "48000", "a.wav"]).unwrap().op_sampling_rate, Some(48000));
+        assert!(args(&["--rate", "0"]).is_err());
+        assert!(args(&["--rate", "500"]).is_err());
+        assert!(args(&["--rate", "999"]).is_err());
+        assert_eq!(args(&["--rate", "1000"]).unwrap().op_sampling_rate, Some(1000));
+        assert_eq!(args(&["--rate", "768000"]).unwrap().op_sampling_rate, Some(768000));
+        assert!(args(&["--rate", "768001"]).is_err());
+        assert!(args(&["--rate", "1000000"]).is_err());
+        assert!(args(&["--rate", "44100.5"]).is_err());
+        assert!(args(&["--tune", "a.wav"]).is_err());
+        assert!(args(&["a.wav", "b.wav"]).is_err());
+    }
 }
 
 #[allow(dead_code)]
@@ -32,7 +92,35 @@ pub struct Track {
     pub channel: usize,
     pub sampling_rate: usize,
     pub bits: usize,
-    pub ch_vec: Vec<Vec<f64>>,
+    pub valid_bits: usize,
+    pub channel_mask: usize,
+    pub info_tags: InfoTags,
+    pub cue_vec: Vec<CuePoint>,    // sorted by position
+    pub op_smpl: Option<Smpl>,
+    pub buffer: AudioBuffer,
+}
+
+impl Track {
+    // Same track converted to "sampling_rate".
+    pub fn resample(&self, sampling_rate: usize) -> Result<Track> {
+        let mut track = self.clone();
+        track.buffer = AudioBuffer::new(resample(&self.buffer.to_channel_vec(), self.sampling_rate, sampling_rate)?, sampling_rate)?;
+        for cue in track.cue_vec.iter_mut() {
+            cue.position = cue.position * sampling_rate / self.sampling_rate;
+            if let Some(region) = cue.region.as_mut() {
+                region.length = region.length * sampling_rate / self.sampling_rate;
+            }
+        }
+        if let Some(smpl) = track.op_smpl.as_mut() {
+            for sample_loop in smpl.loops.iter_mut() {
+                sample_loop.start = sample_loop.start * sampling_rate / self.sampling_rate;
+                sample_loop.end = sample_loop.end * sampling_rate / self.sampling_rate;
+            }
+            smpl.sample_period = 1_000_000_000 / sampling_rate.max(1);
+        }
+        track.sampling_rate = sampling_rate;
+        Ok(track)
+    }
 }
 
 
@@ -66,22 +154,170 @@ fn set_gain(ref_data_vec:&mut Vec<i64>, db:&f64) {
     }
 }
 
-fn wav_to_track( wav_path: &std::path::Path) -> Result<Track> {
+// A4 range of the command line and the keys
+const MIN_PITCH_STANDARD: f64 = 300.0;
+const MAX_PITCH_STANDARD: f64 = 600.0;
+
+// resonance-parrot [--a4 HZ] [--estimate-a4 SECONDS] [--rate HZ] [FILE]
+#[derive(Debug, PartialEq)]
+pub struct Args {
+    pub file_path: String,
+    pub pitch_standard_frequency: f64,
+    pub op_estimate_seconds: Option<f64>,   // A4 is moved by the offset from the nearest semitone of "pitch_standard_frequency"
+    pub op_sampling_rate: Option<usize>,    // the track is resampled to this rate
+}
+
+// "arg_iter" is without the program name.
+fn parse_args<I: Iterator<Item = String>>(mut arg_iter: I) -> Result<Args> {
+    let mut op_file_path: Option<String> = None;
+    let mut pitch_standard_frequency = 440.0;
+    let mut op_estimate_seconds: Option<f64> = None;
+    let mut op_sampling_rate: Option<usize> = None;
+    while let Some(arg) = arg_iter.next() {
+        // "--name value" or "--name=value"
+        let (name, op_value) = match arg.find('=') {
+            Some(pos) if arg.starts_with("--") => (arg[..pos].to_string(), Some(arg[pos+1..].to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| -> Result<f64> {
+            op_value.clone().or_else(|| arg_iter.next())
+                .and_then(|value| value.parse::<f64>().ok())
+                .ok_or_else(|| ResonanceParrotError::new(&format!("{} needs a number!", name)))
+        };
+        match name.as_str() {
+            "--a4" => {
+                pitch_standard_frequency = value("--a4")?;
+                if !(MIN_PITCH_STANDARD..=MAX_PITCH_STANDARD).contains(&pitch_standard_frequency) {
+                    return Err(ResonanceParrotError::new(&format!("A4 must be from {}Hz to {}Hz!", MIN_PITCH_STANDARD, MAX_PITCH_STANDARD)));
+                }
+            }
+            "--estimate-a4" => {
+                let seconds = value("--estimate-a4")?;
+                if !(seconds > 0.0 && seconds.is_finite()) {
+                    return Err(ResonanceParrotError::new("Seconds to estimate A4 must be positive!"));
+                }
+                op_estimate_seconds = Some(seconds);
+            }
+            "--rate" => {
+                let sampling_rate = value("--rate")?;
+                // Same range as WAV files to save the track
+                if !((MIN_SAMPLING_RATE as f64..=MAX_SAMPLING_RATE as f64).contains(&sampling_rate) && sampling_rate.fract() == 0.0) {
+                    return Err(ResonanceParrotError::new(&format!("Sampling rate must be an integer from {}Hz to {}Hz!", MIN_SAMPLING_RATE, MAX_SAMPLING_RATE)));
+                }
+                op_sampling_rate = Some(sampling_rate as usize);
+            }
+            _ if name.starts_with("--") => {
+                return Err(ResonanceParrotError::new(&format!("Unknown option {}!", name)));
+            }
+            _ if op_file_path.is_none() => {
+                op_file_path = Some(arg);
+            }
+            _ => {
+                return Err(ResonanceParrotError::new("Only one file can be opened!"));
+            }
+        }
+    }
+    Ok(Args {
+        file_path: op_file_path.unwrap_or_else(|| r"./test.wav".to_string()),
+        pitch_standard_frequency,
+        op_estimate_seconds,
+        op_sampling_rate,
+    })
+}
+
+// Display blocks per second
+const BLOCK_PER_SECOND: usize = 100;
+
+// One energy per display block
+fn new_resonance(ref_track: &Track, pitch_standard_frequency: f64) -> Result<Resonance> {
+    let config = ResonanceConfig { hop: (ref_track.sampling_rate / BLOCK_PER_SECOND).max(1), ..ResonanceConfig::default() };
+    Resonance::with_config(pitch_standard_frequency, ref_track.sampling_rate, ref_track.buffer.channel_num(), 3, &config)
+}
+
+// The format is chosen by the magic bytes. AIFF and FLAC are converted to WAV.
+fn open_base_file(file_path: &std::path::Path) -> Result<WavFile> {
+    let buf = std::fs::read(file_path)?;
+    match buf.get(0..4) {
+        Some(b"FORM") => {
+            let mut aiff_file = AiffFile::new();
+            aiff_file.open_bytes(buf)?;
+            Ok(aiff_file.to_wav_file()?)
+        }
+        Some(b"fLaC") | Some([b'I', b'D', b'3', _]) => {
+            let mut flac_file = FlacFile::new();
+            flac_file.open_bytes(buf)?;
+            Ok(flac_file.to_wav_file()?)
+        }
+        _ => {
+            let mut wav_file = WavFile::new();
+            wav_file.open_bytes(buf)?;
+            Ok(wav_file)
+        }
+    }
+}
+
+// WAV read block by block without loading the whole file.
+// The returned WavFile has only the chunks of tags, cue points and loops.
+fn open_wav_stream(file_path: &std::path::Path) -> Result<(WavFile, Fmt, AudioBuffer)> {
+    let mut wav_reader = WavReader::open(file_path)?;
+    let fmt = wav_reader.fmt().clone();
     let mut base_file = WavFile::new();
-    base_file.open(wav_path)?;
-    let base_wav_audio = base_file.get_wav_audio()?;
+    for name in [[b'L',b'I',b'S',b'T'], [b'c',b'u',b'e',b' '], [b's',b'm',b'p',b'l']].iter() {
+        base_file.sub_chunks.append(&mut wav_reader.read_sub_chunks(*name)?);
+    }
+    let mut ch_vec: Vec<Vec<f64>> = vec![Vec::new(); fmt.channel];
+    loop {
+        // A second in a block
+        let block_ch_vec = wav_reader.read_frames(fmt.sampling_rate)?;
+        if block_ch_vec[0].is_empty() {
+            break;
+        }
+        for (ch, block_ch) in ch_vec.iter_mut().zip(block_ch_vec) {
+            ch.extend(block_ch);
+        }
+    }
+    let buffer = AudioBuffer::new(ch_vec, fmt.sampling_rate)?;
+    Ok((base_file, fmt, buffer))
+}
+
+fn wav_to_track( wav_path: &std::path::Path) -> Result<Track> {
+    let (base_file, fmt, buffer) = match open_wav_stream(wav_path) {
+        Ok(opened) => opened,
+        // AIFF, FLAC and WAV to be repaired are loaded whole.
+        Err(_) => {
+            let base_file = open_base_file(wav_path)?;
+            let base_wav_audio = base_file.get_wav_audio()?;
+            let buffer = to_audio_buffer(&base_wav_audio)?;
+            (base_file, base_wav_audio.fmt, buffer)
+        }
+    };
+    let mut cue_vec = base_file.cue_points().unwrap_or_default();
+    cue_vec.sort_by_key(|cue| cue.position);
     Ok(Track{
         file_path: wav_path.to_path_buf(),
-        format_id : base_wav_audio.fmt.id,
-        channel : base_wav_audio.fmt.channel,
-        sampling_rate : base_wav_audio.fmt.sampling_rate,
-        bits : base_wav_audio.fmt.bits,
-        ch_vec : to_channel_vec(&base_wav_audio)?
+        format_id : fmt.id,
+        channel : fmt.channel,
+        sampling_rate : fmt.sampling_rate,
+        bits : fmt.bits,
+        valid_bits : fmt.valid_bits,
+        channel_mask : fmt.channel_mask,
+        // Broken tags do not prevent playing.
+        info_tags : base_file.info_tags().unwrap_or(None).unwrap_or_default(),
+        cue_vec : cue_vec,
+        op_smpl : base_file.smpl().unwrap_or(None),
+        buffer,
     })
 }
 
 fn resonance_parrot() -> Result<()> {
-    let base_track = wav_to_track(std::path::Path::new(r"./test.wav"))?;
+    let args = parse_args(std::env::args().skip(1))?;
+    let mut base_track = wav_to_track(std::path::Path::new(&args.file_path))?;
+    // Analysed (and saved) at "--rate"
+    if let Some(sampling_rate) = args.op_sampling_rate {
+        if sampling_rate != base_track.sampling_rate {
+            base_track = base_track.resample(sampling_rate)?;
+        }
+    }
     
     let (event_sender, event_receiver) = channel::<AppEvent>();
 
@@ -104,34 +340,103 @@ fn resonance_parrot() -> Result<()> {
         keyhit_thread(key_event, from_key_sender, to_key_receiver)
     );
 
-    to_display_sender.send(DisplayRequest::open(base_track.file_path.to_string_lossy().to_string(),base_track.sampling_rate,base_track.bits,base_track.ch_vec.len())?)?;
-    to_timeline_sender.send(TimelineRequest::open(base_track.ch_vec[0].len(), base_track.sampling_rate, base_track.sampling_rate/100))?;
-    let mut resonance = Resonance::new(440.0, base_track.sampling_rate, base_track.ch_vec.len(), 3)?;
+    let mut warning_vec: Vec<String> = Vec::new();
+    // Tuning of the first seconds. Silence is warned, and A4 is kept.
+    let mut pitch_standard_frequency = args.pitch_standard_frequency;
+    let mut op_tuning: Option<TuningEstimate> = None;
+    if let Some(seconds) = args.op_estimate_seconds {
+        let estimate_frame_num = (seconds * base_track.sampling_rate as f64) as usize;
+        match estimate_tuning(&base_track.buffer.view(0, estimate_frame_num), pitch_standard_frequency) {
+            Ok(tuning) => {
+                pitch_standard_frequency = tuning.pitch_standard_frequency;
+                op_tuning = Some(tuning);
+            }
+            Err(err) => warning_vec.push(format!("A4 is not estimated. {}", err)),
+        }
+    }
+    let mut resonance = new_resonance(&base_track, pitch_standard_frequency)?;
+    if resonance.reachable_spring_num() < resonance.grid().len() {
+        warning_vec.push(format!("{} and above are not calculated at this sampling rate.", resonance.grid().label(resonance.reachable_spring_num())));
+    }
+
+    let mut tag_vec: Vec<(String, String)> = Vec::new();
+    for (label, id) in [("Title", INFO_TITLE), ("Artist", INFO_ARTIST), ("Comment", INFO_COMMENT)].iter() {
+        if let Some(text) = base_track.info_tags.get(*id) {
+            tag_vec.push((label.to_string(), text.to_string()));
+        }
+    }
+
+    // Cue points as markers
+    let frame_num = base_track.buffer.frame_num();
+    let mut marker_vec: Vec<(usize, String)> = Vec::new();
+    for cue in base_track.cue_vec.iter().filter(|cue| cue.position < frame_num) {
+        let label = if let Some(label) = &cue.label {
+            label.clone()
+        }
+        else if let Some(region) = &cue.region {
+            region.text.clone()
+        }
+        else if let Some(note) = &cue.note {
+            note.clone()
+        }
+        else {
+            format!("#{}", cue.id)
+        };
+        marker_vec.push((cue.position, label));
+    }
+
+    // The first loop of "smpl" is played repeatedly.
+    let op_loop = base_track.op_smpl.as_ref()
+        .and_then(|smpl| smpl.loops.first())
+        .filter(|sample_loop| sample_loop.start < sample_loop.end && sample_loop.end < frame_num)
+        .map(|sample_loop| (sample_loop.start, sample_loop.end));
+    let op_unity_pitch = base_track.op_smpl.as_ref().map(|smpl| smpl.unity_pitch());
 
+    to_display_sender.send(DisplayRequest::open(InputInfo {
+        name: base_track.file_path.to_string_lossy().to_string(),
+        sampling_rate: base_track.sampling_rate,
+        bits: base_track.bits,
+        ch_num: base_track.buffer.channel_num(),
+        frame_num,
+        tag_vec,
+        marker_vec: marker_vec.clone(),
+        op_loop,
+        op_unity_pitch,
+        pitch_standard_frequency,
+        op_tuning,
+        warning_vec,
+    })?)?;
+    let marker_idx_vec: Vec<usize> = marker_vec.iter().map(|(marker_idx, _)| *marker_idx).collect();
+    to_timeline_sender.send(TimelineRequest::open(frame_num, base_track.sampling_rate, base_track.sampling_rate/BLOCK_PER_SECOND, marker_idx_vec, op_loop))?;
+
+    // Start of the block following the last processed one
+    let mut next_block_stt: usize = 0;
     loop {
         let event = event_receiver.recv()?;
         match event.thread_id {
             ThreadID::TimeCounter => {
                 let timeline_report = from_timeline_receiver.recv()?;
-                
-                // Temporary Process
-                let mut sound_vec:Vec<Vec<f64>> = Vec::with_capacity(base_track.ch_vec.len());
-                for (_ch_idx, ch) in base_track.ch_vec.iter().enumerate() {
-                    let data_stt = timeline_report.timeline.time_counter;
-                    let data_end;
-                    if timeline_report.timeline.time_counter + timeline_report.timeline.base.event_divisor < ch.len() {
-                        data_end = timeline_report.timeline.time_counter + timeline_report.timeline.base.event_divisor;
+                let timeline = &timeline_report.timeline;
+                match timeline_report.report_type {
+                    TimelineReportType::Periodical => {
+                        // View of the block (clamped at the loop end and the end of the track)
+                        let data_stt = timeline.time_counter;
+                        let sound_buffer = base_track.buffer.view(data_stt, timeline.block_end());
+                        let spectrum_frame = resonance.process_block(&sound_buffer)?;
+                        next_block_stt = timeline.next_block_stt;
+
+                        let spectrum_arc = Arc::new(spectrum_frame.energy_vec);
+                        to_display_sender.send(DisplayRequest::update_value(data_stt, sound_buffer, spectrum_arc))?;
                     }
-                    else{
-                        data_end = ch.len()
+                    TimelineReportType::ChangedBySelf => {
+                        // Springs ring on only into the next contiguous block. (Not after marker jumps, loop wraps and stop)
+                        if timeline.next_block_stt != next_block_stt {
+                            resonance.reset()?;
+                            next_block_stt = timeline.next_block_stt;
+                        }
                     }
-                    sound_vec.push(ch[data_stt..data_end].to_vec());
+                    TimelineReportType::Response => { /* Nothing to do */ }
                 }
-                let sound_arc = Arc::new(sound_vec);
-                let resonance_vec = resonance.resonance(sound_arc.clone())?;
-
-                let spectrum_arc = Arc::new(resonance_vec);
-                to_display_sender.send(DisplayRequest::update_value(timeline_report.timeline.time_counter, sound_arc, spectrum_arc))?;
             },
             ThreadID::KeyHit => {
                 let input_char = from_key_receiver.recv()?;
@@ -147,6 +452,18 @@ fn resonance_parrot() -> Result<()> {
                 if input_char == 'd' || input_char == 'D' {
                     // Fast Forword
                 }
+                if input_char == 'n' || input_char == 'N' {
+                    // Next Marker
+                    to_timeline_sender.send(TimelineRequest::next())?;
+                }
+                if input_char == 'p' || input_char == 'P' {
+                    // Previous Marker
+                    to_timeline_sender.send(TimelineRequest::prev())?;
+                }
+                if input_char == 'l' || input_char == 'L' {
+                    // Loop On/Off
+                    to_timeline_sender.send(TimelineRequest::toggle_loop())?;
+                }
                 if input_char == 'a' || input_char == 'A' {
                     // Rewind
                 }
@@ -164,6 +481,25 @@ fn resonance_parrot() -> Result<()> {
                     // Shift Range Low
                     to_display_sender.send(DisplayRequest::change_rel_range(-12))?;
                 }
+                if input_char == 'r' || input_char == 'R' || input_char == 'f' || input_char == 'F' {
+                    // A4 Up / Down to the next whole Hz
+                    let new_pitch_standard = if input_char == 'r' || input_char == 'R' {
+                        (pitch_standard_frequency + 1.0).floor().min(MAX_PITCH_STANDARD)
+                    }
+                    else {
+                        (pitch_standard_frequency - 1.0).ceil().max(MIN_PITCH_STANDARD)
+                    };
+                    if new_pitch_standard != pitch_standard_frequency {
+                        // The springs are retuned in their threads.
+                        resonance.set_pitch_standard(new_pitch_standard)?;
+                        pitch_standard_frequency = new_pitch_standard;
+                        to_display_sender.send(DisplayRequest::change_pitch_standard(pitch_standard_frequency))?;
+                    }
+                }
+                if input_char == 'v' || input_char == 'V' {
+                    // Change Channel View (All / Downmix / Single)
+                    to_display_sender.send(DisplayRequest::change_channel_view())?;
+                }
                 to_key_sender.send(KeyHitRequest::Continue)?;
             },
             _ => { /*None*/ }
@@ -207,10 +543,26 @@ fn resonance_parrot() -> Result<()> {
         channel: base_track.channel,
         sampling_rate: base_track.sampling_rate,
         bits: base_track.bits,
+        valid_bits: base_track.valid_bits,
+        channel_mask: base_track.channel_mask,
     };
-    let wav_audio = to_wav_audio(&base_track.ch_vec, &wav_audio_fmt)?;
+    let wav_audio = buffer_to_wav_audio(&base_track.buffer, &wav_audio_fmt)?;
     let mut new_file = WavFile::new();
     new_file.update_wav_audio(&wav_audio)?;
+    // Provenance of the output: Description is the source file name.
+    let mut bext = Bext::new();
+    bext.originator = "resonance-parrot".to_string();
+    if let Some(file_name) = base_track.file_path.file_name() {
+        bext.description = file_name.to_string_lossy().to_string();
+    }
+    new_file.set_bext(&bext)?;
+    let mut info_tags = base_track.info_tags.clone();
+    info_tags.set(INFO_SOFTWARE, "resonance-parrot");
+    new_file.set_info_tags(&info_tags)?;
+    new_file.set_cue_points(&base_track.cue_vec)?;
+    if let Some(smpl) = &base_track.op_smpl {
+        new_file.set_smpl(smpl)?;
+    }
     new_file.save_as(std::path::Path::new(r"./new.wav"))?;
     Ok(())
 }
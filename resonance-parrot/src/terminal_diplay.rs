@@ -5,7 +5,7 @@ use std::convert::TryFrom;
 
 use super::error::*;
 extern crate resonance;
-use resonance::{SPN_LABEL, SPN_NUM, SpnIdx};
+use resonance::{SPN_LABEL, SPN_NUM, SpnIdx, MIDI_NOTE_OF_A0, AudioBuffer, TuningEstimate, strongest_pitch};
 
 fn f64_to_u32(f64_val:f64) -> u32 {
     if f64_val < 0.0 {
@@ -26,54 +26,109 @@ fn f64_to_u32(f64_val:f64) -> u32 {
 enum DisplayRequestType {
     Open,
     ChangeRange,
+    ChangeChannelView,
+    ChangePitchStandard,
     UpdateValue,
     Close,
     Exit,
 }
 
+// Channels shown side by side at most. More channels are picked or downmixed.
+const MAX_SIDE_BY_SIDE_CH: usize = 2;
+
+#[derive(Clone)]
+#[derive(PartialEq)]
+enum ChannelView {
+    All,
+    Downmix,
+    Single(usize),
+}
+
+impl ChannelView {
+    fn initial(ch_num: usize) -> ChannelView {
+        if ch_num <= MAX_SIDE_BY_SIDE_CH {
+            ChannelView::All
+        }
+        else {
+            ChannelView::Downmix
+        }
+    }
+
+    // All -> Downmix -> Single(0) -> ... -> Single(ch_num-1) -> All
+    // ("All" is skipped when there are too many channels.)
+    fn next(&self, ch_num: usize) -> ChannelView {
+        if ch_num <= 1 {
+            return ChannelView::All;
+        }
+        match self {
+            ChannelView::All => ChannelView::Downmix,
+            ChannelView::Downmix => ChannelView::Single(0),
+            ChannelView::Single(ch_idx) if ch_idx + 1 < ch_num => ChannelView::Single(ch_idx + 1),
+            ChannelView::Single(_) => ChannelView::initial(ch_num),
+        }
+    }
+
+    fn ch_num(&self, ch_num: usize) -> usize {
+        match self {
+            ChannelView::All => ch_num,
+            _ => 1,
+        }
+    }
+}
+
 struct NoteRange {
     stt_idx: usize,
     end_idx: usize,
 }
 
-struct InputInfo {
-    name: String,
-    sampling_rate: usize,
-    bits: usize,
-    ch_num: usize,
+pub struct InputInfo {
+    pub name: String,
+    pub sampling_rate: usize,
+    pub bits: usize,
+    pub ch_num: usize,
+    pub frame_num: usize,
+    pub tag_vec: Vec<(String, String)>,     // (Label, Text)
+    pub marker_vec: Vec<(usize, String)>,   // (Time Idx, Label) sorted by Time Idx
+    pub op_loop: Option<(usize, usize)>,    // (Start Idx, End Idx) End is included
+    pub op_unity_pitch: Option<f64>,        // MIDI note number with fraction
+    pub pitch_standard_frequency: f64,      // A4 in Hz at the start
+    pub op_tuning: Option<TuningEstimate>,  // estimated from the first seconds
+    pub warning_vec: Vec<String>,
 }
 
 pub struct DisplayRequest {
     request_type: DisplayRequestType,
     time_idx: Option<usize>,
-    sound_vec_arc: Option<Arc<Vec<Vec<f64>>>>,
+    op_sound_buffer: Option<AudioBuffer>,
     spectrum_vec_arc: Option<Arc<Vec<Vec<Vec<f64>>>>>,
     abs_range: Option<NoteRange>,
     rel_range: Option<isize>,
     input_info: Option<InputInfo>,
+    pitch_standard: Option<f64>,
 }
 
 #[allow(dead_code)]
 impl DisplayRequest {
-    pub fn open( name: String, sampling_rate: usize, bits: usize, ch_num: usize) -> Result<DisplayRequest> {
+    pub fn open(input_info: InputInfo) -> Result<DisplayRequest> {
         Ok(DisplayRequest {
             request_type: DisplayRequestType::Open,
             time_idx: Some(0),
-            sound_vec_arc: None,
+            op_sound_buffer: None,
             spectrum_vec_arc: None,
             abs_range: Some(NoteRange{
                 stt_idx:SpnIdx::A2 as usize,
                 end_idx:SpnIdx::A5 as usize + 1
             }),
             rel_range: None,
-            input_info: Some(InputInfo{name: name, sampling_rate: sampling_rate, bits: bits, ch_num: ch_num}),
+            input_info: Some(input_info),
+            pitch_standard: None,
         })
     }
     pub fn change_abs_range(lowest_note: SpnIdx, highest_note: SpnIdx) -> Result<DisplayRequest> {
         Ok(DisplayRequest {
             request_type: DisplayRequestType::ChangeRange,
             time_idx: None,
-            sound_vec_arc: None,
+            op_sound_buffer: None,
             spectrum_vec_arc: None,
             abs_range: Some(NoteRange{
                 stt_idx:lowest_note as usize,
@@ -81,50 +136,79 @@ impl DisplayRequest {
             }),
             rel_range: None,
             input_info: None,
+            pitch_standard: None,
         })
     }
     pub fn change_rel_range(rel_range: isize) -> DisplayRequest {
         DisplayRequest {
             request_type: DisplayRequestType::ChangeRange,
             time_idx: None,
-            sound_vec_arc: None,
+            op_sound_buffer: None,
             spectrum_vec_arc: None,
             abs_range: None,
             rel_range: Some(rel_range),
             input_info: None,
+            pitch_standard: None,
+        }
+    }
+    pub fn change_channel_view() -> DisplayRequest {
+        DisplayRequest {
+            request_type: DisplayRequestType::ChangeChannelView,
+            time_idx: None,
+            op_sound_buffer: None,
+            spectrum_vec_arc: None,
+            abs_range: None,
+            rel_range: None,
+            input_info: None,
+            pitch_standard: None,
+        }
+    }
+    pub fn change_pitch_standard(pitch_standard_frequency: f64) -> DisplayRequest {
+        DisplayRequest {
+            request_type: DisplayRequestType::ChangePitchStandard,
+            time_idx: None,
+            op_sound_buffer: None,
+            spectrum_vec_arc: None,
+            abs_range: None,
+            rel_range: None,
+            input_info: None,
+            pitch_standard: Some(pitch_standard_frequency),
         }
     }
-    pub fn update_value(time_idx: usize, sound_vec_arc: Arc<Vec<Vec<f64>>>, spectrum_vec_arc: Arc<Vec<Vec<Vec<f64>>>>) -> DisplayRequest {
+    pub fn update_value(time_idx: usize, sound_buffer: AudioBuffer, spectrum_vec_arc: Arc<Vec<Vec<Vec<f64>>>>) -> DisplayRequest {
         DisplayRequest {
             request_type: DisplayRequestType::UpdateValue,
             time_idx: Some(time_idx),
-            sound_vec_arc: Some(sound_vec_arc),
+            op_sound_buffer: Some(sound_buffer),
             spectrum_vec_arc: Some(spectrum_vec_arc),
             abs_range: None,
             rel_range: None,
             input_info: None,
+            pitch_standard: None,
         }
     }
     pub fn close() -> DisplayRequest {
         DisplayRequest {
             request_type: DisplayRequestType::Close,
             time_idx: None,
-            sound_vec_arc: None,
+            op_sound_buffer: None,
             spectrum_vec_arc: None,
             abs_range: None,
             rel_range: None,
             input_info: None,
+            pitch_standard: None,
         }
     }
     pub fn exit() -> DisplayRequest {
         DisplayRequest {
             request_type: DisplayRequestType::Close,
             time_idx: None,
-            sound_vec_arc: None,
+            op_sound_buffer: None,
             spectrum_vec_arc: None,
             abs_range: None,
             rel_range: None,
             input_info: None,
+            pitch_standard: None,
         }
     }
 }
@@ -142,9 +226,12 @@ enum TerminalStatus {
 pub struct ContentsStatus {
     input_info: InputInfo,
     time_idx: usize,
+    op_pitch: Option<f64>,  // Detected pitch in SPN index
+    pitch_standard_frequency: f64,  // A4 of the springs in Hz
     vbar_meter_sound: Vec<VbarMeter>,
     vbar_meter_spectrum: Vec<Vec<VbarMeter>>, // ch<Vbar<VbarMeter>>
     range: NoteRange,
+    ch_view: ChannelView,
 }
 
 #[allow(dead_code)]
@@ -172,14 +259,17 @@ impl TerminalDisplay {
             string: String::with_capacity(2000),
             status: TerminalStatus::Closed,
             contents: ContentsStatus {
-                input_info: InputInfo{name: String::new(), sampling_rate: 0, bits: 0, ch_num: 0},
+                input_info: InputInfo{name: String::new(), sampling_rate: 0, bits: 0, ch_num: 0, frame_num: 0, tag_vec: Vec::new(), marker_vec: Vec::new(), op_loop: None, op_unity_pitch: None, pitch_standard_frequency: 0.0, op_tuning: None, warning_vec: Vec::new()},
                 time_idx: 0,
+                op_pitch: None,
+                pitch_standard_frequency: 0.0,
                 vbar_meter_sound: Vec::new(),
                 vbar_meter_spectrum: Vec::new(),
                 range: NoteRange {
                     stt_idx:SpnIdx::A3 as usize,
                     end_idx:SpnIdx::A4 as usize + 1
-                }
+                },
+                ch_view: ChannelView::All,
             }   
         })
     }
@@ -391,6 +481,69 @@ fn set_vbar_meter_ch_label(ch_idx: usize, ch_len: usize) -> String {
     }
 }
 
+fn set_vbar_meter_view_label(ch_view: &ChannelView, ch_idx: usize, ch_len: usize) -> String {
+    match ch_view {
+        ChannelView::All => set_vbar_meter_ch_label(ch_idx, ch_len),
+        ChannelView::Downmix => r"  Mix:".to_string(),
+        ChannelView::Single(ch_idx) => set_vbar_meter_ch_label(*ch_idx, ch_len),
+    }
+}
+
+fn max_abs<I: Iterator<Item = f64>>(data_iter: I) -> f64 {
+    let mut max = 0.0;
+    for data in data_iter {
+        if data.abs() > max {
+            max = data.abs();
+        }
+    }
+    max
+}
+
+// Max absolute value of each displayed channel. channel<data> -> channel<max>
+fn view_sound_max(ch_view: &ChannelView, sound_buffer: &AudioBuffer) -> Vec<f64> {
+    match ch_view {
+        ChannelView::All => {
+            sound_buffer.channels().map(|ch| max_abs(ch.iter().cloned())).collect()
+        }
+        ChannelView::Downmix => {
+            let ch_num = sound_buffer.channel_num() as f64;
+            let mix_iter = (0..sound_buffer.frame_num()).map(|data_idx| sound_buffer.channels().map(|ch| ch[data_idx]).sum::<f64>() / ch_num);
+            vec![max_abs(mix_iter)]
+        }
+        ChannelView::Single(ch_idx) => {
+            vec![max_abs(sound_buffer.channel(*ch_idx).iter().cloned())]
+        }
+    }
+}
+
+// Max energy of each displayed channel and frequency. channel<freq<data<energy>>> -> channel<freq<max>>
+// Freq Bar is Reversed
+fn view_spectrum_max(ch_view: &ChannelView, spectrum_vec: &[Vec<Vec<f64>>], range: &NoteRange) -> Vec<Vec<f64>> {
+    let mut ch_val_vec:Vec<Vec<f64>> = Vec::new();
+    match ch_view {
+        ChannelView::All => {
+            for ch in spectrum_vec {
+                ch_val_vec.push(ch[range.stt_idx..range.end_idx].iter().rev().map(|freq| max_abs(freq.iter().cloned())).collect());
+            }
+        }
+        ChannelView::Downmix => {
+            let ch_num = spectrum_vec.len() as f64;
+            let mut freq_val_vec: Vec<f64> = Vec::new();
+            for freq_idx in (range.stt_idx..range.end_idx).rev() {
+                let data_len = spectrum_vec[0][freq_idx].len();
+                let mix_iter = (0..data_len).map(|data_idx| spectrum_vec.iter().map(|ch| ch[freq_idx][data_idx]).sum::<f64>() / ch_num);
+                freq_val_vec.push(max_abs(mix_iter));
+            }
+            ch_val_vec.push(freq_val_vec);
+        }
+        ChannelView::Single(ch_idx) => {
+            let ch = &spectrum_vec[*ch_idx];
+            ch_val_vec.push(ch[range.stt_idx..range.end_idx].iter().rev().map(|freq| max_abs(freq.iter().cloned())).collect());
+        }
+    }
+    ch_val_vec
+}
+
 fn set_vbar_meter_spectrum_label(idx:usize, freq_len:usize, spn_label:&str) -> String {
     let bar_label = match idx {
         0 => r"\".to_string(),
@@ -402,21 +555,22 @@ fn set_vbar_meter_spectrum_label(idx:usize, freq_len:usize, spn_label:&str) -> S
 
 fn reset_vbar(terminal :&mut TerminalDisplay) -> Result<()>{
     // Sound
+    let view_ch_num = terminal.contents.ch_view.ch_num(terminal.contents.input_info.ch_num);
     terminal.contents.vbar_meter_sound = Vec::new();
-    for ch_idx in 0..terminal.contents.input_info.ch_num {
-        let vbar_label = set_vbar_meter_ch_label(ch_idx, terminal.contents.input_info.ch_num);
+    for ch_idx in 0..view_ch_num {
+        let vbar_label = set_vbar_meter_view_label(&terminal.contents.ch_view, ch_idx, terminal.contents.input_info.ch_num);
         let vbar_meter = VbarMeter::new(vbar_label, 10, 0.0, 1.0, 40, Some(0.6), Some(0.8))?;
         terminal.contents.vbar_meter_sound.push(vbar_meter);
     }
     // Spectrum
     let range = &terminal.contents.range;
     terminal.contents.vbar_meter_spectrum = Vec::new();
-    for _ in 0..terminal.contents.input_info.ch_num {
+    for _ in 0..view_ch_num {
         terminal.contents.vbar_meter_spectrum.push(Vec::new());
     }
 
     for label_idx in (0..range.end_idx-range.stt_idx).rev() { // Freq Bar is Reversed
-        for ch_idx in 0..terminal.contents.input_info.ch_num {
+        for ch_idx in 0..view_ch_num {
             let vbar_label = set_vbar_meter_spectrum_label(label_idx, range.end_idx-range.stt_idx, SPN_LABEL[range.stt_idx+label_idx]);
             let vbar_meter = VbarMeter::new(vbar_label, 10, 0.0, 0.005, 40, Some(0.6), Some(0.8))?;
             terminal.contents.vbar_meter_spectrum[ch_idx].push(vbar_meter);
@@ -425,13 +579,45 @@ fn reset_vbar(terminal :&mut TerminalDisplay) -> Result<()>{
     Ok(())
 }
 
+// Nearest note and the offset in cents. e.g. "A4 +12 cents"
+fn pitch_label(spn_pitch: f64) -> String {
+    let spn_idx = spn_pitch.round().max(0.0).min((SPN_NUM - 1) as f64);
+    format!("{} {:+.0} cents", SPN_LABEL[spn_idx as usize], (spn_pitch - spn_idx) * 100.0)
+}
+
+// Sum of all channels and data for each spring. channel<freq<data<energy>>>
+fn spectrum_sum(spectrum_vec: &[Vec<Vec<f64>>]) -> Vec<f64> {
+    let mut sum_vec = vec![0.0; spectrum_vec[0].len()];
+    for ch in spectrum_vec {
+        for (sum, freq) in sum_vec.iter_mut().zip(ch.iter()) {
+            *sum += freq.iter().sum::<f64>();
+        }
+    }
+    sum_vec
+}
+
 fn push_time_display(terminal :&mut TerminalDisplay) -> Result<()> {
     if terminal.contents.input_info.sampling_rate == 0 {
         return Err(ResonanceParrotError::new("Display SamplingRate is 0!"));
     }
     let time_idx = terminal.contents.time_idx;
     let sampling = terminal.contents.input_info.sampling_rate;
-    terminal.push_one_line(format!("  Time {:02}:{:02}.{:02}",time_idx/60/sampling, time_idx/sampling%60, time_idx*100/sampling%100));
+    // Last marker at or before the current time
+    let marker_label = match terminal.contents.input_info.marker_vec.iter().rev().find(|(marker_idx, _)| *marker_idx <= time_idx) {
+        Some((_, label)) => format!("  Marker:{}", label),
+        None => String::new(),
+    };
+    // Detected pitch against Unity Note of "smpl"
+    let pitch_text = match (terminal.contents.input_info.op_unity_pitch, terminal.contents.op_pitch) {
+        (Some(unity_pitch), Some(pitch)) => {
+            let unity_spn_pitch = unity_pitch - MIDI_NOTE_OF_A0 as f64;
+            format!("  Pitch:{} (Unity {:+.0} cents)", pitch_label(pitch), (pitch - unity_spn_pitch) * 100.0)
+        },
+        _ => String::new(),
+    };
+    // Erase the rest of the line for a shorter label
+    terminal.push_one_line(format!("  Time {:02}:{:02}.{:02}  A4:{:.1}Hz{}{}\u{001B}[0K",time_idx/60/sampling, time_idx/sampling%60, time_idx*100/sampling%100,
+        terminal.contents.pitch_standard_frequency, marker_label, pitch_text));
     Ok(())
 }
 
@@ -482,10 +668,35 @@ pub fn display_thread_main(to_display_receiver: Receiver<DisplayRequest>) ->  Re
                     return Err(ResonanceParrotError::new("Display Open with No Time Idx!"));
                 }
                 terminal.contents.time_idx = display_request.time_idx.unwrap();
+                terminal.contents.ch_view = ChannelView::initial(terminal.contents.input_info.ch_num);
+                terminal.contents.pitch_standard_frequency = terminal.contents.input_info.pitch_standard_frequency;
 
                 terminal.erase_display()?;
                 terminal.push_one_line(terminal.contents.input_info.name.clone());
-                terminal.push_one_line(format!("  Sampling Rate:{}  Bits/Sample:{}", terminal.contents.input_info.sampling_rate, terminal.contents.input_info.bits));
+                for (label, text) in terminal.contents.input_info.tag_vec.clone() {
+                    terminal.push_one_line(format!("  {}:{}", label, text));
+                }
+                let frame_num = terminal.contents.input_info.frame_num;
+                let sampling = terminal.contents.input_info.sampling_rate;
+                terminal.push_one_line(format!("  Sampling Rate:{}  Bits/Sample:{}  Channel:{}  Duration {:02}:{:02}.{:02}",
+                    sampling, terminal.contents.input_info.bits, terminal.contents.input_info.ch_num,
+                    frame_num/60/sampling, frame_num/sampling%60, frame_num*100/sampling%100));
+                if let Some(unity_pitch) = terminal.contents.input_info.op_unity_pitch {
+                    terminal.push_one_line(format!("  Unity Note:{}", pitch_label(unity_pitch - MIDI_NOTE_OF_A0 as f64)));
+                }
+                if let Some((loop_stt, loop_end)) = terminal.contents.input_info.op_loop {
+                    terminal.push_one_line(format!("  Loop {:02}:{:02}.{:02} - {:02}:{:02}.{:02}",
+                        loop_stt/60/sampling, loop_stt/sampling%60, loop_stt*100/sampling%100,
+                        loop_end/60/sampling, loop_end/sampling%60, loop_end*100/sampling%100));
+                }
+                if let Some(tuning) = terminal.contents.input_info.op_tuning {
+                    // Offset from the nearest semitone. A recording a semitone off looks in tune.
+                    terminal.push_one_line(format!("  Tuning:{:+.1} cents from the nearest semitone of A4={:.1}Hz (springs at A4={:.1}Hz)",
+                        tuning.cents_offset, tuning.reference_frequency, tuning.pitch_standard_frequency));
+                }
+                for warning in terminal.contents.input_info.warning_vec.clone() {
+                    terminal.push_one_line(format!("  Warning:{}", warning));
+                }
                 terminal.print_and_flush()?;
                 terminal.set_current_pos_as_home();
 
@@ -523,6 +734,32 @@ pub fn display_thread_main(to_display_receiver: Receiver<DisplayRequest>) ->  Re
                     print_blank_vbar(&mut terminal)?;
                 }
             }
+            DisplayRequestType::ChangeChannelView => {
+                if terminal.status == TerminalStatus::Closed {
+                    return Err(ResonanceParrotError::new("Display ChangeChannelView when Status is Closed!"));
+                }
+                terminal.contents.ch_view = terminal.contents.ch_view.next(terminal.contents.input_info.ch_num);
+                terminal.back_to_home_line()?;
+                terminal.erase_display_from_cusor_to_end()?;
+                reset_vbar(&mut terminal)?;
+                print_blank_vbar(&mut terminal)?;
+            }
+            DisplayRequestType::ChangePitchStandard => {
+                if terminal.status == TerminalStatus::Closed {
+                    return Err(ResonanceParrotError::new("Display ChangePitchStandard when Status is Closed!"));
+                }
+                if let Some(pitch_standard_frequency) = display_request.pitch_standard {
+                    terminal.contents.pitch_standard_frequency = pitch_standard_frequency;
+                }
+                else {
+                    return Err(ResonanceParrotError::new("Display ChangePitchStandard with No Frequency!"));
+                }
+                // Redraw the time line only.
+                terminal.back_to_home_line()?;
+                push_time_display(&mut terminal)?;
+                terminal.print_and_flush()?;
+                terminal.back_to_home_line()?;
+            }
             DisplayRequestType::UpdateValue => {
                 if terminal.status == TerminalStatus::Closed {
                     return Err(ResonanceParrotError::new("Display UpdateValue when Status is Closed!"));
@@ -533,7 +770,7 @@ pub fn display_thread_main(to_display_receiver: Receiver<DisplayRequest>) ->  Re
                     return Err(ResonanceParrotError::new("Display Open with No Time Idx!"));
                 }
                 terminal.contents.time_idx = display_request.time_idx.unwrap();
-                if display_request.sound_vec_arc.is_none() {
+                if display_request.op_sound_buffer.is_none() {
                     return Err(ResonanceParrotError::new("Display Open with No Sound Data!"));
                 }
                 if display_request.spectrum_vec_arc.is_none() {
@@ -541,44 +778,24 @@ pub fn display_thread_main(to_display_receiver: Receiver<DisplayRequest>) ->  Re
                 }
                 
                 // tmp value
-                let sound_vec_arc = display_request.sound_vec_arc.unwrap();
+                let sound_buffer = display_request.op_sound_buffer.unwrap();
                 let spectrum_vec_arc = display_request.spectrum_vec_arc.unwrap();
+                if terminal.contents.input_info.op_unity_pitch.is_some() {
+                    terminal.contents.op_pitch = strongest_pitch(&spectrum_sum(&spectrum_vec_arc));
+                }
 
                 terminal.back_to_home_line()?;
                 // Time Display
                 push_time_display(&mut terminal)?;
                 // Extract Max Value in Data Block
-                for (ch_idx, ch) in sound_vec_arc.iter().enumerate() {
-                    let mut max = 0.0;
-                    for data_idx in 0..ch.len() {
-                        let tmp = ch[data_idx].abs();
-                        if tmp > max {
-                            max = tmp;
-                        }
-                    }
+                for (ch_idx, max) in view_sound_max(&terminal.contents.ch_view, &sound_buffer).into_iter().enumerate() {
                     terminal.push_one_line(terminal.contents.vbar_meter_sound[ch_idx].set_value(max));
                 }
 
                 terminal.push_one_line("".to_string());
 
                 // channel<freq<data<energy>>>
-                let mut ch_val_vec:Vec<Vec<f64>> = Vec::new();
-                for ch in &*spectrum_vec_arc {
-                    let mut freq_val_vec: Vec<f64> = Vec::new();
-                    // Freq Bar is Reversed
-                    for freq in ch[terminal.contents.range.stt_idx..terminal.contents.range.end_idx].iter().rev() {
-                        let mut max = 0.0;
-                        for data_idx in 0..freq.len() {
-                            let tmp = freq[data_idx].abs();
-                            if tmp > max {
-                                max = tmp;
-                            }
-
-                        }
-                        freq_val_vec.push(max);
-                    }
-                    ch_val_vec.push(freq_val_vec);
-                }
+                let ch_val_vec = view_spectrum_max(&terminal.contents.ch_view, &spectrum_vec_arc, &terminal.contents.range);
                 for freq_idx in 0..ch_val_vec[0].len() {
                     for ch_idx in 0..ch_val_vec.len() {
                         if ch_idx < terminal.contents.vbar_meter_spectrum.len() - 1 {
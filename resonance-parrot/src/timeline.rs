@@ -7,6 +7,81 @@ use std::sync::mpsc::{Sender, Receiver};
 use super::error::*;
 use super::{ThreadID, AppEvent};
 
+#[cfg(test)]
+mod tests {
+    use super::{TimelineStatus, TimelineRequest, PlayStatus};
+
+    fn play(len: usize, marker_vec: Vec<usize>, op_loop: Option<(usize, usize)>) -> TimelineStatus {
+        let mut timeline = TimelineStatus::new(TimelineRequest::open(len, 1000, 10, marker_vec, op_loop)).unwrap();
+        timeline.update(TimelineRequest::play_or_pause()).unwrap();
+        timeline
+    }
+
+    // Periodical blocks while playing "count_num" counts
+    fn blocks(timeline: &mut TimelineStatus, count_num: usize) -> Vec<(usize, usize)> {
+        let mut block_vec = Vec::new();
+        for _ in 0..count_num {
+            if timeline.begin_block() {
+                block_vec.push((timeline.time_counter, timeline.block_end()));
+            }
+            timeline.advance();
+        }
+        block_vec
+    }
+
+    #[test]
+    fn test_timeline_blocks() {
+        let mut timeline = play(35, vec![], None);
+        assert_eq!(blocks(&mut timeline, 35), vec![(0, 10), (10, 20), (20, 30), (30, 35)]);
+        assert!(timeline.play_status == PlayStatus::Stop);
+        assert_eq!((timeline.time_counter, timeline.next_block_stt), (0, 0));
+
+        // Pause and play go on with the same block.
+        let mut timeline = play(35, vec![], None);
+        assert_eq!(blocks(&mut timeline, 4), vec![(0, 10)]);
+        timeline.update(TimelineRequest::play_or_pause()).unwrap();
+        assert_eq!(blocks(&mut timeline, 4), vec![]);
+        timeline.update(TimelineRequest::play_or_pause()).unwrap();
+        assert_eq!(blocks(&mut timeline, 10), vec![(10, 20)]);
+    }
+
+    #[test]
+    fn test_timeline_wrap_loop() {
+        // The block ending at the loop end is reported before going back.
+        let mut timeline = play(100, vec![], Some((5, 26)));
+        assert_eq!(blocks(&mut timeline, 50), vec![(0, 10), (10, 20), (20, 27), (5, 15), (15, 25), (25, 27), (5, 15)]);
+
+        let mut timeline = play(100, vec![], Some((5, 26)));
+        timeline.update(TimelineRequest::point(27)).unwrap();
+        assert!(timeline.wrap_loop());
+        assert_eq!((timeline.time_counter, timeline.next_block_stt), (5, 5));
+        timeline.update(TimelineRequest::toggle_loop()).unwrap();
+        timeline.update(TimelineRequest::point(27)).unwrap();
+        assert!(!timeline.wrap_loop());
+        assert_eq!(timeline.block_end(), 37);
+    }
+
+    #[test]
+    fn test_timeline_markers() {
+        let mut timeline = play(100, vec![33, 71], None);
+        assert_eq!(blocks(&mut timeline, 3), vec![(0, 10)]);
+        // Blocks start at the marker.
+        timeline.update(TimelineRequest::next()).unwrap();
+        assert_eq!(blocks(&mut timeline, 11), vec![(33, 43), (43, 53)]);
+        timeline.update(TimelineRequest::next()).unwrap();
+        assert_eq!((timeline.time_counter, timeline.next_block_stt), (71, 71));
+        // 33 was passed just before.
+        timeline.update(TimelineRequest::prev()).unwrap();
+        assert_eq!(timeline.time_counter, 33);
+        timeline.update(TimelineRequest::prev()).unwrap();
+        assert_eq!((timeline.time_counter, timeline.next_block_stt), (0, 0));
+        timeline.update(TimelineRequest::point(80)).unwrap();
+        timeline.update(TimelineRequest::next()).unwrap();
+        assert!(timeline.play_status == PlayStatus::Stop);
+        assert_eq!(timeline.time_counter, 0);
+    }
+}
+
 #[derive(Clone)]
 #[derive(PartialEq)]
 pub enum TimelineReportType {
@@ -28,7 +103,9 @@ pub enum PlayStatus {
 pub struct TimelineBase {
     pub len: usize,
     pub frequency: usize,
-    pub event_divisor: usize
+    pub event_divisor: usize,
+    pub marker_vec: Vec<usize>, // Sorted time counters to jump by Next/Prev
+    pub op_loop: Option<(usize, usize)>, // Loop start and end (included)
 }
 
 pub struct TimelineReport {
@@ -40,6 +117,8 @@ pub struct TimelineReport {
 pub struct TimelineStatus {
     pub play_status: PlayStatus,
     pub time_counter: usize,
+    pub next_block_stt: usize, // Time counter of the next Periodical report
+    pub loop_enabled: bool,
     pub base:TimelineBase
 }
 
@@ -48,16 +127,72 @@ impl TimelineStatus {
         if request.op_base.is_none() {
             return Err(ResonanceParrotError::new("All TimeLine Valueables needs to be set the first time!"));
         }
+        let base = request.op_base.unwrap();
         Ok(TimelineStatus {
             play_status: PlayStatus::Stop, 
             time_counter:0,
-            base: request.op_base.unwrap(),
+            next_block_stt: 0,
+            loop_enabled: base.op_loop.is_some(),
+            base,
         }) 
     }
 
+    // Blocks are reported from "time_counter" again.
+    fn jump(&mut self, time_counter: usize) {
+        self.time_counter = time_counter;
+        self.next_block_stt = time_counter;
+    }
+
     fn stop(&mut self) {
         self.play_status = PlayStatus::Stop;
-        self.time_counter = 0;
+        self.jump(0);
+    }
+
+    // Back to the loop start when the loop end is passed.
+    fn wrap_loop(&mut self) -> bool {
+        if let Some((loop_stt, loop_end)) = self.base.op_loop {
+            if self.loop_enabled && self.time_counter > loop_end {
+                self.jump(loop_stt);
+                return true;
+            }
+        }
+        false
+    }
+
+    // End of the block from "time_counter" (clamped at the loop end and the end of the track)
+    pub fn block_end(&self) -> usize {
+        let mut block_end = self.time_counter + self.base.event_divisor;
+        if let Some((_, loop_end)) = self.base.op_loop {
+            if self.loop_enabled && self.time_counter <= loop_end {
+                block_end = block_end.min(loop_end + 1);
+            }
+        }
+        block_end.min(self.base.len)
+    }
+
+    // Start of the next block to report as Periodical while playing.
+    fn begin_block(&mut self) -> bool {
+        if self.play_status == PlayStatus::Play && self.time_counter == self.next_block_stt {
+            self.next_block_stt = self.block_end();
+            return true;
+        }
+        false
+    }
+
+    // One count of playing. True when it jumps back to the loop start or stops at the end.
+    fn advance(&mut self) -> bool {
+        if self.play_status != PlayStatus::Play {
+            return false;
+        }
+        self.time_counter += 1;
+        if self.wrap_loop() {
+            return true;
+        }
+        if self.time_counter > self.base.len - 1 {
+            self.stop();
+            return true;
+        }
+        false
     }
 
     fn update(&mut self, request:TimelineRequest) -> Result<()> {
@@ -70,20 +205,29 @@ impl TimelineStatus {
                 }
             }
             TimelineRequestType::Stop => {
-                self.play_status = PlayStatus::Stop;
-                self.time_counter = 0;
+                self.stop();
             }
             TimelineRequestType::Next => {
-                self.play_status = PlayStatus::Stop;
-                self.time_counter = 0;
+                // Jump to the next marker. Stop at the end if there is none.
+                match self.base.marker_vec.iter().find(|marker| **marker > self.time_counter) {
+                    Some(marker) => { self.jump(*marker); }
+                    None => { self.stop(); }
+                }
             }
             TimelineRequestType::Prev => {
-                self.play_status = PlayStatus::Stop;
-                self.time_counter = 0;
+                // Jump to the previous marker. Markers just passed while playing are skipped.
+                let back_limit = self.time_counter.saturating_sub(self.base.event_divisor);
+                match self.base.marker_vec.iter().rev().find(|marker| **marker < back_limit) {
+                    Some(marker) => { self.jump(*marker); }
+                    None => { self.jump(0); }
+                }
+            }
+            TimelineRequestType::Loop => {
+                self.loop_enabled = !self.loop_enabled && self.base.op_loop.is_some();
             }
             TimelineRequestType::Point => {
                 if let Some(time_counter) = request.op_time_counter {
-                    self.time_counter = time_counter;
+                    self.jump(time_counter);
                 }
             }
             _ => {
@@ -106,6 +250,7 @@ pub enum TimelineRequestType {
     Stop,
     Next,
     Prev,
+    Loop,
     Point,
 }
 
@@ -117,14 +262,16 @@ pub struct TimelineRequest {
 
 #[allow(dead_code)]
 impl TimelineRequest {
-    pub fn open(len: usize, frequency: usize, event_divisor: usize) -> TimelineRequest {
+    pub fn open(len: usize, frequency: usize, event_divisor: usize, marker_vec: Vec<usize>, op_loop: Option<(usize, usize)>) -> TimelineRequest {
         TimelineRequest {
             request_type: TimelineRequestType::Open,
             op_time_counter: None,
             op_base:Some(TimelineBase {
                 len: len,
                 frequency: frequency,
-                event_divisor: event_divisor
+                event_divisor: event_divisor,
+                marker_vec,
+                op_loop
             })
         }
     }
@@ -149,6 +296,27 @@ impl TimelineRequest {
             op_base:None
         }
     }
+    pub fn next() -> TimelineRequest {
+        TimelineRequest {
+            request_type: TimelineRequestType::Next,
+            op_time_counter: None,
+            op_base:None
+        }
+    }
+    pub fn prev() -> TimelineRequest {
+        TimelineRequest {
+            request_type: TimelineRequestType::Prev,
+            op_time_counter: None,
+            op_base:None
+        }
+    }
+    pub fn toggle_loop() -> TimelineRequest {
+        TimelineRequest {
+            request_type: TimelineRequestType::Loop,
+            op_time_counter: None,
+            op_base:None
+        }
+    }
     pub fn point(counter: usize) -> TimelineRequest {
         TimelineRequest {
             request_type: TimelineRequestType::Point,
@@ -250,18 +418,12 @@ impl TimeLine {
                     self.freq_slice = FrequencySlice::new(self.timeline.time_counter);
                 }
                 Err(_) => {
-                    if self.timeline.play_status == PlayStatus::Play {
-                        self.timeline.time_counter += 1;
-                        //if self.timeline.time_counter % (self.timeline.base.event_divisor * 100) == 0{
-                        //    println!("time_counter {}", self.timeline.time_counter);
-                        //}
-                        if self.timeline.time_counter % self.timeline.base.event_divisor == 0{
-                            self.send(TimelineReportType::Periodical)?;
-                        }
-                        if self.timeline.time_counter > self.timeline.base.len - 1 {
-                            self.timeline.stop();
-                            self.send(TimelineReportType::ChangedBySelf)?;
-                        }
+                    if self.timeline.begin_block() {
+                        self.send(TimelineReportType::Periodical)?;
+                    }
+                    if self.timeline.advance() {
+                        self.freq_slice = FrequencySlice::new(self.timeline.time_counter);
+                        self.send(TimelineReportType::ChangedBySelf)?;
                     }
                 }
             }
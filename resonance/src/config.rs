@@ -0,0 +1,105 @@
+use std::f64::consts::PI;
+
+use super::error::*;
+use super::Integrator;
+
+#[cfg(test)]
+mod tests {
+    use super::{ResonanceConfig, Damping, DAMPING_COEFFICIENT};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_damping_vec() {
+        let hz_vec = [110.0, 440.0, 1760.0];
+        assert_eq!(ResonanceConfig::default().damping_vec(&hz_vec).unwrap(), vec![DAMPING_COEFFICIENT; 3]);
+
+        // Same bandwidth in semitones
+        let damping_vec = ResonanceConfig::new(Damping::ConstantQ(20.0)).damping_vec(&hz_vec).unwrap();
+        for (damping, hz) in damping_vec.iter().zip(hz_vec.iter()) {
+            assert!((damping - 2.0 * PI * hz / 20.0).abs() < 1e-9);
+        }
+        let damping_vec = ResonanceConfig::new(Damping::BandwidthCents(100.0)).damping_vec(&hz_vec).unwrap();
+        let bandwidth_hz = 440.0 * (2.0_f64.powf(1.0 / 24.0) - 2.0_f64.powf(-1.0 / 24.0));
+        assert!((damping_vec[1] - 2.0 * PI * bandwidth_hz).abs() < 1e-9);
+        assert!((damping_vec[2] / damping_vec[0] - 16.0).abs() < 1e-9);
+
+        assert_eq!(ResonanceConfig::new(Damping::PerSpring(vec![0.0, 1.0, 2.0])).damping_vec(&hz_vec).unwrap(), vec![0.0, 1.0, 2.0]);
+        assert!(ResonanceConfig::new(Damping::PerSpring(vec![1.0; 4])).damping_vec(&hz_vec).is_err());
+        assert!(ResonanceConfig::new(Damping::ConstantQ(0.0)).damping_vec(&hz_vec).is_err());
+        assert!(ResonanceConfig::new(Damping::Coefficient(-1.0)).damping_vec(&hz_vec).is_err());
+    }
+}
+
+// Damping coefficient of Damping::default()
+pub const DAMPING_COEFFICIENT: f64 = 100.0;
+
+// Damping "c" of each spring: x'' = force - k*x - c*x'
+// The -3dB bandwidth of a spring is c/(2*PI) Hz. Narrower bandwidth rings longer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Damping {
+    Coefficient(f64),       // same "c" for every spring (same bandwidth in Hz)
+    ConstantQ(f64),         // Q = omega/c (same bandwidth in semitones)
+    BandwidthCents(f64),    // -3dB bandwidth in cents around each spring frequency
+    PerSpring(Vec<f64>),    // "c" of each spring in FrequencyGrid
+}
+
+impl Default for Damping {
+    fn default() -> Damping {
+        Damping::Coefficient(DAMPING_COEFFICIENT)
+    }
+}
+
+// Analysis settings of Resonance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResonanceConfig {
+    pub damping: Damping,
+    pub integrator: Integrator,
+    pub hop: usize,     // samples per output energy (the max in the hop). 1: every sample
+}
+
+impl Default for ResonanceConfig {
+    fn default() -> ResonanceConfig {
+        ResonanceConfig::new(Damping::default())
+    }
+}
+
+impl ResonanceConfig {
+    pub fn new(damping: Damping) -> ResonanceConfig {
+        ResonanceConfig { damping, integrator: Integrator::default(), hop: 1 }
+    }
+
+    // "c" of each spring frequency from the lowest spring.
+    pub fn damping_vec(&self, hz_vec: &[f64]) -> Result<Vec<f64>> {
+        // -- Check Parameter --
+        let check = |val: f64, zero_ok: bool, message: &str| -> Result<()> {
+            if !val.is_finite() || val < 0.0 || (!zero_ok && val == 0.0) {
+                return Err(ResonanceError::new(message));
+            }
+            Ok(())
+        };
+        match &self.damping {
+            Damping::Coefficient(damping) => {
+                check(*damping, true, "Damping coefficient must be 0 or positive!")?;
+                Ok(vec![*damping; hz_vec.len()])
+            }
+            Damping::ConstantQ(q) => {
+                check(*q, false, "Q factor must be positive!")?;
+                Ok(hz_vec.iter().map(|hz| 2.0 * PI * hz / q).collect())
+            }
+            Damping::BandwidthCents(cents) => {
+                check(*cents, false, "Bandwidth must be positive!")?;
+                let ratio = 2.0_f64.powf(cents / 2400.0) - 2.0_f64.powf(-cents / 2400.0);
+                Ok(hz_vec.iter().map(|hz| 2.0 * PI * hz * ratio).collect())
+            }
+            Damping::PerSpring(damping_vec) => {
+                if damping_vec.len() != hz_vec.len() {
+                    return Err(ResonanceError::new("Damping must be given for every spring!"));
+                }
+                for damping in damping_vec {
+                    check(*damping, true, "Damping coefficient must be 0 or positive!")?;
+                }
+                Ok(damping_vec.clone())
+            }
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use std::convert::TryFrom;
+use std::path::Path;
+
+use super::error::*;
+use super::{SPN_LABEL, LOWEST_PITCH_IDX, HIGHEST_PITCH_IDX};
+
+#[cfg(test)]
+mod tests {
+    use super::{FrequencyGrid, note_label};
+    use super::super::{SPN_LABEL, SPN_NUM};
+
+    fn assert_hz(hz: f64, expected: f64) {
+        assert!((hz - expected).abs() < 1e-6 * expected, "{} {}", hz, expected);
+    }
+
+    #[test]
+    fn test_spn_and_edo() {
+        let grid = FrequencyGrid::spn(440.0);
+        assert_eq!(grid.len(), SPN_NUM);
+        assert_hz(grid.hz(0), 27.5);
+        assert_eq!(grid.label(SPN_NUM - 1), SPN_LABEL[SPN_NUM - 1]);
+        assert_eq!(FrequencyGrid::edo(440.0, 12, 0.0, 27.5, 7040.0).unwrap().hz_vec(), grid.hz_vec());
+
+        // Quarter tones 10 cents up from A4 to A5
+        let grid = FrequencyGrid::edo(440.0, 24, 10.0, 440.0, 880.0).unwrap();
+        assert_eq!(grid.len(), 24);
+        assert_hz(grid.hz(0), 440.0 * 2.0_f64.powf(10.0 / 1200.0));
+        assert_hz(grid.hz(1), 440.0 * 2.0_f64.powf(60.0 / 1200.0));
+        assert_eq!((grid.label(0), grid.label(1)), ("A4+10", "A#4-40"));
+        assert!(FrequencyGrid::edo(440.0, 0, 0.0, 27.5, 7040.0).is_err());
+        assert!(FrequencyGrid::edo(440.0, 12, 0.0, 100.0, 101.0).is_err());
+
+        assert_eq!(note_label(261.6255653, 440.0), "C4");
+        assert_eq!(note_label(27.5 * 2.0_f64.powf(-0.2 / 12.0), 440.0), "A0-20");
+    }
+
+    #[test]
+    fn test_scala() {
+        let scl = "! meantone.scl\n!\nQuarter-comma meantone (partial)\n 4\n!\n 193.157\n 5/4\n 696.578 fifth\n 2/1\n";
+        // Without "kbm": degree 0 at middle C (261.63Hz)
+        let grid = FrequencyGrid::scala(scl, None).unwrap();
+        let c4 = 440.0 * 2.0_f64.powf(-9.0 / 12.0);
+        let c4_idx = grid.hz_vec().iter().position(|hz| (hz - c4).abs() < 1e-6).unwrap();
+        assert_hz(grid.hz(c4_idx + 2), c4 * 1.25);
+        assert_hz(grid.hz(c4_idx + 4), c4 * 2.0);
+        assert_hz(grid.hz(c4_idx - 1), c4 / 2.0 * 2.0_f64.powf(696.578 / 1200.0));
+
+        // "kbm": C, D, E and G keys from C4 to C5, G4 at 392Hz
+        let kbm = "! cdeg.kbm\n12\n60\n72\n60\n67\n392.0\n4\n! mapping\n0\nx\n1\nx\n2\nx\nx\n3\nx\nx\nx\nx\n";
+        let grid = FrequencyGrid::scala(scl, Some(kbm)).unwrap();
+        assert_eq!(grid.len(), 5);
+        assert_hz(grid.hz(3), 392.0);
+        let c4 = 392.0 / 2.0_f64.powf(696.578 / 1200.0);
+        assert_hz(grid.hz(0), c4);
+        assert_hz(grid.hz(2), c4 * 1.25);
+        assert_hz(grid.hz(4), c4 * 2.0);
+
+        // Unordered degrees and a degree past the period, with a kbm repeating degree 0
+        let unordered_scl = "Unordered\n 4\n 5/4\n 9/8\n 5/2\n 2/1\n";
+        let kbm = "4\n60\n67\n60\n60\n100.0\n4\n0\n3\n0\n2\n";
+        let grid = FrequencyGrid::scala(unordered_scl, Some(kbm)).unwrap();
+        let hz_vec: Vec<f64> = [1.0, 1.125, 2.0, 2.25, 2.5, 5.0].iter().map(|ratio| 100.0 * ratio).collect();
+        assert_eq!(grid.len(), hz_vec.len());
+        for (hz, expected) in grid.hz_vec().iter().zip(hz_vec.iter()) {
+            assert_hz(*hz, *expected);
+        }
+
+        // Keys out of MIDI or fractional, octave degree 0 and a huge map
+        for kbm in ["12\n0\n200\n60\n69\n440.0\n12\n", "12\n0\n127\n60.5\n69\n440.0\n12\n", "12\n-1\n127\n60\n69\n440.0\n12\n", "12\n0\n127\n60\n69\n440.0\n0\n", "4000000000\n0\n127\n60\n69\n440.0\n12\n"].iter() {
+            assert!(FrequencyGrid::scala(scl, Some(kbm)).is_err(), "{}", kbm);
+        }
+        // Linear mapping over all keys
+        assert_eq!(FrequencyGrid::scala(scl, Some("0\n0\n127\n60\n69\n440.0\n0\n")).unwrap().len(), 128);
+
+        assert!(FrequencyGrid::scala("Broken\n 2\n 100.0\n", None).is_err());
+        assert!(FrequencyGrid::scala("Huge\n 4000000000\n 100.0\n", None).is_err());
+        assert!(FrequencyGrid::scala("Zero\n 1\n 0/1\n", None).is_err());
+    }
+
+    #[test]
+    fn test_hz_list() {
+        let grid = FrequencyGrid::from_hz(vec![100.0, 150.0, 200.0]).unwrap();
+        assert_eq!(grid.len(), 3);
+        assert_eq!(grid.label(1), "D3+37");
+        let grid = grid.with_labels(vec!["Sa".to_string(), "Pa".to_string(), "Sa'".to_string()]).unwrap();
+        assert_eq!(grid.label(1), "Pa");
+        assert!(grid.clone().with_labels(Vec::new()).is_err());
+        assert!(FrequencyGrid::from_hz(vec![200.0, 100.0]).is_err());
+        assert!(FrequencyGrid::from_hz(Vec::new()).is_err());
+    }
+}
+
+// MIDI note number of A4, the default "kbm" keyboard range (A0 to A8) and the highest key of "kbm"
+const MIDI_NOTE_OF_A4: i32 = 69;
+const MIDI_NOTE_OF_MIDDLE_C: i32 = 60;
+const LOWEST_KEY: i32 = 21;
+const HIGHEST_KEY: i32 = 117;
+const HIGHEST_MIDI_KEY: i32 = 127;
+
+const NOTE_NAME: [&str; 12] = ["A", "A#", "B", "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#"];
+
+// Frequencies of the springs from low to high, with a label for each.
+// Spring index of Resonance is the index in the grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrequencyGrid {
+    hz_vec: Vec<f64>,
+    label_vec: Vec<String>,
+}
+
+impl FrequencyGrid {
+    // 12-TET from A0 to A8 (SPN_NUM springs, SpnIdx and SPN_LABEL).
+    pub fn spn(pitch_standard_frequency: f64) -> FrequencyGrid {
+        let hz_vec = (LOWEST_PITCH_IDX..=HIGHEST_PITCH_IDX).map(|pitch_idx| pitch_standard_frequency * 2.0_f64.powf(pitch_idx as f64 / 12.0)).collect();
+        let label_vec = SPN_LABEL.iter().map(|label| label.to_string()).collect();
+        FrequencyGrid { hz_vec, label_vec }
+    }
+
+    // "steps_per_octave" equal steps through A4 moved by "cents_offset", from "lowest_hz" to "highest_hz".
+    // e.g. 24: quarter tones, 120: 10 cents resolution
+    pub fn edo(pitch_standard_frequency: f64, steps_per_octave: usize, cents_offset: f64, lowest_hz: f64, highest_hz: f64) -> Result<FrequencyGrid> {
+        // -- Check Parameter --
+        if steps_per_octave == 0 {
+            return Err(ResonanceError::new("Steps per octave must not be 0!"));
+        }
+        if !(pitch_standard_frequency > 0.0 && lowest_hz > 0.0 && lowest_hz <= highest_hz && highest_hz.is_finite() && cents_offset.is_finite()) {
+            return Err(ResonanceError::new("Frequency range is not valid!"));
+        }
+        let steps = f64::from(u32::try_from(steps_per_octave)?);
+        let base_hz = pitch_standard_frequency * 2.0_f64.powf(cents_offset / 1200.0);
+        // Allow rounding error at both ends.
+        let step_hz = |step: f64| base_hz * 2.0_f64.powf(step / steps);
+        let lowest_step = ((lowest_hz / base_hz).log2() * steps - 1e-9).ceil();
+        let highest_step = ((highest_hz / base_hz).log2() * steps + 1e-9).floor();
+        let mut hz_vec: Vec<f64> = Vec::new();
+        let mut step = lowest_step;
+        while step <= highest_step {
+            hz_vec.push(step_hz(step));
+            step += 1.0;
+        }
+        FrequencyGrid::with_note_labels(hz_vec, pitch_standard_frequency)
+    }
+
+    // Scala scale ("scl" contents) mapped to keys by "kbm" contents.
+    // Without "kbm", degree 0 is middle C of 12-TET at 440Hz, and keys from A0 to A8 are mapped linearly.
+    pub fn scala(scl: &str, op_kbm: Option<&str>) -> Result<FrequencyGrid> {
+        let scale = get_scl(scl)?;
+        let keyboard = match op_kbm {
+            Some(kbm) => get_kbm(kbm)?,
+            None => KeyboardMap {
+                first_key: LOWEST_KEY,
+                last_key: HIGHEST_KEY,
+                middle_key: MIDI_NOTE_OF_MIDDLE_C,
+                reference_key: MIDI_NOTE_OF_MIDDLE_C,
+                reference_hz: 440.0 * 2.0_f64.powf(f64::from(MIDI_NOTE_OF_MIDDLE_C - MIDI_NOTE_OF_A4) / 12.0),
+                octave_degree: scale.len(),
+                op_degree_vec: Vec::new(),
+            },
+        };
+        let reference_ratio = keyboard.key_ratio(keyboard.reference_key, &scale).ok_or_else(|| ResonanceError::new("Reference key of kbm is not mapped!"))?;
+        let mut hz_vec: Vec<f64> = Vec::new();
+        for key in keyboard.first_key..=keyboard.last_key {
+            if let Some(ratio) = keyboard.key_ratio(key, &scale) {
+                hz_vec.push(keyboard.reference_hz * ratio / reference_ratio);
+            }
+        }
+        // Degrees may be unordered or past the period, and keys may share a degree.
+        hz_vec.sort_by(|hz, other_hz| hz.partial_cmp(other_hz).unwrap_or(std::cmp::Ordering::Equal));
+        hz_vec.dedup_by(|hz, lower_hz| (*hz - *lower_hz).abs() <= 1e-9 * *lower_hz);
+        FrequencyGrid::with_note_labels(hz_vec, 440.0)
+    }
+
+    // scala() of ".scl" and ".kbm" files.
+    pub fn open_scala(scl_path: &Path, op_kbm_path: Option<&Path>) -> Result<FrequencyGrid> {
+        let scl = std::fs::read_to_string(scl_path)?;
+        let op_kbm = match op_kbm_path {
+            Some(kbm_path) => Some(std::fs::read_to_string(kbm_path)?),
+            None => None,
+        };
+        FrequencyGrid::scala(&scl, op_kbm.as_deref())
+    }
+
+    // Explicit frequencies in ascending order.
+    pub fn from_hz(hz_vec: Vec<f64>) -> Result<FrequencyGrid> {
+        FrequencyGrid::with_note_labels(hz_vec, 440.0)
+    }
+
+    // Replace the generated labels.
+    pub fn with_labels(self, label_vec: Vec<String>) -> Result<FrequencyGrid> {
+        if label_vec.len() != self.hz_vec.len() {
+            return Err(ResonanceError::new("Label must be given for every spring!"));
+        }
+        Ok(FrequencyGrid { hz_vec: self.hz_vec, label_vec })
+    }
+
+    pub fn len(&self) -> usize {
+        self.hz_vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hz_vec.is_empty()
+    }
+
+    pub fn hz(&self, spring_idx: usize) -> f64 {
+        self.hz_vec[spring_idx]
+    }
+
+    pub fn hz_vec(&self) -> &[f64] {
+        &self.hz_vec
+    }
+
+    pub fn label(&self, spring_idx: usize) -> &str {
+        &self.label_vec[spring_idx]
+    }
+
+    pub fn label_vec(&self) -> &[String] {
+        &self.label_vec
+    }
+
+    // Labelled by the nearest 12-TET note of "pitch_standard_frequency" and cents from it.
+    fn with_note_labels(hz_vec: Vec<f64>, pitch_standard_frequency: f64) -> Result<FrequencyGrid> {
+        // -- Check Parameter --
+        if hz_vec.is_empty() {
+            return Err(ResonanceError::new("Frequency grid has no spring!"));
+        }
+        for (idx, hz) in hz_vec.iter().enumerate() {
+            if !(hz.is_finite() && *hz > 0.0) || (idx > 0 && *hz <= hz_vec[idx - 1]) {
+                return Err(ResonanceError::new("Frequencies must be positive and ascending!"));
+            }
+        }
+        let label_vec = hz_vec.iter().map(|hz| note_label(*hz, pitch_standard_frequency)).collect();
+        Ok(FrequencyGrid { hz_vec, label_vec })
+    }
+}
+
+// e.g. "A4", "C#5+25", "D3-10"
+fn note_label(hz: f64, pitch_standard_frequency: f64) -> String {
+    let semitone = 12.0 * (hz / pitch_standard_frequency).log2();
+    // A quarter tone is "+50" of the lower note.
+    let note = (semitone + 0.5 - 1e-6).floor();
+    let cents = ((semitone - note) * 100.0).round() as i32;
+    let note = note as i32;
+    let octave = (MIDI_NOTE_OF_A4 + note).div_euclid(12) - 1;
+    let name = NOTE_NAME[note.rem_euclid(12) as usize];
+    if cents == 0 {
+        format!("{}{}", name, octave)
+    }
+    else {
+        format!("{}{}{:+}", name, octave, cents)
+    }
+}
+
+// Ratio of each degree from 1/1. The last is the period (usually 2/1).
+fn get_scl(scl: &str) -> Result<Vec<f64>> {
+    let mut line_iter = scl.lines().filter(|line| !line.starts_with('!'));
+    // Description
+    line_iter.next().ok_or_else(|| ResonanceError::new("scl has no description line!"))?;
+    let note_num: usize = line_iter.next()
+        .and_then(|line| line.split_whitespace().next())
+        .and_then(|word| word.parse().ok())
+        .ok_or_else(|| ResonanceError::new("scl has no number of notes!"))?;
+    // "note_num" is not trusted for allocation.
+    let mut ratio_vec: Vec<f64> = Vec::new();
+    for _ in 0..note_num {
+        let word = line_iter.next()
+            .and_then(|line| line.split_whitespace().next())
+            .ok_or_else(|| ResonanceError::new("scl has less notes than the number!"))?;
+        let op_ratio = if word.contains('.') {
+            // Cents
+            word.parse::<f64>().ok().map(|cents| 2.0_f64.powf(cents / 1200.0))
+        }
+        else {
+            // Ratio "n/d" or integer "n"
+            let mut part_iter = word.splitn(2, '/');
+            let numerator = part_iter.next().and_then(|part| part.parse::<f64>().ok());
+            let denominator = part_iter.next().map_or(Some(1.0), |part| part.parse::<f64>().ok());
+            numerator.zip(denominator).map(|(numerator, denominator)| numerator / denominator)
+        };
+        match op_ratio {
+            Some(ratio) if ratio.is_finite() && ratio > 0.0 => ratio_vec.push(ratio),
+            _ => return Err(ResonanceError::new(&format!("scl note is not valid! \"{}\"", word))),
+        }
+    }
+    if ratio_vec.is_empty() {
+        return Err(ResonanceError::new("scl has no notes!"));
+    }
+    Ok(ratio_vec)
+}
+
+// Keyboard mapping of "kbm".
+struct KeyboardMap {
+    first_key: i32,
+    last_key: i32,
+    middle_key: i32,            // key of degree 0
+    reference_key: i32,
+    reference_hz: f64,
+    octave_degree: usize,       // degree of the formal octave
+    op_degree_vec: Vec<Option<usize>>,  // empty: linear mapping, None: not mapped
+}
+
+impl KeyboardMap {
+    // Ratio of "key" from the middle key. None if the key is not mapped.
+    fn key_ratio(&self, key: i32, ref_scale: &[f64]) -> Option<f64> {
+        let degree_ratio = |degree: i64| -> f64 {
+            let len = ref_scale.len() as i64;
+            let period = ref_scale[ref_scale.len() - 1];
+            let step = degree.rem_euclid(len) as usize;
+            let step_ratio = if step == 0 { 1.0 } else { ref_scale[step - 1] };
+            step_ratio * period.powi(i32::try_from(degree.div_euclid(len)).unwrap_or(i32::MAX))
+        };
+        let offset = i64::from(key - self.middle_key);
+        if self.op_degree_vec.is_empty() {
+            return Some(degree_ratio(offset));
+        }
+        let map_size = self.op_degree_vec.len() as i64;
+        let degree = self.op_degree_vec[offset.rem_euclid(map_size) as usize]?;
+        let octave_ratio = degree_ratio(self.octave_degree as i64);
+        Some(degree_ratio(degree as i64) * octave_ratio.powi(i32::try_from(offset.div_euclid(map_size)).unwrap_or(i32::MAX)))
+    }
+}
+
+fn get_kbm(kbm: &str) -> Result<KeyboardMap> {
+    let mut word_iter = kbm.lines()
+        .filter(|line| !line.starts_with('!'))
+        .filter_map(|line| line.split_whitespace().next());
+    let mut next_number = |name: &str| -> Result<f64> {
+        word_iter.next()
+            .and_then(|word| if word == "x" { Some(-1.0) } else { word.parse::<f64>().ok() })
+            .ok_or_else(|| ResonanceError::new(&format!("kbm has no valid {}!", name)))
+    };
+    let map_size = next_number("map size")?;
+    let key_vec = ["first key", "last key", "middle key", "reference key"].iter().map(|name| next_number(name)).collect::<Result<Vec<f64>>>()?;
+    let reference_hz = next_number("reference frequency")?;
+    let octave_degree = next_number("octave degree")?;
+    // -- Check Parameter --
+    if !key_vec.iter().all(|key| (0.0..=f64::from(HIGHEST_MIDI_KEY)).contains(key) && key.fract() == 0.0) {
+        return Err(ResonanceError::new(&format!("kbm keys must be MIDI note numbers from 0 to {}!", HIGHEST_MIDI_KEY)));
+    }
+    if !(0.0..=f64::from(HIGHEST_MIDI_KEY + 1)).contains(&map_size) {
+        return Err(ResonanceError::new(&format!("kbm map size must be up to {}!", HIGHEST_MIDI_KEY + 1)));
+    }
+    // The formal octave repeats the mapping.
+    if map_size >= 1.0 && octave_degree < 1.0 {
+        return Err(ResonanceError::new("kbm octave degree must not be 0!"));
+    }
+    let (first_key, last_key, middle_key, reference_key) = (key_vec[0] as i32, key_vec[1] as i32, key_vec[2] as i32, key_vec[3] as i32);
+    let mut op_degree_vec: Vec<Option<usize>> = Vec::new();
+    for _ in 0..(map_size as usize) {
+        // Missing entries at the end are not mapped.
+        let degree = next_number("mapping").unwrap_or(-1.0);
+        op_degree_vec.push(if degree < 0.0 { None } else { Some(degree as usize) });
+    }
+    if !(reference_hz > 0.0 && reference_hz.is_finite()) || first_key > last_key || octave_degree < 0.0 {
+        return Err(ResonanceError::new("kbm is not valid!"));
+    }
+    Ok(KeyboardMap { first_key, last_key, middle_key, reference_key, reference_hz, octave_degree: octave_degree as usize, op_degree_vec })
+}
@@ -0,0 +1,148 @@
+use std::f64::consts::PI;
+
+use super::Float;
+
+#[cfg(test)]
+mod tests {
+    use super::{Integrator, SpringCoefficient};
+    use std::f64::consts::PI;
+
+    // Position and speed after "step_num" samples of force 1.0 from rest.
+    fn step_response(coefficient: &SpringCoefficient<f64>, step_num: usize) -> (f64, f64) {
+        let (mut position, mut speed) = (0.0, 0.0);
+        for _ in 0..step_num {
+            let next_position = coefficient.position_position * position + coefficient.position_speed * speed + coefficient.position_force;
+            speed = coefficient.speed_position * position + coefficient.speed_speed * speed + coefficient.speed_force;
+            position = next_position;
+        }
+        (position, speed)
+    }
+
+    #[test]
+    fn test_exact_step_response() {
+        // x(t) = (1 - e^(-a*t) * (cos(wd*t) + a/wd * sin(wd*t))) / k
+        let (spring_constant, damping, data_period) = ((2.0 * PI * 440.0).powi(2), 100.0, 1.0 / 8000.0);
+        let coefficient = SpringCoefficient::<f64>::new(Integrator::Exact, spring_constant, damping, data_period);
+        let t = 100.0 * data_period;
+        let (a, wd) = (damping / 2.0, (spring_constant - (damping / 2.0).powi(2)).sqrt());
+        let expected = (1.0 - (-a * t).exp() * ((wd * t).cos() + a / wd * (wd * t).sin())) / spring_constant;
+        assert!((step_response(&coefficient, 100).0 - expected).abs() < 1e-12);
+
+        // Critically damped and over damped springs settle at 1/k.
+        for damping in [2.0 * spring_constant.sqrt(), 4.0 * spring_constant.sqrt()].iter() {
+            let coefficient = SpringCoefficient::<f64>::new(Integrator::Exact, spring_constant, *damping, data_period);
+            let (position, speed) = step_response(&coefficient, 8000);
+            assert!((position * spring_constant - 1.0).abs() < 1e-9 && speed.abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_stability() {
+        // A7 at 8kHz
+        let (spring_constant, data_period) = ((2.0 * PI * 3520.0).powi(2), 1.0 / 8000.0);
+        assert!(!Integrator::Euler.is_stable(spring_constant, 100.0, data_period));
+        for integrator in [Integrator::Bilinear, Integrator::Exact].iter() {
+            assert!(integrator.is_stable(spring_constant, 100.0, data_period));
+            let coefficient = SpringCoefficient::<f64>::new(*integrator, spring_constant, 100.0, data_period);
+            let (position, speed) = step_response(&coefficient, 80000);
+            assert!(position.is_finite() && speed.is_finite());
+        }
+    }
+}
+
+// Discretisation of the spring-damper update in split_resonance().
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Integrator {
+    // Semi-implicit Euler. Fast, but detuned near Nyquist and unstable above k*dt^2 = 4 - 2*c*dt.
+    #[default]
+    Euler,
+    // Bilinear transform with the spring frequency prewarped. Stable up to Nyquist.
+    Bilinear,
+    // Exact solution for the force held over each sample. Stable up to Nyquist.
+    Exact,
+}
+
+impl Integrator {
+    // Springs must be below Nyquist frequency and keep the update stable.
+    pub fn is_stable(&self, spring_constant: f64, damping_coefficient: f64, data_period: f64) -> bool {
+        let nyquist_limit = (PI / data_period).powi(2);
+        match self {
+            Integrator::Euler => {
+                let euler_limit = (4.0 - 2.0 * damping_coefficient * data_period) / data_period.powi(2);
+                spring_constant < nyquist_limit.min(euler_limit)
+            }
+            Integrator::Bilinear | Integrator::Exact => spring_constant < nyquist_limit,
+        }
+    }
+}
+
+// One sample update of a spring:
+// position' = position_position*position + position_speed*speed + position_force*force
+// speed'    = speed_position*position    + speed_speed*speed    + speed_force*force
+#[derive(Debug, Clone)]
+pub(crate) struct SpringCoefficient<T: Float> {
+    pub(crate) position_position: T,
+    pub(crate) position_speed: T,
+    pub(crate) position_force: T,
+    pub(crate) speed_position: T,
+    pub(crate) speed_speed: T,
+    pub(crate) speed_force: T,
+}
+
+impl<T: Float> SpringCoefficient<T> {
+    // Calculated in f64 and converted to "T".
+    pub(crate) fn new(integrator: Integrator, spring_constant: f64, damping_coefficient: f64, data_period: f64) -> SpringCoefficient<T> {
+        let (k, c, dt) = (spring_constant, damping_coefficient, data_period);
+        let matrix = match integrator {
+            // speed' = speed + (force - k*position - c*speed)*dt, position' = position + speed'*dt
+            Integrator::Euler => [
+                [1.0 - k * dt * dt, (1.0 - c * dt) * dt, dt * dt],
+                [-k * dt, 1.0 - c * dt, dt],
+            ],
+            Integrator::Bilinear => {
+                // Prewarp so that the spring frequency is kept after the transform.
+                let k = (2.0 / dt * (k.sqrt() * dt / 2.0).tan()).powi(2);
+                let det = 1.0 + c * dt / 2.0 + k * dt * dt / 4.0;
+                [
+                    [(1.0 + c * dt / 2.0 - k * dt * dt / 4.0) / det, dt / det, dt * dt / 2.0 / det],
+                    [-k * dt / det, (1.0 - c * dt / 2.0 - k * dt * dt / 4.0) / det, dt / det],
+                ]
+            }
+            Integrator::Exact => {
+                // e^(A*dt) of A = [[0, 1], [-k, -c]]
+                let a = c / 2.0;
+                let discriminant = k - a * a;
+                // cos(wd*dt) and sin(wd*dt)/wd for under, critically and over damped springs.
+                let (cos, sin_per_w) = if discriminant > 0.0 {
+                    let wd = discriminant.sqrt();
+                    ((wd * dt).cos(), (wd * dt).sin() / wd)
+                }
+                else if discriminant < 0.0 {
+                    let wd = (-discriminant).sqrt();
+                    ((wd * dt).cosh(), (wd * dt).sinh() / wd)
+                }
+                else {
+                    (1.0, dt)
+                };
+                let decay = (-a * dt).exp();
+                let phi = [
+                    [decay * (cos + a * sin_per_w), decay * sin_per_w],
+                    [-decay * k * sin_per_w, decay * (cos - a * sin_per_w)],
+                ];
+                // A^-1 * (e^(A*dt) - I) * [0, 1]
+                [
+                    [phi[0][0], phi[0][1], (1.0 - phi[1][1] - c * phi[0][1]) / k],
+                    [phi[1][0], phi[1][1], phi[0][1]],
+                ]
+            }
+        };
+        SpringCoefficient {
+            position_position: T::from_f64(matrix[0][0]),
+            position_speed: T::from_f64(matrix[0][1]),
+            position_force: T::from_f64(matrix[0][2]),
+            speed_position: T::from_f64(matrix[1][0]),
+            speed_speed: T::from_f64(matrix[1][1]),
+            speed_force: T::from_f64(matrix[1][2]),
+        }
+    }
+}
@@ -1,6 +1,19 @@
 mod error;
 use error::*;
 
+mod config;
+pub use config::{ResonanceConfig, Damping, DAMPING_COEFFICIENT};
+
+mod integrator;
+pub use integrator::Integrator;
+use integrator::SpringCoefficient;
+
+mod grid;
+pub use grid::FrequencyGrid;
+
+mod tuning;
+pub use tuning::{estimate_tuning, TuningEstimate};
+
 use std::convert::TryFrom;
 use std::f64::consts::PI;
 use std::rc::Rc;
@@ -8,8 +21,8 @@ use std::rc::Rc;
 use std::thread;
 use std::sync::mpsc::channel;
 use std::sync::mpsc::{Sender, Receiver};
-use std::sync::Arc;
 
+pub use audio_sample::{Sample, Float, I24, AudioBuffer};
 
 
 const LOWEST_PITCH_IDX: isize = -48; // A0 to A8
@@ -58,111 +71,401 @@ pub enum SpnIdx { // Scientific Pitch Notation
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reachable_spring_num() {
+        // 8kHz: Euler integration is unstable above about 2538Hz (E7 is 2637Hz)
+        let resonance = Resonance::new(440.0, 8000, 1, 2).unwrap();
+        assert_eq!(resonance.reachable_spring_num(), SpnIdx::E7 as usize);
+        // 22.05kHz: A8 (7040Hz) is dropped
+        let resonance = Resonance::new(440.0, 22050, 1, 2).unwrap();
+        assert_eq!(resonance.reachable_spring_num(), SpnIdx::A8 as usize);
+        let resonance = Resonance::new(440.0, 48000, 1, 2).unwrap();
+        assert_eq!(resonance.reachable_spring_num(), SPN_NUM);
+        // Stable integrators reach Nyquist frequency. (C8 is 4186Hz)
+        let config = ResonanceConfig { integrator: Integrator::Exact, ..ResonanceConfig::default() };
+        let resonance = Resonance::with_config(440.0, 8000, 1, 2, &config).unwrap();
+        assert_eq!(resonance.reachable_spring_num(), SpnIdx::C8 as usize);
+    }
+
+    #[test]
+    fn test_strongest_pitch() {
+        assert_eq!(strongest_pitch(&[0.0; SPN_NUM]), None);
+        assert_eq!(strongest_pitch(&[0.0, 1.0, 3.0, 1.0, 0.0]), Some(2.0));
+        assert!(strongest_pitch(&[0.0, 1.0, 3.0, 2.0, 0.0]).unwrap() > 2.0);
+        assert!(strongest_pitch(&[0.0, 2.0, 3.0, 1.0, 0.0]).unwrap() < 2.0);
+
+        // A4 + 30 cents
+        let sampling_rate = 48000;
+        let hz = 440.0 * 2.0_f64.powf(0.3 / 12.0);
+        let sound_vec: Vec<f64> = (0..sampling_rate).map(|i| 0.5 * (2.0 * PI * hz * i as f64 / sampling_rate as f64).sin()).collect();
+        let resonance = Resonance::new(440.0, sampling_rate, 1, 2).unwrap();
+        let energy_vec = resonance.resonance(&AudioBuffer::new(vec![sound_vec], sampling_rate).unwrap()).unwrap();
+        let mean_vec: Vec<f64> = energy_vec[0].iter().map(|spring| spring[sampling_rate/2..].iter().sum::<f64>()).collect();
+        let pitch = strongest_pitch(&mean_vec).unwrap();
+        assert!((pitch - (SpnIdx::A4 as usize as f64 + 0.3)).abs() < 0.05, "{}", pitch);
+    }
+
+    #[test]
+    fn test_sample_type() {
+        // f32 calculation of 16bit PCM is close to f64 calculation.
+        let sampling_rate = 8000;
+        let pcm_vec: Vec<i16> = (0..sampling_rate).map(|i| (8000.0 * (2.0 * PI * 440.0 * i as f64 / sampling_rate as f64).sin()) as i16).collect();
+        let f64_vec: Vec<f64> = pcm_vec.iter().map(|data| data.to_f64()).collect();
+        let resonance_f32 = Resonance::<f32>::new_as(440.0, sampling_rate, 1, 2).unwrap();
+        let resonance_f64 = Resonance::new(440.0, sampling_rate, 1, 2).unwrap();
+        let energy_f32 = resonance_f32.resonance_from(&AudioBuffer::new(vec![pcm_vec], sampling_rate).unwrap()).unwrap();
+        let energy_f64 = resonance_f64.resonance(&AudioBuffer::new(vec![f64_vec], sampling_rate).unwrap()).unwrap();
+        let a4 = SpnIdx::A4 as usize;
+        for (val_f32, val_f64) in energy_f32[0][a4].iter().zip(energy_f64[0][a4].iter()) {
+            assert!((f64::from(*val_f32) - val_f64).abs() < 1e-4 * val_f64.max(1e-3));
+        }
+        assert_eq!(strongest_pitch(&energy_f32[0].iter().map(|spring| spring.iter().sum::<f32>()).collect::<Vec<f32>>()).unwrap().round(), a4 as f64);
+    }
+
+    #[test]
+    fn test_constant_q() {
+        // Energy of the spring a semitone above the tone, relative to the tone spring.
+        let neighbour_ratio = |ref_config: &ResonanceConfig, spring_idx: usize| -> f64 {
+            let sampling_rate = 48000;
+            let hz = 440.0 * 2.0_f64.powf((spring_idx as f64 - SpnIdx::A4 as usize as f64) / 12.0);
+            let sound_vec: Vec<f64> = (0..sampling_rate).map(|i| 0.5 * (2.0 * PI * hz * i as f64 / sampling_rate as f64).sin()).collect();
+            let resonance = Resonance::with_config(440.0, sampling_rate, 1, 2, ref_config).unwrap();
+            let energy_vec = resonance.resonance(&AudioBuffer::new(vec![sound_vec], sampling_rate).unwrap()).unwrap();
+            let mean = |idx: usize| energy_vec[0][idx][sampling_rate/2..].iter().sum::<f64>();
+            mean(spring_idx + 1) / mean(spring_idx)
+        };
+        let (low, high) = (SpnIdx::A2 as usize, SpnIdx::A5 as usize);
+        let default_config = ResonanceConfig::default();
+        assert!(neighbour_ratio(&default_config, low) > 2.0 * neighbour_ratio(&default_config, high));
+        let q_config = ResonanceConfig::new(Damping::ConstantQ(30.0));
+        let (low_ratio, high_ratio) = (neighbour_ratio(&q_config, low), neighbour_ratio(&q_config, high));
+        assert!((low_ratio - high_ratio).abs() < 0.05, "{} {}", low_ratio, high_ratio);
+    }
+
+    #[test]
+    fn test_frequency_grid() {
+        // A quarter tone above A4 is caught by its own spring in 24 steps per octave.
+        let sampling_rate = 48000;
+        let hz = 440.0 * 2.0_f64.powf(0.5 / 12.0);
+        let sound_vec: Vec<f64> = (0..sampling_rate).map(|i| 0.5 * (2.0 * PI * hz * i as f64 / sampling_rate as f64).sin()).collect();
+        let grid = FrequencyGrid::edo(440.0, 24, 0.0, 220.0, 880.0).unwrap();
+        let config = ResonanceConfig::new(Damping::BandwidthCents(25.0));
+        let resonance = Resonance::with_grid(&grid, sampling_rate, 1, 2, &config).unwrap();
+        assert_eq!(resonance.grid().len(), 49);
+        let energy_vec = resonance.resonance(&AudioBuffer::new(vec![sound_vec], sampling_rate).unwrap()).unwrap();
+        assert_eq!(energy_vec[0].len(), 49);
+        let mean_vec: Vec<f64> = energy_vec[0].iter().map(|spring| spring[sampling_rate/2..].iter().sum::<f64>()).collect();
+        let pitch = strongest_pitch(&mean_vec).unwrap();
+        assert_eq!(resonance.grid().label(pitch.round() as usize), "A4+50");
+
+        // Default springs are the SPN grid.
+        let resonance = Resonance::new(440.0, sampling_rate, 1, 1).unwrap();
+        assert_eq!(resonance.grid(), &FrequencyGrid::spn(440.0));
+    }
+
+    #[test]
+    fn test_process_block() {
+        let sampling_rate = 8000;
+        let sound_vec: Vec<f64> = (0..sampling_rate).map(|i| 0.5 * (2.0 * PI * 440.0 * i as f64 / sampling_rate as f64).sin()).collect();
+        let sound_buffer = AudioBuffer::new(vec![sound_vec.clone(), sound_vec], sampling_rate).unwrap();
+        let config = ResonanceConfig { hop: 80, ..ResonanceConfig::default() };
+
+        let mut resonance = Resonance::with_config(440.0, sampling_rate, 2, 2, &config).unwrap();
+        let whole = resonance.process_block(&sound_buffer).unwrap();
+        assert_eq!((whole.first_hop_idx, whole.hop_num), (0, 100));
+        assert_eq!(whole.energy_vec[1][SpnIdx::A4 as usize].len(), 100);
+
+        // Blocks not aligned to the hop give the same energy.
+        resonance.reset().unwrap();
+        let mut hop_vec: Vec<f64> = Vec::new();
+        let mut block_stt = 0;
+        for block_len in [7, 80, 333, 1, 2000, 8000].iter() {
+            let frame = resonance.process_block(&sound_buffer.view(block_stt, block_stt + block_len)).unwrap();
+            assert_eq!(frame.first_hop_idx, hop_vec.len());
+            assert_eq!(frame.first_frame_idx(), hop_vec.len() * 80);
+            hop_vec.extend_from_slice(&frame.energy_vec[0][SpnIdx::A4 as usize]);
+            block_stt = (block_stt + block_len).min(sampling_rate);
+        }
+        assert_eq!(hop_vec, whole.energy_vec[0][SpnIdx::A4 as usize]);
+
+        // Each hop is the max of the energy of every sample.
+        let resonance = Resonance::new(440.0, sampling_rate, 2, 2).unwrap();
+        let sample_vec = resonance.resonance(&sound_buffer).unwrap();
+        let max = sample_vec[0][SpnIdx::A4 as usize][80..160].iter().cloned().fold(0.0, f64::max);
+        assert_eq!(max, whole.energy_vec[0][SpnIdx::A4 as usize][1]);
+
+        let config = ResonanceConfig { hop: 0, ..ResonanceConfig::default() };
+        assert!(Resonance::with_config(440.0, sampling_rate, 1, 1, &config).is_err());
+    }
+
+    #[test]
+    fn test_set_pitch_standard() {
+        let sampling_rate = 8000;
+        let sound_vec: Vec<f64> = (0..sampling_rate).map(|i| 0.5 * (2.0 * PI * 415.0 * i as f64 / sampling_rate as f64).sin()).collect();
+        let sound_buffer = AudioBuffer::new(vec![sound_vec.clone(), sound_vec], sampling_rate).unwrap();
+        let config = ResonanceConfig { hop: 80, ..ResonanceConfig::default() };
+        let mut created = Resonance::with_config(415.0, sampling_rate, 2, 3, &config).unwrap();
+        let expected = created.process_block(&sound_buffer).unwrap();
+
+        // Retuned springs give the same energy as springs created at the pitch standard.
+        let mut resonance = Resonance::with_config(440.0, sampling_rate, 2, 3, &config).unwrap();
+        resonance.process_block(&sound_buffer.view(0, 1000)).unwrap();
+        resonance.set_pitch_standard(415.0).unwrap();
+        assert_eq!(resonance.grid(), &FrequencyGrid::spn(415.0));
+        let retuned = resonance.process_block(&sound_buffer).unwrap();
+        assert_eq!(retuned.first_hop_idx, 0);
+        assert_eq!(retuned.energy_vec, expected.energy_vec);
+
+        // E7 is reachable in Euler integration at 8kHz when A4 is 415Hz.
+        assert_eq!(resonance.reachable_spring_num(), SpnIdx::E7 as usize + 1);
+        resonance.set_pitch_standard(440.0).unwrap();
+        assert_eq!(resonance.reachable_spring_num(), SpnIdx::E7 as usize);
+    }
+
+    // Detune in cents of the frequency where the spring responds most.
+    fn peak_detune(integrator: Integrator, hz: f64, sampling_rate: usize) -> f64 {
+        let spring_constant = (2.0 * PI * hz).powi(2);
+        let damping_coefficient = 2.0 * PI * hz / 50.0;
+        let mean_energy = |cents: f64| -> f64 {
+            let tone_hz = hz * 2.0_f64.powf(cents / 1200.0);
+            let sound_vec: Vec<f64> = (0..(sampling_rate * 4)).map(|i| (2.0 * PI * tone_hz * i as f64 / sampling_rate as f64).sin()).collect();
+            let mut split_resonance = SplitResonance::new(vec![spring_constant], vec![damping_coefficient], 1.0 / sampling_rate as f64, integrator, 1, 0).unwrap();
+            let report = split_resonance.split_resonance(&AudioBuffer::new(vec![sound_vec], sampling_rate).unwrap()).unwrap();
+            report.energy_spring_vec[0][(sampling_rate * 2)..].iter().sum()
+        };
+        let step = 5.0;
+        let (low, peak, high) = (mean_energy(-step), mean_energy(0.0), mean_energy(step));
+        step * 0.5 * (low - high) / (low - 2.0 * peak + high)
+    }
+
+    #[test]
+    fn test_integrator_pitch() {
+        let sampling_rate = 16000;
+        let spring_hz = |spring_idx: usize| 440.0 * 2.0_f64.powf((spring_idx as f64 - SpnIdx::A4 as usize as f64) / 12.0);
+        for integrator in [Integrator::Bilinear, Integrator::Exact].iter() {
+            for spring_idx in (SpnIdx::A1 as usize..SPN_NUM).step_by(12) {
+                let detune = peak_detune(*integrator, spring_hz(spring_idx), sampling_rate);
+                assert!(detune.abs() < 1.0, "{:?} {}: {} cents", integrator, SPN_LABEL[spring_idx], detune);
+            }
+        }
+        // Euler is already off at A4.
+        assert!(peak_detune(Integrator::Euler, 440.0, sampling_rate).abs() > 1.0);
+    }
+
+    #[test]
+    fn test_unreachable_spring_is_silent() {
+        let resonance = Resonance::new(440.0, 8000, 2, 3).unwrap();
+        let mut seed: u64 = 1;
+        let mut noise_vec: Vec<f64> = Vec::new();
+        for _ in 0..8000 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            noise_vec.push(f64::from((seed >> 40) as u32) / f64::from(1 << 24) - 0.5);
+        }
+        let energy_vec = resonance.resonance(&AudioBuffer::new(vec![noise_vec.clone(), noise_vec], 8000).unwrap()).unwrap();
+        for ch in energy_vec.iter() {
+            assert_eq!(ch.len(), SPN_NUM);
+            for (spring_idx, spring) in ch.iter().enumerate() {
+                assert_eq!(spring.len(), 8000);
+                for energy in spring {
+                    assert!(energy.is_finite());
+                    if spring_idx >= resonance.reachable_spring_num() {
+                        assert_eq!(*energy, 0.0);
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[allow(dead_code)]
 #[derive(Clone)]
-pub struct SpringStatus {
-    speed:f64,
-    position:f64,
+pub struct SpringStatus<T: Float> {
+    speed:T,
+    position:T,
 }
 
 #[derive(Clone)]
 #[derive(PartialEq)]
 enum ResonanceRequestType {
     Calc,
+    Reset,
+    Retune,
     Exit,
 }
 
-pub struct ResonanceRequest {
+pub struct ResonanceRequest<T: Float> {
     request_type: ResonanceRequestType,
-    sound_data_arc: Option<Arc<Vec<Vec<f64>>>>,
+    op_sound_buffer: Option<AudioBuffer<T>>,
+    op_split_resonance: Option<SplitResonance<T>>,   // Retune: springs replacing the current ones
 }
 
-pub struct ResonanceReport {
+pub struct ResonanceReport<T: Float> {
     ch_idx: usize,
-    energy_spring_vec: Vec<Vec<f64>>,
+    hop_num: usize,     // hops ended in the block
+    energy_spring_vec: Vec<Vec<T>>,
+}
+
+// Output of Resonance::process_block().
+#[derive(Clone)]
+pub struct SpectrumFrame<T: Float = f64> {
+    pub hop: usize,
+    pub first_hop_idx: usize,           // hops before this block since the start or reset()
+    pub hop_num: usize,                 // hops ended in the block
+    pub energy_vec: Vec<Vec<Vec<T>>>,   // channel<spring<hop<energy>>>
+}
+
+impl<T: Float> SpectrumFrame<T> {
+    // Sample index (since the start or reset()) of the first input of the first hop in the block
+    pub fn first_frame_idx(&self) -> usize {
+        self.first_hop_idx * self.hop
+    }
 }
 
 
 #[allow(dead_code)]
 #[derive(Clone)]
-pub struct SplitResonance {
-    spring_constant_vec: Vec<f64>,
-    data_period: f64,
+pub struct SplitResonance<T: Float> {
+    spring_constant_vec: Vec<T>,
+    coefficient_vec: Vec<SpringCoefficient<T>>,
     ch_idx: usize,
-    pub spring_sts_vec: Vec<SpringStatus>,  // channel<spring<SpringStatus>>
+    pub spring_sts_vec: Vec<SpringStatus<T>>,  // channel<spring<SpringStatus>>
+    hop: usize,
+    hop_pos: usize,         // samples of the current hop
+    hop_max_vec: Vec<T>,    // max energy of each spring in the current hop
 }
 
-impl SplitResonance {
-    pub fn new(spring_constant_vec: Vec<f64>, data_period: f64, ch_idx: usize) -> Result<SplitResonance>  {
-        let mut spring_sts_vec: Vec<SpringStatus> = Vec::new();
+impl<T: Float> SplitResonance<T> {
+    // "damping_vec" is the damping coefficient of each spring. One energy is output every "hop" samples.
+    pub fn new(spring_constant_vec: Vec<f64>, damping_vec: Vec<f64>, data_period: f64, integrator: Integrator, hop: usize, ch_idx: usize) -> Result<SplitResonance<T>>  {
+        if damping_vec.len() != spring_constant_vec.len() {
+            return Err(ResonanceError::new("Damping must be given for every spring!"));
+        }
+        if hop == 0 {
+            return Err(ResonanceError::new("hop must not be 0!"));
+        }
+        let mut coefficient_vec: Vec<SpringCoefficient<T>> = Vec::with_capacity(spring_constant_vec.len());
+        for (spring_constant, damping_coefficient) in spring_constant_vec.iter().zip(damping_vec.iter()) {
+            if !integrator.is_stable(*spring_constant, *damping_coefficient, data_period) {
+                return Err(ResonanceError::new("Spring is not stable in the integrator!"));
+            }
+            coefficient_vec.push(SpringCoefficient::new(integrator, *spring_constant, *damping_coefficient, data_period));
+        }
+        let mut spring_sts_vec: Vec<SpringStatus<T>> = Vec::new();
         for _ in 0..spring_constant_vec.len(){
             let spring_sts = SpringStatus{
-                speed:0.0,
-                position:0.0,
+                speed:T::default(),
+                position:T::default(),
             };
             spring_sts_vec.push(spring_sts);
         }
 
         Ok(SplitResonance {
-            spring_constant_vec: spring_constant_vec,
-            data_period : data_period,
+            spring_constant_vec: spring_constant_vec.iter().map(|spring_constant| T::from_f64(*spring_constant)).collect(),
+            coefficient_vec,
             ch_idx: ch_idx,
             spring_sts_vec: spring_sts_vec,
+            hop,
+            hop_pos: 0,
+            hop_max_vec: vec![T::default(); spring_constant_vec.len()],
         }) 
     }
 
-    pub fn split_resonance(&mut self, sound_data_arc: Arc<Vec<Vec<f64>>>)  -> Result<ResonanceReport> {
-        let mut energy_max = 0.0;
-        let mut ret_energy_spring_vec: Vec<Vec<f64>> = Vec::with_capacity(self.spring_constant_vec.len());
-        for (spring_idx, spring_constant) in self.spring_constant_vec.iter().enumerate() {
+    // Springs at rest and a new hop from the next sample
+    pub fn reset(&mut self) {
+        for spring_sts in self.spring_sts_vec.iter_mut() {
+            spring_sts.speed = T::default();
+            spring_sts.position = T::default();
+        }
+        self.hop_pos = 0;
+        for hop_max in self.hop_max_vec.iter_mut() {
+            *hop_max = T::default();
+        }
+    }
+
+    // Springs continue from the end of the previous block. A hop over the end of the block is output in the next block.
+    pub fn split_resonance(&mut self, sound_buffer: &AudioBuffer<T>)  -> Result<ResonanceReport<T>> {
+        let half = T::from_f64(0.5);
+        let hop_num = (self.hop_pos + sound_buffer.frame_num()) / self.hop;
+        let mut ret_energy_spring_vec: Vec<Vec<T>> = Vec::with_capacity(self.spring_constant_vec.len());
+        for (spring_idx, (spring_constant, coefficient)) in self.spring_constant_vec.iter().zip(self.coefficient_vec.iter()).enumerate() {
+            let spring_constant = *spring_constant;
             let spring_sts = &mut self.spring_sts_vec[spring_idx];
-            let mut ret_energy_vec :Vec<f64> = Vec::with_capacity(sound_data_arc.len());
-            for data in sound_data_arc[self.ch_idx].iter() {
-                spring_sts.speed = (*data - spring_constant * spring_sts.position - spring_sts.speed * 100.0)*self.data_period + spring_sts.speed;
-                spring_sts.position = spring_sts.speed*self.data_period + spring_sts.position;
-                let route_energy = (0.5*spring_sts.speed.powi(2) + 0.5*spring_constant*spring_sts.position.powi(2)).powf(0.5);
-                ret_energy_vec.push(route_energy);
-                if energy_max < route_energy {
-                    energy_max = route_energy;
+            let mut hop_pos = self.hop_pos;
+            let mut hop_max = self.hop_max_vec[spring_idx];
+            let mut ret_energy_vec :Vec<T> = Vec::with_capacity(hop_num);
+            for data in sound_buffer.channel(self.ch_idx).iter() {
+                let position = coefficient.position_position*spring_sts.position + coefficient.position_speed*spring_sts.speed + coefficient.position_force * *data;
+                spring_sts.speed = coefficient.speed_position*spring_sts.position + coefficient.speed_speed*spring_sts.speed + coefficient.speed_force * *data;
+                spring_sts.position = position;
+                let route_energy = (half*spring_sts.speed.powi(2) + half*spring_constant*spring_sts.position.powi(2)).sqrt();
+                if hop_max < route_energy {
+                    hop_max = route_energy;
+                }
+                hop_pos += 1;
+                if hop_pos == self.hop {
+                    ret_energy_vec.push(hop_max);
+                    hop_max = T::default();
+                    hop_pos = 0;
                 }
             }
+            self.hop_max_vec[spring_idx] = hop_max;
             ret_energy_spring_vec.push(ret_energy_vec);
         }
+        self.hop_pos = (self.hop_pos + sound_buffer.frame_num()) % self.hop;
 
         Ok( ResonanceReport{
             ch_idx: self.ch_idx,
+            hop_num,
             energy_spring_vec: ret_energy_spring_vec,
         })
     }
 }
 #[allow(dead_code)]
+// Energy is calculated in "T". f32 is faster, f64 (default) is more accurate.
 #[derive(Clone)]
-pub struct Resonance {
+pub struct Resonance<T: Float = f64> {
     ch_num:usize,
+    grid: FrequencyGrid,
+    reachable_spring_num: usize,
+    hop: usize,
+    hop_counter: usize,     // hops output by process_block() since the start or reset()
     thread_per_ch: usize,
+    data_period: f64,
+    config: ResonanceConfig,
     thread_vec: Rc<Vec<thread::JoinHandle<Result<()>>>>,
-    to_resonance_sender_vec: Rc<Vec<Sender<ResonanceRequest>>>,
-    from_resonance_receiver_vec: Rc<Vec<Receiver<ResonanceReport>>>,
+    to_resonance_sender_vec: Rc<Vec<Sender<ResonanceRequest<T>>>>,
+    from_resonance_receiver_vec: Rc<Vec<Receiver<ResonanceReport<T>>>>,
 }
 
-fn resonance_thread_main( from_resonanance_sender: Sender<ResonanceReport>, to_resonance_receiver: Receiver<ResonanceRequest>,
-    split_spring_vec: Vec<f64>, data_period: f64, ch_idx: usize) -> Result<()> {
+fn resonance_thread_main<T: Float>( from_resonanance_sender: Sender<ResonanceReport<T>>, to_resonance_receiver: Receiver<ResonanceRequest<T>>,
+    split_spring_vec: Vec<f64>, split_damping_vec: Vec<f64>, data_period: f64, config: ResonanceConfig, ch_idx: usize) -> Result<()> {
     
-    let mut split_resonance = SplitResonance::new(split_spring_vec, data_period, ch_idx)?;
+    let mut split_resonance = SplitResonance::new(split_spring_vec, split_damping_vec, data_period, config.integrator, config.hop, ch_idx)?;
 
     loop {
         let resonance_request = to_resonance_receiver.recv()?;
         match resonance_request.request_type {
             ResonanceRequestType::Calc => {
-                if let Some(sound_data_arc) = resonance_request.sound_data_arc {
-                    from_resonanance_sender.send(split_resonance.split_resonance(sound_data_arc)?)?;
+                if let Some(sound_buffer) = resonance_request.op_sound_buffer {
+                    from_resonanance_sender.send(split_resonance.split_resonance(&sound_buffer)?)?;
                 }
                 else {
                     return Err(ResonanceError::new("Calc Resonance must be with Sound Data!"));
                 }
             }
+            ResonanceRequestType::Reset => {
+                split_resonance.reset();
+            }
+            ResonanceRequestType::Retune => {
+                if let Some(new_split_resonance) = resonance_request.op_split_resonance {
+                    split_resonance = new_split_resonance;
+                }
+                else {
+                    return Err(ResonanceError::new("Retune Resonance must be with Springs!"));
+                }
+            }
             ResonanceRequestType::Exit => {
                 break;
             }
@@ -171,9 +474,9 @@ fn resonance_thread_main( from_resonanance_sender: Sender<ResonanceReport>, to_r
     Ok(())
 }
 
-fn resonance_thread( from_resonanance_sender: Sender<ResonanceReport>, to_resonance_receiver: Receiver<ResonanceRequest>,
-    split_spring_vec: Vec<f64>, data_period: f64, ch_idx: usize) -> Result<()> {
-    match resonance_thread_main( from_resonanance_sender, to_resonance_receiver, split_spring_vec, data_period, ch_idx) {
+fn resonance_thread<T: Float>( from_resonanance_sender: Sender<ResonanceReport<T>>, to_resonance_receiver: Receiver<ResonanceRequest<T>>,
+    split_spring_vec: Vec<f64>, split_damping_vec: Vec<f64>, data_period: f64, config: ResonanceConfig, ch_idx: usize) -> Result<()> {
+    match resonance_thread_main( from_resonanance_sender, to_resonance_receiver, split_spring_vec, split_damping_vec, data_period, config, ch_idx) {
         Ok(_ret) => { /* Nothing to do */ }
         Err(err) => {
             println!("Error! resonance_thread!");
@@ -183,47 +486,130 @@ fn resonance_thread( from_resonanance_sender: Sender<ResonanceReport>, to_resona
     Ok(())
 }
 
-impl Resonance {
-    pub fn new(pitch_standard_frequency: f64, data_frequency: usize, ch_num:usize, thread_per_ch: usize) -> Result<Resonance>  {
-        if thread_per_ch == 0 {
-            return Err(ResonanceError::new("thread_per_ch must not be 0!"));
+// thread<(spring constants, damping)>
+type SplitSpringVec = Vec<(Vec<f64>, Vec<f64>)>;
+
+// Springs of each thread in a channel. (reachable spring number, thread<(spring constants, damping)>)
+fn split_springs(ref_grid: &FrequencyGrid, data_period: f64, thread_per_ch: usize, ref_config: &ResonanceConfig) -> Result<(usize, SplitSpringVec)> {
+    let hz_vec = ref_grid.hz_vec();
+    let all_damping_vec = ref_config.damping_vec(hz_vec)?;
+
+    // Springs above the limit of the integrator are dropped. (They are reported as zero energy.)
+    let integrator = ref_config.integrator;
+    let mut spring_constant_vec: Vec<f64> = Vec::new();
+    let mut damping_vec: Vec<f64> = Vec::new();
+    for (hz, damping_coefficient) in hz_vec.iter().zip(all_damping_vec.iter()) {
+        let spring_constant = (hz*2.0*PI).powi(2);
+        if !integrator.is_stable(spring_constant, *damping_coefficient, data_period) {
+            break;
         }
+        spring_constant_vec.push(spring_constant);
+        damping_vec.push(*damping_coefficient);
+    }
+    let reachable_spring_num = spring_constant_vec.len();
 
-        let mut to_resonance_sender_vec: Vec<Sender<ResonanceRequest>> = Vec::new();
-        let mut from_resonance_receiver_vec: Vec<Receiver<ResonanceReport>> = Vec::new();
-        let mut resonance_thread_instanse_vec: Vec<thread::JoinHandle<Result<()>>> = Vec::new();
+    // split by thread
+    let split_pitch_range;
+    if reachable_spring_num.is_multiple_of(thread_per_ch) {
+        split_pitch_range = reachable_spring_num / thread_per_ch;
+    }
+    else{
+        split_pitch_range = reachable_spring_num / thread_per_ch + 1;
+    }
+    let mut split_vec: SplitSpringVec = Vec::with_capacity(thread_per_ch);
+    for split_idx in 0..thread_per_ch {
+        let split_stt = reachable_spring_num.min(split_pitch_range*split_idx);
+        let split_end;
+        if split_idx < thread_per_ch - 1 {
+            split_end = reachable_spring_num.min(split_pitch_range*(split_idx+1));
+        }
+        else{ 
+            split_end = reachable_spring_num;
+        }
+        split_vec.push((spring_constant_vec[split_stt..split_end].to_vec(), damping_vec[split_stt..split_end].to_vec()));
+    }
+    Ok((reachable_spring_num, split_vec))
+}
 
-        let mut spring_constant_vec: Vec<f64> = Vec::new();
-        for i in LOWEST_PITCH_IDX..HIGHEST_PITCH_IDX+1 {
-            let hz = pitch_standard_frequency*2.0_f64.powf(f64::from(i32::try_from(i)?)/12.0);
-            spring_constant_vec.push((hz*2.0*PI).powi(2));
+// MIDI note number of A0 (SPN index 0)
+pub const MIDI_NOTE_OF_A0: usize = 21;
+
+// Pitch of the strongest spring in SPN index. spring<energy>
+// The fraction is interpolated by a parabola through the neighbouring springs.
+// None if there is no energy.
+pub fn strongest_pitch<T: Float>(energy_vec: &[T]) -> Option<f64> {
+    let energy_vec: Vec<f64> = energy_vec.iter().map(|energy| energy.to_f64()).collect();
+    let mut peak_idx = 0;
+    for (idx, energy) in energy_vec.iter().enumerate() {
+        if *energy > energy_vec[peak_idx] {
+            peak_idx = idx;
         }
+    }
+    if energy_vec.is_empty() || energy_vec[peak_idx] <= 0.0 {
+        return None;
+    }
+    if peak_idx == 0 || peak_idx == energy_vec.len() - 1 {
+        return Some(peak_idx as f64);
+    }
+    // Squared root energy of a damped spring falls like 1/(a + b*detune^2), so its reciprocal is a parabola.
+    let (low, peak, high) = (energy_vec[peak_idx - 1].powi(-2), energy_vec[peak_idx].powi(-2), energy_vec[peak_idx + 1].powi(-2));
+    let curvature = low - 2.0 * peak + high;
+    if !curvature.is_finite() || curvature <= 0.0 {
+        return Some(peak_idx as f64);
+    }
+    Some(peak_idx as f64 + 0.5 * (low - high) / curvature)
+}
+
+impl Resonance {
+    pub fn new(pitch_standard_frequency: f64, data_frequency: usize, ch_num:usize, thread_per_ch: usize) -> Result<Resonance>  {
+        Resonance::new_as(pitch_standard_frequency, data_frequency, ch_num, thread_per_ch)
+    }
 
-        // split by thread
-        let split_pitch_range;
-        if SPN_NUM % thread_per_ch == 0 {
-            split_pitch_range = SPN_NUM / thread_per_ch;
+    pub fn with_config(pitch_standard_frequency: f64, data_frequency: usize, ch_num:usize, thread_per_ch: usize, ref_config: &ResonanceConfig) -> Result<Resonance>  {
+        Resonance::with_config_as(pitch_standard_frequency, data_frequency, ch_num, thread_per_ch, ref_config)
+    }
+
+    pub fn with_grid(ref_grid: &FrequencyGrid, data_frequency: usize, ch_num:usize, thread_per_ch: usize, ref_config: &ResonanceConfig) -> Result<Resonance>  {
+        Resonance::with_grid_as(ref_grid, data_frequency, ch_num, thread_per_ch, ref_config)
+    }
+}
+
+impl<T: Float> Resonance<T> {
+    // new() calculating in "T". e.g. Resonance::<f32>::new_as()
+    pub fn new_as(pitch_standard_frequency: f64, data_frequency: usize, ch_num:usize, thread_per_ch: usize) -> Result<Resonance<T>>  {
+        Resonance::with_config_as(pitch_standard_frequency, data_frequency, ch_num, thread_per_ch, &ResonanceConfig::default())
+    }
+
+    // Damping and integrator of the springs are set by "ref_config".
+    pub fn with_config_as(pitch_standard_frequency: f64, data_frequency: usize, ch_num:usize, thread_per_ch: usize, ref_config: &ResonanceConfig) -> Result<Resonance<T>>  {
+        Resonance::with_grid_as(&FrequencyGrid::spn(pitch_standard_frequency), data_frequency, ch_num, thread_per_ch, ref_config)
+    }
+
+    // Springs at the frequencies of "ref_grid" instead of A0 to A8.
+    pub fn with_grid_as(ref_grid: &FrequencyGrid, data_frequency: usize, ch_num:usize, thread_per_ch: usize, ref_config: &ResonanceConfig) -> Result<Resonance<T>>  {
+        if thread_per_ch == 0 {
+            return Err(ResonanceError::new("thread_per_ch must not be 0!"));
         }
-        else{
-            split_pitch_range =SPN_NUM / thread_per_ch + 1;
+        if ref_config.hop == 0 {
+            return Err(ResonanceError::new("hop must not be 0!"));
         }
 
+        let mut to_resonance_sender_vec: Vec<Sender<ResonanceRequest<T>>> = Vec::new();
+        let mut from_resonance_receiver_vec: Vec<Receiver<ResonanceReport<T>>> = Vec::new();
+        let mut resonance_thread_instanse_vec: Vec<thread::JoinHandle<Result<()>>> = Vec::new();
+
         let data_period = 1.0/f64::from(u32::try_from(data_frequency)?);
 
+        let (reachable_spring_num, split_vec) = split_springs(ref_grid, data_period, thread_per_ch, ref_config)?;
+
         for ch_idx in 0..ch_num {
-            for split_idx in 0..thread_per_ch {
-                let split_spring_vec;
-                if split_idx < thread_per_ch - 1 {
-                    split_spring_vec = spring_constant_vec[split_pitch_range*split_idx..split_pitch_range*(split_idx+1)].to_vec();
-                }
-                else{ 
-                    split_spring_vec = spring_constant_vec[split_pitch_range*split_idx..].to_vec();
-                }
+            for (split_spring_vec, split_damping_vec) in split_vec.iter().cloned() {
+                let config = ref_config.clone();
 
-                let (to_resonance_sender, to_resonance_receiver) = channel::<ResonanceRequest>(); // data
-                let (from_resonance_sender, from_resonance_receiver) = channel::<ResonanceReport>(); // spring<data<energy>>
+                let (to_resonance_sender, to_resonance_receiver) = channel::<ResonanceRequest<T>>(); // data
+                let (from_resonance_sender, from_resonance_receiver) = channel::<ResonanceReport<T>>(); // spring<data<energy>>
                 let resonance_thread_instanse = thread::spawn(move || 
-                    resonance_thread(from_resonance_sender, to_resonance_receiver, split_spring_vec, data_period, ch_idx)
+                    resonance_thread::<T>(from_resonance_sender, to_resonance_receiver, split_spring_vec, split_damping_vec, data_period, config, ch_idx)
                 );
                 to_resonance_sender_vec.push(to_resonance_sender);
                 from_resonance_receiver_vec.push(from_resonance_receiver);
@@ -232,31 +618,136 @@ impl Resonance {
         }
         Ok(Resonance {
             ch_num: ch_num,
+            grid: ref_grid.clone(),
+            reachable_spring_num,
+            hop: ref_config.hop,
+            hop_counter: 0,
             thread_per_ch: thread_per_ch,
+            data_period,
+            config: ref_config.clone(),
             thread_vec: Rc::new(resonance_thread_instanse_vec),
             to_resonance_sender_vec: Rc::new(to_resonance_sender_vec),
             from_resonance_receiver_vec: Rc::new(from_resonance_receiver_vec)
         })
     }
 
-    // Temporary Implementation
-    pub fn resonance(&self, sound_data_arc: Arc<Vec<Vec<f64>>>) -> Result<Vec<Vec<Vec<f64>>>> { // channel<spring<data<energy>>>
+    // Energy of the hops ended in "sound_buffer". channel<spring<hop<energy>>> (hop 1: channel<spring<data<energy>>>)
+    // Springs continue from the previous block as process_block().
+    // Each thread gets a view of "sound_buffer", the data is not copied.
+    pub fn resonance(&self, sound_buffer: &AudioBuffer<T>) -> Result<Vec<Vec<Vec<T>>>> {
+        Ok(self.calc_block(sound_buffer)?.1)
+    }
+
+    // (hops ended in the block, channel<spring<hop<energy>>>)
+    fn calc_block(&self, sound_buffer: &AudioBuffer<T>) -> Result<(usize, Vec<Vec<Vec<T>>>)> {
+      if sound_buffer.channel_num() != self.ch_num {
+        return Err(ResonanceError::new("Channel number of sound data does not match!"));
+      }
       for sender in &*self.to_resonance_sender_vec {
         sender.send(ResonanceRequest {
                 request_type: ResonanceRequestType::Calc,
-                sound_data_arc: Some(sound_data_arc.clone()),
+                op_sound_buffer: Some(sound_buffer.clone()),
+                op_split_resonance: None,
         })?;
       }
       
-      let mut energy_spring_ch_vec:Vec<Vec<Vec<f64>>> = Vec::with_capacity(self.ch_num);
+      let mut energy_spring_ch_vec:Vec<Vec<Vec<T>>> = Vec::with_capacity(self.ch_num);
       for _ in 0..self.ch_num {
         energy_spring_ch_vec.push(Vec::new());
       }
+      let mut hop_num = 0;
       for receiver in &*self.from_resonance_receiver_vec {
         let mut resonance_report = receiver.recv()?;
+        hop_num = resonance_report.hop_num;
         energy_spring_ch_vec[resonance_report.ch_idx].append(&mut resonance_report.energy_spring_vec);
       }
-      Ok(energy_spring_ch_vec)
+      // Unreachable springs
+      for energy_spring_vec in energy_spring_ch_vec.iter_mut() {
+        energy_spring_vec.resize(self.grid.len(), vec![T::default(); hop_num]);
+      }
+      Ok((hop_num, energy_spring_ch_vec))
+    }
+
+    // resonance() of sound data in another sample type. e.g. 16bit PCM as i16
+    pub fn resonance_from<S: Sample>(&self, sound_buffer: &AudioBuffer<S>) -> Result<Vec<Vec<Vec<T>>>> {
+        self.resonance(&sound_buffer.convert())
+    }
+
+    // Streaming: feed the sound block by block in any length.
+    // Position and speed of the springs carry over between blocks, so the energy is the same as one long block.
+    // A hop is output in the block where it ends, and the rest of the block starts the next hop.
+    pub fn process_block(&mut self, sound_buffer: &AudioBuffer<T>) -> Result<SpectrumFrame<T>> {
+        let (hop_num, energy_vec) = self.calc_block(sound_buffer)?;
+        let spectrum_frame = SpectrumFrame {
+            hop: self.hop,
+            first_hop_idx: self.hop_counter,
+            hop_num,
+            energy_vec,
+        };
+        self.hop_counter += hop_num;
+        Ok(spectrum_frame)
+    }
+
+    // process_block() of sound data in another sample type.
+    pub fn process_block_from<S: Sample>(&mut self, sound_buffer: &AudioBuffer<S>) -> Result<SpectrumFrame<T>> {
+        self.process_block(&sound_buffer.convert())
+    }
+
+    // Springs at rest as just created. The next block starts hop 0. (e.g. after seeking)
+    pub fn reset(&mut self) -> Result<()> {
+        for sender in &*self.to_resonance_sender_vec {
+            sender.send(ResonanceRequest {
+                    request_type: ResonanceRequestType::Reset,
+                    op_sound_buffer: None,
+                    op_split_resonance: None,
+            })?;
+        }
+        self.hop_counter = 0;
+        Ok(())
+    }
+
+    // Springs moved to the frequencies of "ref_grid" in the running threads. Damping and integrator are kept.
+    // The springs are at rest and the next block starts hop 0 as reset().
+    pub fn set_grid(&mut self, ref_grid: &FrequencyGrid) -> Result<()> {
+        let (reachable_spring_num, split_vec) = split_springs(ref_grid, self.data_period, self.thread_per_ch, &self.config)?;
+        // Springs of every thread are checked before any thread is retuned.
+        let mut split_resonance_vec: Vec<SplitResonance<T>> = Vec::with_capacity(self.to_resonance_sender_vec.len());
+        for ch_idx in 0..self.ch_num {
+            for (split_spring_vec, split_damping_vec) in split_vec.iter().cloned() {
+                split_resonance_vec.push(SplitResonance::new(split_spring_vec, split_damping_vec, self.data_period, self.config.integrator, self.hop, ch_idx)?);
+            }
+        }
+        for (sender, split_resonance) in self.to_resonance_sender_vec.iter().zip(split_resonance_vec) {
+            sender.send(ResonanceRequest {
+                    request_type: ResonanceRequestType::Retune,
+                    op_sound_buffer: None,
+                    op_split_resonance: Some(split_resonance),
+            })?;
+        }
+        self.grid = ref_grid.clone();
+        self.reachable_spring_num = reachable_spring_num;
+        self.hop_counter = 0;
+        Ok(())
+    }
+
+    // set_grid() of A0 to A8 from "pitch_standard_frequency". (A4)
+    pub fn set_pitch_standard(&mut self, pitch_standard_frequency: f64) -> Result<()> {
+        self.set_grid(&FrequencyGrid::spn(pitch_standard_frequency))
+    }
+
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    // Frequencies and labels of the springs. (index of the energy)
+    pub fn grid(&self) -> &FrequencyGrid {
+        &self.grid
+    }
+
+    // Springs from the lowest up to (not including) this index are calculated.
+    // Higher springs are above Nyquist or would make the integration unstable.
+    pub fn reachable_spring_num(&self) -> usize {
+        self.reachable_spring_num
     }
 
     // manual exit
@@ -265,7 +756,8 @@ impl Resonance {
         for sender in &*self.to_resonance_sender_vec {
             match sender.send(ResonanceRequest {
                     request_type: ResonanceRequestType::Exit,
-                    sound_data_arc: None,
+                    op_sound_buffer: None,
+                    op_split_resonance: None,
             }) {
                 Ok(_) => {
                     // Ok!
@@ -321,12 +813,13 @@ impl Resonance {
 }
 
 // auto exit
-impl Drop for Resonance {
+impl<T: Float> Drop for Resonance<T> {
     fn drop(&mut self) {
         for sender in &*self.to_resonance_sender_vec {
             match sender.send(ResonanceRequest {
                     request_type: ResonanceRequestType::Exit,
-                    sound_data_arc: None,
+                    op_sound_buffer: None,
+                    op_split_resonance: None,
             }) {
                 Ok(_) => {
                     // Ok!
@@ -0,0 +1,90 @@
+use std::f64::consts::PI;
+
+use super::error::*;
+use super::{Resonance, ResonanceConfig, Damping, Integrator, FrequencyGrid, Sample, AudioBuffer};
+
+#[cfg(test)]
+mod tests {
+    use super::estimate_tuning;
+    use super::super::AudioBuffer;
+    use std::f64::consts::PI;
+
+    // A minor chord and its octaves in "pitch_standard_frequency".
+    fn chord(pitch_standard_frequency: f64, sampling_rate: usize) -> AudioBuffer {
+        let hz_vec: Vec<f64> = [-12, -9, -5, 0, 3, 7].iter().map(|semitone| pitch_standard_frequency * 2.0_f64.powf(f64::from(*semitone) / 12.0)).collect();
+        let sound_vec: Vec<f64> = (0..(sampling_rate * 2)).map(|i| {
+            let t = i as f64 / sampling_rate as f64;
+            hz_vec.iter().map(|hz| 0.1 * (2.0 * PI * hz * t).sin()).sum()
+        }).collect();
+        AudioBuffer::new(vec![sound_vec.clone(), sound_vec], sampling_rate).unwrap()
+    }
+
+    #[test]
+    fn test_estimate_tuning() {
+        // A4 of 415Hz is 101.3 cents below 440Hz, so -1.3 cents from A-flat of 440Hz.
+        for hz in [415.0, 442.0, 440.0, 432.0].iter() {
+            let cents = 1200.0 * (hz / 440.0_f64).log2();
+            let folded_cents = cents - 100.0 * (cents / 100.0).round();
+            let tuning = estimate_tuning(&chord(*hz, 8000), 440.0).unwrap();
+            assert_eq!(tuning.reference_frequency, 440.0);
+            assert!((tuning.cents_offset - folded_cents).abs() < 2.0, "{} {:?}", hz, tuning);
+            assert!((tuning.pitch_standard_frequency - 440.0 * 2.0_f64.powf(folded_cents / 1200.0)).abs() < 0.5, "{} {:?}", hz, tuning);
+        }
+        let tuning = estimate_tuning(&chord(415.0, 8000), 440.0).unwrap();
+        assert!((tuning.cents_offset + 1.3).abs() < 2.0, "{:?}", tuning);
+
+        let silence = AudioBuffer::new(vec![vec![0.0; 8000]], 8000).unwrap();
+        assert!(estimate_tuning(&silence, 440.0).is_err());
+    }
+}
+
+// Resolution of the analysis grid in cents
+const TUNING_STEP_CENTS: f64 = 10.0;
+const TUNING_BIN_NUM: usize = 10; // 100 cents / TUNING_STEP_CENTS
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TuningEstimate {
+    pub reference_frequency: f64,       // the given A4 of the 12-TET reference
+    pub cents_offset: f64,              // from the nearest semitone of the reference (-50 to +50 cents)
+    pub pitch_standard_frequency: f64,  // reference moved by "cents_offset" to put the springs in tune. Not the absolute A4 of the sound.
+}
+
+// Tuning of "sound_buffer" against 12-TET of "pitch_standard_frequency".
+// The energy of springs every 10 cents is folded into a semitone, and its peak is taken by the circular mean.
+// The offset is from the nearest semitone, e.g. A4 of 415Hz against 440Hz is -1.3 cents (439.7Hz), not -101.3 cents.
+// A sound a whole semitone off is in tune with its neighbouring note, so the absolute A4 is not known without the key.
+// Pass a view of the first seconds to analyse only them.
+pub fn estimate_tuning<S: Sample>(sound_buffer: &AudioBuffer<S>, pitch_standard_frequency: f64) -> Result<TuningEstimate> {
+    let grid = FrequencyGrid::edo(pitch_standard_frequency, 1200 / TUNING_STEP_CENTS as usize, 0.0, pitch_standard_frequency / 16.0, pitch_standard_frequency * 16.0)?;
+    // Energy of every 10ms in blocks of a second
+    let sampling_rate = sound_buffer.sampling_rate();
+    let config = ResonanceConfig { damping: Damping::BandwidthCents(2.0 * TUNING_STEP_CENTS), integrator: Integrator::Exact, hop: (sampling_rate / 100).max(1) };
+    let mut resonance = Resonance::<f64>::with_grid_as(&grid, sampling_rate, sound_buffer.channel_num(), 4, &config)?;
+
+    let mut bin_vec = [0.0; TUNING_BIN_NUM];
+    for block_stt in (0..sound_buffer.frame_num()).step_by(sampling_rate.max(1)) {
+        let spectrum_frame = resonance.process_block_from(&sound_buffer.view(block_stt, block_stt + sampling_rate))?;
+        for spring_idx in 0..resonance.reachable_spring_num() {
+            let cents = 1200.0 * (grid.hz(spring_idx) / pitch_standard_frequency).log2();
+            let bin_idx = ((cents / TUNING_STEP_CENTS).round() as i64).rem_euclid(TUNING_BIN_NUM as i64) as usize;
+            bin_vec[bin_idx] += spectrum_frame.energy_vec.iter().map(|ch| ch[spring_idx].iter().sum::<f64>()).sum::<f64>();
+        }
+    }
+    // Floor of the untuned energy is not counted.
+    let floor = bin_vec.iter().cloned().fold(f64::INFINITY, f64::min);
+    let (mut cos_sum, mut sin_sum) = (0.0, 0.0);
+    for (bin_idx, energy) in bin_vec.iter().enumerate() {
+        let angle = 2.0 * PI * bin_idx as f64 / TUNING_BIN_NUM as f64;
+        cos_sum += (energy - floor) * angle.cos();
+        sin_sum += (energy - floor) * angle.sin();
+    }
+    if !(cos_sum.hypot(sin_sum) > 0.0 && cos_sum.is_finite() && sin_sum.is_finite()) {
+        return Err(ResonanceError::new("No tone to estimate the tuning!"));
+    }
+    let cents_offset = 100.0 * sin_sum.atan2(cos_sum) / (2.0 * PI);
+    Ok(TuningEstimate {
+        reference_frequency: pitch_standard_frequency,
+        cents_offset,
+        pitch_standard_frequency: pitch_standard_frequency * 2.0_f64.powf(cents_offset / 1200.0),
+    })
+}
@@ -0,0 +1,72 @@
+// Decode speed of the copying path (WavFile) and the memory mapped path (MappedWav).
+// cargo bench -p wavfile
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+extern crate wavfile;
+use wavfile::*;
+
+const REPEAT: usize = 10;
+
+fn bench<F: FnMut() -> f64>(label: &str, mut func: F) -> Duration {
+    let mut check = 0.0;
+    // Warm up the page cache.
+    check += func();
+    let stt = Instant::now();
+    for _ in 0..REPEAT {
+        check += func();
+    }
+    let elapsed = stt.elapsed() / REPEAT as u32;
+    println!("{:<40} {:>10.3} ms  (check {:.3})", label, elapsed.as_secs_f64() * 1000.0, check);
+    elapsed
+}
+
+fn write_test_file(path: &Path, fmt: &Fmt, sec: usize) {
+    let frame_num = fmt.sampling_rate * sec;
+    let mut ch_vec: Vec<Vec<f64>> = Vec::new();
+    for ch in 0..fmt.channel {
+        let freq = 440.0 * (ch + 1) as f64;
+        ch_vec.push((0..frame_num).map(|idx| 0.5 * (2.0 * std::f64::consts::PI * freq * idx as f64 / fmt.sampling_rate as f64).sin()).collect());
+    }
+    let mut wav_file = WavFile::new();
+    wav_file.update_wav_audio(&to_wav_audio(&ch_vec, fmt).unwrap()).unwrap();
+    wav_file.save_as(path).unwrap();
+}
+
+fn main() {
+    for (id, bits) in [(WAVE_FORMAT_PCM, 16), (WAVE_FORMAT_PCM, 24), (WAVE_FORMAT_IEEE_FLOAT, 32)].iter() {
+        let fmt = Fmt::new(*id, 2, 48000, *bits);
+        let path = std::env::temp_dir().join(format!("wavfile_bench_{}_{}.wav", id, bits));
+        write_test_file(&path, &fmt, 60);
+        println!("-- 60s stereo 48kHz Format ID:{} Bits:{} --", id, bits);
+
+        let copy_vec = bench("WavFile open + to_channel_vec", || {
+            let mut wav_file = WavFile::new();
+            wav_file.open(&path).unwrap();
+            to_channel_vec(&wav_file.get_wav_audio().unwrap()).unwrap()[0][1000]
+        });
+        let mapped_vec = bench("MappedWav open + to_channel_vec", || {
+            let mapped_wav = MappedWav::open(&path).unwrap();
+            mapped_wav.frame_view().unwrap().to_channel_vec()[0][1000]
+        });
+        println!("{:<40} {:>10.1} x", "Gain (to_channel_vec)", copy_vec.as_secs_f64() / mapped_vec.as_secs_f64());
+
+        // Same peak on both paths. WavFile has to copy the channels to scan them.
+        let copy_peak = bench("WavFile open + to_channel_vec peak", || {
+            let mut wav_file = WavFile::new();
+            wav_file.open(&path).unwrap();
+            to_channel_vec(&wav_file.get_wav_audio().unwrap()).unwrap().iter()
+                .map(|ch| ch.iter().fold(0.0, |peak: f64, val| peak.max(val.abs())))
+                .sum()
+        });
+        let mapped_peak = bench("MappedWav open + channel_iter peak", || {
+            let mapped_wav = MappedWav::open(&path).unwrap();
+            let frame_view = mapped_wav.frame_view().unwrap();
+            (0..frame_view.channel())
+                .map(|ch| frame_view.channel_iter(ch).fold(0.0, |peak: f64, val| peak.max(val.abs())))
+                .sum()
+        });
+        println!("{:<40} {:>10.1} x", "Gain (peak, no allocation)", copy_peak.as_secs_f64() / mapped_peak.as_secs_f64());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
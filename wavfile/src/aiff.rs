@@ -0,0 +1,640 @@
+use std::path::{Path, PathBuf};
+use std::convert::TryFrom;
+use std::io::prelude::*;
+use std::io::BufReader;
+
+use std::fs::File;
+
+use super::error::*;
+use super::bytes::{read_u16_be, read_u32_be, get_text};
+use super::{Fmt, WavAudio, WavFile, SubChunk, CuePoint, Smpl, SampleLoop, InfoTags, fmt_check};
+use super::{WAVE_FORMAT_PCM, WAVE_FORMAT_IEEE_FLOAT, INFO_TITLE, INFO_ARTIST, INFO_COMMENT, INFO_COPYRIGHT};
+
+#[cfg(test)]
+mod tests {
+    use super::{AiffFile, f64_to_ext80, ext80_to_f64};
+    use super::super::{WavFile, Fmt, CuePoint, Smpl, SampleLoop, InfoTags, INFO_TITLE, INFO_ARTIST, INFO_COMMENT, to_wav_audio, to_channel_vec};
+
+    fn test_ch_vec() -> Vec<Vec<f64>> {
+        vec![vec![0.5, 0.25, -0.25, -1.0, 0.0], vec![-0.5, -0.25, 0.25, 0.75, 0.0]]
+    }
+
+    #[test]
+    fn test_ext80() {
+        for val in [8000.0, 44100.0, 48000.0, 96000.0, 22050.0, 0.0, 1.5].iter() {
+            assert_eq!(ext80_to_f64(&f64_to_ext80(*val)), *val);
+        }
+        // 44100Hz as written by Apple tools
+        assert_eq!(f64_to_ext80(44100.0), [0x40, 0x0E, 0xAC, 0x44, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_aiff_round_trip() {
+        for (id, bits) in [(1, 8), (1, 16), (1, 24), (1, 32), (3, 32), (3, 64)].iter() {
+            let wav_audio = to_wav_audio(&test_ch_vec(), &Fmt::new(*id, 2, 44100, *bits)).unwrap();
+            let mut aiff_file = AiffFile::new();
+            aiff_file.update_wav_audio(&wav_audio).unwrap();
+            let buf = aiff_file.to_bytes().unwrap();
+            assert_eq!(&buf[0..4], b"FORM");
+            assert_eq!(&buf[8..12], if *id == 1 { b"AIFF" } else { b"AIFC" });
+
+            let mut read_file = AiffFile::new();
+            read_file.open_bytes(buf).unwrap();
+            let read_audio = read_file.get_wav_audio().unwrap();
+            assert_eq!(read_audio.fmt.sampling_rate, 44100);
+            assert_eq!(read_audio.fmt.bits, *bits);
+            assert_eq!(read_audio.data, wav_audio.data);
+        }
+    }
+
+    #[test]
+    fn test_aiff_valid_bits() {
+        // 24bit in 32bit is written as 3 bytes of 24bit.
+        let mut fmt = Fmt::new(1, 2, 44100, 32);
+        fmt.valid_bits = 24;
+        let wav_audio = to_wav_audio(&test_ch_vec(), &fmt).unwrap();
+        let mut aiff_file = AiffFile::new();
+        aiff_file.update_wav_audio(&wav_audio).unwrap();
+        let ssnd = aiff_file.sub_chunks.iter().find(|sub_chunk| sub_chunk.name == *b"SSND").unwrap();
+        assert_eq!(ssnd.data.len(), 8 + 5 * 2 * 3);
+        assert_eq!(&ssnd.data[8..11], &[0x40, 0x00, 0x00]);
+
+        let mut read_file = AiffFile::new();
+        read_file.open_bytes(aiff_file.to_bytes().unwrap()).unwrap();
+        let read_audio = read_file.get_wav_audio().unwrap();
+        assert_eq!((read_audio.fmt.bits, read_audio.fmt.valid_bits), (24, 24));
+        assert_eq!(to_channel_vec(&read_audio).unwrap(), test_ch_vec());
+    }
+
+    #[test]
+    fn test_aiff_big_endian() {
+        // 16bit mono 2 frames: 0x1234, -2
+        let mut buf = b"FORM".to_vec();
+        buf.extend_from_slice(&(4 + 8 + 18 + 8 + 12_u32).to_be_bytes());
+        buf.extend_from_slice(b"AIFFCOMM");
+        buf.extend_from_slice(&18_u32.to_be_bytes());
+        buf.extend_from_slice(&[0, 1, 0, 0, 0, 2, 0, 16]);
+        buf.extend_from_slice(&f64_to_ext80(8000.0));
+        buf.extend_from_slice(b"SSND");
+        buf.extend_from_slice(&12_u32.to_be_bytes());
+        buf.extend_from_slice(&[0; 8]);
+        buf.extend_from_slice(&[0x12, 0x34, 0xFF, 0xFE]);
+        let mut aiff_file = AiffFile::new();
+        aiff_file.open_bytes(buf).unwrap();
+        let wav_audio = aiff_file.get_wav_audio().unwrap();
+        assert_eq!(wav_audio.data, vec![0x34, 0x12, 0xFE, 0xFF]);
+
+        // Little endian "sowt" of AIFF-C
+        let mut aiff_file = AiffFile::new();
+        aiff_file.update_wav_audio(&wav_audio).unwrap();
+        let comm = aiff_file.sub_chunks.iter_mut().find(|sub_chunk| sub_chunk.name == *b"COMM").unwrap();
+        comm.data.extend_from_slice(b"sowt\x00\x00");
+        comm.body_size = comm.data.len();
+        let ssnd = aiff_file.sub_chunks.iter_mut().find(|sub_chunk| sub_chunk.name == *b"SSND").unwrap();
+        ssnd.data[8..].copy_from_slice(&[0x34, 0x12, 0xFE, 0xFF]);
+        let buf = aiff_file.to_bytes().unwrap();
+        assert_eq!(&buf[8..12], b"AIFC");
+        aiff_file.open_bytes(buf).unwrap();
+        assert_eq!(aiff_file.get_wav_audio().unwrap().data, wav_audio.data);
+
+        // 8bit "sowt" is signed as well.
+        let mut aiff_file = AiffFile::new();
+        aiff_file.update_wav_audio(&to_wav_audio(&test_ch_vec(), &Fmt::new(1, 2, 8000, 8)).unwrap()).unwrap();
+        let comm = aiff_file.sub_chunks.iter_mut().find(|sub_chunk| sub_chunk.name == *b"COMM").unwrap();
+        comm.data.extend_from_slice(b"sowt\x00\x00");
+        comm.body_size = comm.data.len();
+        let ssnd = aiff_file.sub_chunks.iter_mut().find(|sub_chunk| sub_chunk.name == *b"SSND").unwrap();
+        assert_eq!(&ssnd.data[8..10], &[0x40, 0xC0]);
+        let buf = aiff_file.to_bytes().unwrap();
+        aiff_file.open_bytes(buf).unwrap();
+        let wav_audio_8bit = aiff_file.get_wav_audio().unwrap();
+        assert_eq!(&wav_audio_8bit.data[..2], &[0xC0, 0x40]);
+        assert_eq!(to_channel_vec(&wav_audio_8bit).unwrap(), test_ch_vec());
+
+        // Unsupported compression
+        let comm = aiff_file.sub_chunks.iter_mut().find(|sub_chunk| sub_chunk.name == *b"COMM").unwrap();
+        comm.data[18..22].copy_from_slice(b"ulaw");
+        assert!(aiff_file.get_wav_audio().is_err());
+    }
+
+    #[test]
+    fn test_wav_conversion() {
+        let mut wav_file = WavFile::new();
+        wav_file.update_wav_audio(&to_wav_audio(&test_ch_vec(), &Fmt::new(1, 2, 48000, 24)).unwrap()).unwrap();
+        let mut info_tags = InfoTags::new();
+        info_tags.set(INFO_TITLE, "Take 1");
+        info_tags.set(INFO_ARTIST, "Parrot");
+        info_tags.set(INFO_COMMENT, "Mac studio");
+        wav_file.set_info_tags(&info_tags).unwrap();
+        let mut cue = CuePoint::new(1, 2);
+        cue.label = Some("Verse".to_string());
+        wav_file.set_cue_points(&[cue]).unwrap();
+        let mut smpl = Smpl::new(48000);
+        smpl.unity_note = 62;
+        smpl.pitch_fraction = 0x8000_0000;
+        smpl.loops.push(SampleLoop { id: 0, loop_type: 0, start: 1, end: 3, fraction: 0, play_count: 0 });
+        wav_file.set_smpl(&smpl).unwrap();
+
+        let aiff_file = AiffFile::from_wav_file(&wav_file).unwrap();
+        let path = std::env::temp_dir().join("wavfile_aiff_test.aiff");
+        let mut aiff_file = aiff_file;
+        aiff_file.save_as(&path).unwrap();
+        let mut read_file = AiffFile::new();
+        read_file.open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let read_wav = read_file.to_wav_file().unwrap();
+        assert_eq!(to_channel_vec(&read_wav.get_wav_audio().unwrap()).unwrap(), test_ch_vec());
+        assert_eq!(read_wav.info_tags().unwrap().unwrap(), info_tags);
+        let cue_vec = read_wav.cue_points().unwrap();
+        assert_eq!(cue_vec[0].position, 2);
+        assert_eq!(cue_vec[0].label.as_deref(), Some("Verse"));
+        let read_smpl = read_wav.smpl().unwrap().unwrap();
+        assert_eq!(read_smpl.unity_note, 62);
+        assert_eq!(read_smpl.pitch_fraction, 0x8000_0000);
+        assert_eq!((read_smpl.loops[0].start, read_smpl.loops[0].end), (1, 3));
+    }
+}
+
+const AIFC_VERSION_1: u32 = 0xA280_5140;
+const MARK_LOOP_START: &str = "beg loop";
+const MARK_LOOP_END: &str = "end loop";
+
+// AIFF / AIFF-C (Audio Interchange File Format) file.
+// Chunk bodies are kept as they are in the file (big endian), audio is exchanged as WavAudio.
+pub struct AiffFile {
+    pub file_path: PathBuf,
+    pub size: usize,
+    pub sub_chunks: Vec<SubChunk>
+}
+
+impl AiffFile {
+    pub fn new() -> AiffFile {
+        AiffFile {file_path:PathBuf::new(), size:0, sub_chunks:Vec::new()}
+    }
+
+    pub fn open(&mut self, file_path: &Path) -> Result<()> {
+        // -- Check Parameter --
+        if !file_path.is_file() {
+            return Err(WavFileError::NotFile { path: file_path.to_path_buf() });
+        }
+        let target_file = File::open(file_path)?;
+        let mut buf = Vec::new();
+        BufReader::new(&target_file).read_to_end(&mut buf)?;
+        self.open_bytes(buf)?;
+        self.file_path = file_path.to_path_buf();
+        Ok(())
+    }
+
+    pub fn open_bytes(&mut self, buf: Vec<u8>) -> Result<()> {
+        let file_size = buf.len();
+        if file_size < 12 {
+            return Err(WavFileError::TruncatedHeader { expected: 12, actual: file_size });
+        }
+        // "FORM"
+        let form_id = <[u8;4]>::try_from(&buf[0x00..0x04])?;
+        if form_id != [b'F',b'O',b'R',b'M'] {
+            return Err(WavFileError::NotAiff { id: form_id });
+        }
+        let form_size = usize::try_from(read_u32_be(&buf, 0x04)?)?;
+        if form_size != file_size - 8 {
+            return Err(WavFileError::RiffSize { expected: file_size - 8, actual: form_size });
+        }
+        // "AIFF" or "AIFC"
+        let form_type = <[u8;4]>::try_from(&buf[0x08..0x0c])?;
+        if form_type != [b'A',b'I',b'F',b'F'] && form_type != [b'A',b'I',b'F',b'C'] {
+            return Err(WavFileError::NotAiff { id: form_type });
+        }
+
+        let mut sub_chunks: Vec<SubChunk> = Vec::new();
+        let mut chunk_head_addr: usize = 0x0c;
+        while chunk_head_addr + 8 <= file_size {
+            let name = <[u8;4]>::try_from(&buf[chunk_head_addr..(chunk_head_addr + 4)])?;
+            let body_size = usize::try_from(read_u32_be(&buf, chunk_head_addr + 4)?)?;
+            let body_addr = chunk_head_addr + 8;
+            if file_size - body_addr < body_size {
+                return Err(WavFileError::TruncatedChunk { id: name, offset: chunk_head_addr, expected: body_size, actual: file_size - body_addr });
+            }
+            sub_chunks.push(SubChunk { name, body_size, data: buf[body_addr..(body_addr + body_size)].to_vec() });
+            // Odd size chunk is followed by a pad byte.
+            chunk_head_addr = body_addr + body_size + (body_size & 1);
+        }
+        self.size = file_size;
+        self.sub_chunks = sub_chunks;
+        Ok(())
+    }
+
+    pub fn save_as(&mut self, file_path: &Path) -> Result<()> {
+        // -- Check Parameter --
+        match file_path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if is_aiff_extension(ext) => {},
+            Some(_) => return Err(WavFileError::Extension { path: file_path.to_path_buf() }),
+            None => return Err(WavFileError::NotFile { path: file_path.to_path_buf() }),
+        }
+        let buf = self.to_bytes()?;
+        let mut target_file = File::create(file_path)?;
+        target_file.write_all(&buf)?;
+
+        // Update Self Infomation
+        self.file_path = file_path.to_path_buf();
+        self.size = buf.len();
+        Ok(())
+    }
+
+    // "AIFC" (with "FVER") when "COMM" has a compression type, "AIFF" otherwise.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let is_aifc = self.find_sub_chunk([b'C',b'O',b'M',b'M']).is_some_and(|comm| comm.data.len() > 18);
+        let mut body: Vec<u8> = Vec::new();
+        body.extend_from_slice(if is_aifc { b"AIFC" } else { b"AIFF" });
+        if is_aifc && self.find_sub_chunk([b'F',b'V',b'E',b'R']).is_none() {
+            push_chunk(&mut body, [b'F',b'V',b'E',b'R'], &AIFC_VERSION_1.to_be_bytes())?;
+        }
+        for sub_chunk in &self.sub_chunks {
+            if !is_aifc && sub_chunk.name == [b'F',b'V',b'E',b'R'] {
+                continue;
+            }
+            push_chunk(&mut body, sub_chunk.name, &sub_chunk.data)?;
+        }
+        let mut buf: Vec<u8> = b"FORM".to_vec();
+        buf.extend_from_slice(&u32::try_from(body.len())?.to_be_bytes());
+        buf.append(&mut body);
+        Ok(buf)
+    }
+
+    fn find_sub_chunk(&self, name: [u8;4]) -> Option<&SubChunk> {
+        self.sub_chunks.iter().find(|sub_chunk| sub_chunk.name == name)
+    }
+
+    // Replace the first "name" chunk, add it or remove it (no "op_data").
+    fn replace_sub_chunk(&mut self, name: [u8;4], op_data: Option<Vec<u8>>) {
+        let op_idx = self.sub_chunks.iter().position(|sub_chunk| sub_chunk.name == name);
+        match (op_idx, op_data) {
+            (Some(idx), Some(data)) => { self.sub_chunks[idx] = SubChunk { name, body_size: data.len(), data }; }
+            (Some(idx), None) => { self.sub_chunks.remove(idx); }
+            (None, Some(data)) => { self.sub_chunks.push(SubChunk { name, body_size: data.len(), data }); }
+            (None, None) => {}
+        }
+    }
+
+    // "COMM" and "SSND" as little endian interleaved WAV data.
+    pub fn get_wav_audio(&self) -> Result<WavAudio> {
+        let comm = self.find_sub_chunk([b'C',b'O',b'M',b'M']).ok_or(WavFileError::MissingChunk { id: [b'C',b'O',b'M',b'M'] })?;
+        let (fmt, frame_num, encoding) = get_comm(&comm.data)?;
+        let size = fmt.bits / 8;
+        let data_size = frame_num * fmt.channel * size;
+        let sound = match self.find_sub_chunk([b'S',b'S',b'N',b'D']) {
+            Some(ssnd) => {
+                if ssnd.data.len() < 8 {
+                    return Err(WavFileError::TruncatedChunk { id: [b'S',b'S',b'N',b'D'], offset: 0, expected: 8, actual: ssnd.data.len() });
+                }
+                let offset = 8 + usize::try_from(read_u32_be(&ssnd.data, 0)?)?;
+                if ssnd.data.len() < offset || ssnd.data.len() - offset < data_size {
+                    return Err(WavFileError::TruncatedChunk { id: [b'S',b'S',b'N',b'D'], offset: 0, expected: offset + data_size, actual: ssnd.data.len() });
+                }
+                &ssnd.data[offset..(offset + data_size)]
+            }
+            // No "SSND" is allowed for no sample frames.
+            None if frame_num == 0 => &[],
+            None => return Err(WavFileError::MissingChunk { id: [b'S',b'S',b'N',b'D'] }),
+        };
+        let data = match encoding {
+            Encoding::BigEndian => swap_bytes(sound, size, fmt.id == WAVE_FORMAT_PCM),
+            // 8bit is in one byte, so only the sign is converted.
+            Encoding::LittleEndian if size == 1 => swap_bytes(sound, size, true),
+            Encoding::LittleEndian => sound.to_vec(),
+        };
+        Ok(WavAudio { fmt, data })
+    }
+
+    // PCM is written as "AIFF", IEEE Float as "AIFC" with "fl32"/"fl64".
+    pub fn update_wav_audio(&mut self, ref_wav_audio: &WavAudio) -> Result<()> {
+        fmt_check(&ref_wav_audio.fmt)?;
+        let size = ref_wav_audio.fmt.bits / 8;
+        let frame_num = ref_wav_audio.data.len() / (ref_wav_audio.fmt.channel * size);
+        let comm = set_comm(&ref_wav_audio.fmt, frame_num)?;
+        let data = &ref_wav_audio.data[..(frame_num * ref_wav_audio.fmt.channel * size)];
+        let mut ssnd: Vec<u8> = vec![0; 8];
+        if ref_wav_audio.fmt.id == WAVE_FORMAT_PCM && ref_wav_audio.fmt.valid_bits.div_ceil(8) < size {
+            // "COMM" has the valid bits, so the samples are in the bytes of the valid bits (24bit in 32bit as 3 bytes).
+            let valid_size = ref_wav_audio.fmt.valid_bits.div_ceil(8);
+            ssnd.append(&mut swap_bytes(&pack_valid_bytes(data, size, valid_size), valid_size, true));
+        }
+        else {
+            ssnd.append(&mut swap_bytes(data, size, ref_wav_audio.fmt.id == WAVE_FORMAT_PCM));
+        }
+        if comm.len() == 18 {
+            self.replace_sub_chunk([b'F',b'V',b'E',b'R'], None);
+        }
+        self.replace_sub_chunk([b'C',b'O',b'M',b'M'], Some(comm));
+        self.replace_sub_chunk([b'S',b'S',b'N',b'D'], Some(ssnd));
+        Ok(())
+    }
+
+    // "NAME", "AUTH", "(c) " and "ANNO" as INFO tags.
+    pub fn info_tags(&self) -> InfoTags {
+        let mut info_tags = InfoTags::new();
+        for sub_chunk in &self.sub_chunks {
+            if let Some(id) = info_id(sub_chunk.name) {
+                let text = get_text(&sub_chunk.data);
+                match info_tags.get(id) {
+                    // Several "ANNO" are joined.
+                    Some(prev_text) => {
+                        let joined = format!("{}\n{}", prev_text, text);
+                        info_tags.set(id, &joined);
+                    }
+                    None => info_tags.set(id, &text),
+                }
+            }
+        }
+        info_tags
+    }
+
+    pub fn set_info_tags(&mut self, ref_info_tags: &InfoTags) {
+        for name in [*b"NAME", *b"AUTH", *b"(c) ", *b"ANNO"].iter() {
+            self.sub_chunks.retain(|sub_chunk| sub_chunk.name != *name);
+            if let Some(text) = info_id(*name).and_then(|id| ref_info_tags.get(id)) {
+                self.replace_sub_chunk(*name, Some(text.as_bytes().to_vec()));
+            }
+        }
+    }
+
+    // Markers ("MARK") as cue points. Labels are the marker names.
+    pub fn cue_points(&self) -> Result<Vec<CuePoint>> {
+        match self.find_sub_chunk([b'M',b'A',b'R',b'K']) {
+            Some(sub_chunk) => get_mark(&sub_chunk.data),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn set_cue_points(&mut self, cue_points: &[CuePoint]) -> Result<()> {
+        let op_data = if cue_points.is_empty() { None } else { Some(set_mark(cue_points)?) };
+        self.replace_sub_chunk([b'M',b'A',b'R',b'K'], op_data);
+        Ok(())
+    }
+
+    // Instrument ("INST") as "smpl". The sustain loop is the first loop.
+    pub fn smpl(&self) -> Result<Option<Smpl>> {
+        let inst = match self.find_sub_chunk([b'I',b'N',b'S',b'T']) {
+            Some(sub_chunk) => &sub_chunk.data,
+            None => return Ok(None),
+        };
+        if inst.len() < 20 {
+            return Err(WavFileError::TruncatedChunk { id: [b'I',b'N',b'S',b'T'], offset: 0, expected: 20, actual: inst.len() });
+        }
+        let sampling_rate = match self.find_sub_chunk([b'C',b'O',b'M',b'M']) {
+            Some(comm) => get_comm(&comm.data)?.0.sampling_rate,
+            None => 0,
+        };
+        let mut smpl = Smpl::new(sampling_rate);
+        // Detune in cents (-50..50) is the fraction above the note.
+        let base_note = i32::from(inst[0] as i8);
+        let detune = i32::from(inst[1] as i8);
+        let (note, cents) = if detune < 0 { (base_note - 1, detune + 100) } else { (base_note, detune) };
+        smpl.unity_note = usize::try_from(note.max(0))?;
+        smpl.pitch_fraction = usize::try_from(u64::try_from(cents)? * 0x1_0000_0000 / 100)?;
+
+        let cue_vec = self.cue_points()?;
+        let marker_position = |marker_id: u16| cue_vec.iter().find(|cue| cue.id == usize::from(marker_id)).map(|cue| cue.position);
+        // Sustain loop and release loop: playMode, beginLoop, endLoop
+        for loop_addr in [8, 14].iter() {
+            let play_mode = read_u16_be(inst, *loop_addr)?;
+            let begin = marker_position(read_u16_be(inst, loop_addr + 2)?);
+            let end = marker_position(read_u16_be(inst, loop_addr + 4)?);
+            if let (1..=2, Some(start), Some(end)) = (play_mode, begin, end) {
+                if start < end {
+                    // AIFF loop end is the marker after the last frame.
+                    smpl.loops.push(SampleLoop { id: smpl.loops.len(), loop_type: usize::from(play_mode - 1), start, end: end - 1, fraction: 0, play_count: 0 });
+                }
+            }
+        }
+        Ok(Some(smpl))
+    }
+
+    // Writes "INST" and adds loop markers to "MARK".
+    pub fn set_smpl(&mut self, ref_smpl: &Smpl) -> Result<()> {
+        let mut cue_vec = self.cue_points()?;
+        cue_vec.retain(|cue| cue.label.as_deref() != Some(MARK_LOOP_START) && cue.label.as_deref() != Some(MARK_LOOP_END));
+        let cents = i64::try_from(ref_smpl.pitch_fraction)? * 100 / 0x1_0000_0000;
+        let (base_note, detune) = if cents > 50 { (i64::try_from(ref_smpl.unity_note)? + 1, cents - 100) } else { (i64::try_from(ref_smpl.unity_note)?, cents) };
+        let mut inst: Vec<u8> = vec![i8::try_from(base_note)? as u8, i8::try_from(detune)? as u8, 0, 127, 1, 127, 0, 0];
+        for op_loop in [ref_smpl.loops.first(), ref_smpl.loops.get(1)].iter() {
+            match op_loop {
+                Some(sample_loop) if sample_loop.loop_type <= 1 => {
+                    let next_id = cue_vec.iter().map(|cue| cue.id).max().unwrap_or(0) + 1;
+                    let mut start = CuePoint::new(next_id, sample_loop.start);
+                    start.label = Some(MARK_LOOP_START.to_string());
+                    let mut end = CuePoint::new(next_id + 1, sample_loop.end + 1);
+                    end.label = Some(MARK_LOOP_END.to_string());
+                    cue_vec.push(start);
+                    cue_vec.push(end);
+                    inst.extend_from_slice(&u16::try_from(sample_loop.loop_type + 1)?.to_be_bytes());
+                    inst.extend_from_slice(&u16::try_from(next_id)?.to_be_bytes());
+                    inst.extend_from_slice(&u16::try_from(next_id + 1)?.to_be_bytes());
+                }
+                _ => inst.extend_from_slice(&[0; 6]),
+            }
+        }
+        self.set_cue_points(&cue_vec)?;
+        self.replace_sub_chunk([b'I',b'N',b'S',b'T'], Some(inst));
+        Ok(())
+    }
+
+    // WavFile with the audio, INFO tags, cue points and "smpl".
+    pub fn to_wav_file(&self) -> Result<WavFile> {
+        let mut wav_file = WavFile::new();
+        wav_file.update_wav_audio(&self.get_wav_audio()?)?;
+        wav_file.set_info_tags(&self.info_tags())?;
+        let op_smpl = self.smpl()?;
+        let mut cue_vec = self.cue_points()?;
+        if op_smpl.is_some() {
+            cue_vec.retain(|cue| cue.label.as_deref() != Some(MARK_LOOP_START) && cue.label.as_deref() != Some(MARK_LOOP_END));
+        }
+        wav_file.set_cue_points(&cue_vec)?;
+        if let Some(smpl) = op_smpl {
+            wav_file.set_smpl(&smpl)?;
+        }
+        Ok(wav_file)
+    }
+
+    // AiffFile with the audio, INFO tags, cue points and "smpl" of "ref_wav_file".
+    pub fn from_wav_file(ref_wav_file: &WavFile) -> Result<AiffFile> {
+        let mut aiff_file = AiffFile::new();
+        aiff_file.update_wav_audio(&ref_wav_file.get_wav_audio()?)?;
+        if let Some(info_tags) = ref_wav_file.info_tags()? {
+            aiff_file.set_info_tags(&info_tags);
+        }
+        aiff_file.set_cue_points(&ref_wav_file.cue_points()?)?;
+        if let Some(smpl) = ref_wav_file.smpl()? {
+            aiff_file.set_smpl(&smpl)?;
+        }
+        Ok(aiff_file)
+    }
+}
+
+impl Default for AiffFile {
+    fn default() -> AiffFile {
+        AiffFile::new()
+    }
+}
+
+// ".aif", ".aiff" or ".aifc"
+pub fn is_aiff_extension(ext: &str) -> bool {
+    ["aif", "aiff", "aifc"].iter().any(|aiff_ext| ext.eq_ignore_ascii_case(aiff_ext))
+}
+
+enum Encoding {
+    BigEndian,
+    LittleEndian,
+}
+
+fn push_chunk(buf: &mut Vec<u8>, name: [u8;4], data: &[u8]) -> Result<()> {
+    buf.extend_from_slice(&name);
+    buf.extend_from_slice(&u32::try_from(data.len())?.to_be_bytes());
+    buf.extend_from_slice(data);
+    if data.len() & 1 == 1 {
+        buf.push(0);
+    }
+    Ok(())
+}
+
+// Reverse the byte order of each sample. 8bit PCM is signed in AIFF and unsigned in WAV.
+fn swap_bytes(data: &[u8], size: usize, is_pcm: bool) -> Vec<u8> {
+    let mut swapped: Vec<u8> = Vec::with_capacity(data.len());
+    for sample in data.chunks_exact(size) {
+        if size == 1 && is_pcm {
+            swapped.push(sample[0] ^ 0x80);
+        }
+        else {
+            swapped.extend(sample.iter().rev());
+        }
+    }
+    swapped
+}
+
+// Upper "valid_size" bytes of each little endian sample. (Samples are left-justified in WAV.)
+fn pack_valid_bytes(data: &[u8], size: usize, valid_size: usize) -> Vec<u8> {
+    data.chunks_exact(size).flat_map(|sample| sample[(size - valid_size)..].iter().cloned()).collect()
+}
+
+// 80bit IEEE 754 extended precision (sample rate of "COMM")
+fn ext80_to_f64(bytes: &[u8; 10]) -> f64 {
+    let sign = if bytes[0] & 0x80 == 0 { 1.0 } else { -1.0 };
+    let exponent = i32::from(u16::from_be_bytes([bytes[0] & 0x7F, bytes[1]]));
+    let mantissa = u64::from_be_bytes([bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7], bytes[8], bytes[9]]);
+    if exponent == 0 && mantissa == 0 {
+        return 0.0;
+    }
+    sign * mantissa as f64 * 2.0_f64.powi(exponent - 16383 - 63)
+}
+
+fn f64_to_ext80(val: f64) -> [u8; 10] {
+    let mut bytes = [0; 10];
+    if val == 0.0 {
+        return bytes;
+    }
+    let bits = val.to_bits();
+    let exponent = ((bits >> 52) & 0x7FF) as u16 - 1023 + 16383;
+    let mantissa = (1 << 63) | ((bits & 0x000F_FFFF_FFFF_FFFF) << 11);
+    bytes[0..2].copy_from_slice(&(exponent | if val < 0.0 { 0x8000 } else { 0 }).to_be_bytes());
+    bytes[2..10].copy_from_slice(&mantissa.to_be_bytes());
+    bytes
+}
+
+// "COMM": channels, sample frames, sample size, sample rate (+ compression type and name of AIFF-C)
+fn get_comm(ref_chunk_body: &[u8]) -> Result<(Fmt, usize, Encoding)> {
+    if ref_chunk_body.len() < 18 {
+        return Err(WavFileError::FmtSize { expected: 18, actual: ref_chunk_body.len() });
+    }
+    let channel = usize::from(read_u16_be(ref_chunk_body, 0x00)?);
+    let frame_num = usize::try_from(read_u32_be(ref_chunk_body, 0x02)?)?;
+    let valid_bits = usize::from(read_u16_be(ref_chunk_body, 0x06)?);
+    let sampling_rate = ext80_to_f64(&<[u8;10]>::try_from(&ref_chunk_body[0x08..0x12])?).round();
+    let compression = if ref_chunk_body.len() >= 22 { <[u8;4]>::try_from(&ref_chunk_body[0x12..0x16])? } else { *b"NONE" };
+    let (id, bits, encoding) = match &compression {
+        b"NONE" | b"twos" => (WAVE_FORMAT_PCM, valid_bits.div_ceil(8) * 8, Encoding::BigEndian),
+        b"sowt" => (WAVE_FORMAT_PCM, valid_bits.div_ceil(8) * 8, Encoding::LittleEndian),
+        b"fl32" | b"FL32" => (WAVE_FORMAT_IEEE_FLOAT, 32, Encoding::BigEndian),
+        b"fl64" | b"FL64" => (WAVE_FORMAT_IEEE_FLOAT, 64, Encoding::BigEndian),
+        _ => return Err(WavFileError::Compression { id: compression }),
+    };
+    let fmt = Fmt {
+        id,
+        channel,
+        sampling_rate: sampling_rate as usize,
+        bits,
+        valid_bits: if id == WAVE_FORMAT_PCM { valid_bits } else { bits },
+        channel_mask: 0,
+    };
+    fmt_check(&fmt)?;
+    Ok((fmt, frame_num, encoding))
+}
+
+fn set_comm(ref_fmt: &Fmt, frame_num: usize) -> Result<Vec<u8>> {
+    let mut chunk_body: Vec<u8> = Vec::new();
+    chunk_body.extend_from_slice(&u16::try_from(ref_fmt.channel)?.to_be_bytes());
+    chunk_body.extend_from_slice(&u32::try_from(frame_num)?.to_be_bytes());
+    chunk_body.extend_from_slice(&u16::try_from(ref_fmt.valid_bits)?.to_be_bytes());
+    chunk_body.extend_from_slice(&f64_to_ext80(ref_fmt.sampling_rate as f64));
+    match (ref_fmt.id, ref_fmt.bits) {
+        (WAVE_FORMAT_PCM, _) => {},
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => chunk_body.extend_from_slice(b"fl32\x0cIEEE 32-bit\x00"),
+        (WAVE_FORMAT_IEEE_FLOAT, 64) => chunk_body.extend_from_slice(b"fl64\x0cIEEE 64-bit\x00"),
+        (WAVE_FORMAT_IEEE_FLOAT, bits) => return Err(WavFileError::Bits { bits }),
+        (format_id, _) => return Err(WavFileError::FormatId { format_id }),
+    }
+    Ok(chunk_body)
+}
+
+// Text chunk IDs and INFO tags
+fn info_id(name: [u8;4]) -> Option<[u8;4]> {
+    match &name {
+        b"NAME" => Some(INFO_TITLE),
+        b"AUTH" => Some(INFO_ARTIST),
+        b"(c) " => Some(INFO_COPYRIGHT),
+        b"ANNO" => Some(INFO_COMMENT),
+        _ => None,
+    }
+}
+
+fn get_mark(ref_chunk_body: &[u8]) -> Result<Vec<CuePoint>> {
+    let truncated = |addr: usize| WavFileError::TruncatedChunk { id: [b'M',b'A',b'R',b'K'], offset: addr, expected: addr + 7, actual: ref_chunk_body.len() };
+    if ref_chunk_body.len() < 2 {
+        return Err(truncated(0));
+    }
+    let marker_num = usize::from(read_u16_be(ref_chunk_body, 0)?);
+    let mut cue_vec: Vec<CuePoint> = Vec::with_capacity(marker_num);
+    let mut marker_addr: usize = 2;
+    for _ in 0..marker_num {
+        if ref_chunk_body.len() < marker_addr + 7 {
+            return Err(truncated(marker_addr));
+        }
+        let mut cue = CuePoint::new(usize::from(read_u16_be(ref_chunk_body, marker_addr)?), usize::try_from(read_u32_be(ref_chunk_body, marker_addr + 2)?)?);
+        let name_len = usize::from(ref_chunk_body[marker_addr + 6]);
+        let name_addr = marker_addr + 7;
+        if ref_chunk_body.len() < name_addr + name_len {
+            return Err(truncated(marker_addr));
+        }
+        if name_len > 0 {
+            cue.label = Some(String::from_utf8_lossy(&ref_chunk_body[name_addr..(name_addr + name_len)]).to_string());
+        }
+        cue_vec.push(cue);
+        // Count byte + text is padded to even length.
+        marker_addr = name_addr + name_len + (1 - (name_len & 1));
+    }
+    Ok(cue_vec)
+}
+
+fn set_mark(cue_points: &[CuePoint]) -> Result<Vec<u8>> {
+    let mut chunk_body: Vec<u8> = u16::try_from(cue_points.len())?.to_be_bytes().to_vec();
+    for cue in cue_points {
+        let name = cue.label.as_deref().unwrap_or("");
+        chunk_body.extend_from_slice(&u16::try_from(cue.id)?.to_be_bytes());
+        chunk_body.extend_from_slice(&u32::try_from(cue.position)?.to_be_bytes());
+        chunk_body.push(u8::try_from(name.len()).map_err(|_| WavFileError::FieldLength { field: "Marker Name", max: 255, actual: name.len() })?);
+        chunk_body.extend_from_slice(name.as_bytes());
+        if name.len() & 1 == 0 {
+            chunk_body.push(0);
+        }
+    }
+    Ok(chunk_body)
+}
@@ -0,0 +1,230 @@
+use std::convert::TryFrom;
+
+use super::error::*;
+use super::bytes::{get_text, get_trimmed_text, set_text};
+use super::{WavFile, SubChunk};
+
+#[cfg(test)]
+mod tests {
+    use super::{Bext, BEXT_FIXED_SIZE};
+    use super::super::{WavFile, SubChunk, Fmt, to_wav_audio};
+
+    fn test_wav_file() -> WavFile {
+        let mut wav_file = WavFile::new();
+        wav_file.update_wav_audio(&to_wav_audio(&vec![vec![0.0; 10]], &Fmt::new(1, 1, 8000, 16)).unwrap()).unwrap();
+        wav_file
+    }
+
+    #[test]
+    fn test_bext_round_trip() {
+        let mut wav_file = test_wav_file();
+        assert!(wav_file.bext().unwrap().is_none());
+
+        let mut bext = Bext::new();
+        bext.description = "Resonance analysis".to_string();
+        bext.originator = "resonance-parrot".to_string();
+        bext.originator_reference = "RP0001".to_string();
+        bext.origination_date = "2024-01-31".to_string();
+        bext.origination_time = "12:34:56".to_string();
+        bext.time_reference = 0x1_0000_0010;
+        bext.umid[0] = 0x06;
+        bext.loudness_value = -2300;
+        bext.max_true_peak_level = -100;
+        bext.coding_history = "A=PCM,F=8000,W=16,M=mono\r\n".to_string();
+        wav_file.set_bext(&bext).unwrap();
+
+        let path = std::env::temp_dir().join("wavfile_bext_test.wav");
+        wav_file.save_as(&path).unwrap();
+        let mut read_file = WavFile::new();
+        read_file.open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read_file.bext().unwrap().unwrap(), bext);
+        assert_eq!(read_file.sub_chunks.iter().find(|sub_chunk| sub_chunk.name == *b"bext").unwrap().body_size, BEXT_FIXED_SIZE + 26);
+        // "bext" is before "data".
+        let name_vec: Vec<[u8;4]> = read_file.sub_chunks.iter().map(|sub_chunk| sub_chunk.name).collect();
+        assert_eq!(name_vec, vec![*b"fmt ", *b"bext", *b"data"]);
+    }
+
+    #[test]
+    fn test_coding_history_padding() {
+        let mut data = vec![0; BEXT_FIXED_SIZE];
+        data.extend_from_slice(b"A=PCM,F=48000,W=24,M=stereo\r\n\0A=PCM,F=8000,W=16,M=mono\r\n\0\0\0");
+        let mut wav_file = test_wav_file();
+        wav_file.update_sub_chunk(SubChunk { name: *b"bext", body_size: data.len(), data }).unwrap();
+        assert_eq!(wav_file.bext().unwrap().unwrap().coding_history, "A=PCM,F=48000,W=24,M=stereo\r\n\0A=PCM,F=8000,W=16,M=mono\r\n");
+    }
+
+    #[test]
+    fn test_bext_unknown_fields() {
+        // Reserved bytes written by a newer version are kept as they are.
+        let mut data = vec![0; BEXT_FIXED_SIZE];
+        data[0..4].copy_from_slice(b"Test");
+        data[346] = 3; // version
+        data[450] = 0xAB; // reserved
+        data[BEXT_FIXED_SIZE - 1] = 0xCD;
+        let mut wav_file = test_wav_file();
+        wav_file.update_sub_chunk(SubChunk { name: *b"bext", body_size: data.len(), data: data.clone() }).unwrap();
+
+        let mut bext = wav_file.bext().unwrap().unwrap();
+        assert_eq!(bext.description, "Test");
+        assert_eq!(bext.version, 3);
+        wav_file.set_bext(&bext).unwrap();
+        assert_eq!(wav_file.sub_chunks.iter().find(|sub_chunk| sub_chunk.name == *b"bext").unwrap().data, data);
+
+        bext.description = "x".repeat(257);
+        assert!(wav_file.set_bext(&bext).is_err());
+
+        wav_file.update_sub_chunk(SubChunk { name: *b"bext", body_size: 10, data: vec![0; 10] }).unwrap();
+        assert!(wav_file.bext().is_err());
+    }
+
+    #[test]
+    fn test_ixml() {
+        let mut wav_file = test_wav_file();
+        assert!(wav_file.ixml().unwrap().is_none());
+        let ixml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?><BWFXML><PROJECT>Resonance</PROJECT></BWFXML>";
+        wav_file.set_ixml(ixml).unwrap();
+        assert_eq!(wav_file.ixml().unwrap().unwrap(), ixml);
+        // Trailing padding written by recorders is dropped.
+        let mut data = ixml.as_bytes().to_vec();
+        data.extend_from_slice(&[0; 7]);
+        wav_file.update_sub_chunk(SubChunk { name: *b"iXML", body_size: data.len(), data }).unwrap();
+        assert_eq!(wav_file.ixml().unwrap().unwrap(), ixml);
+    }
+}
+
+// Fixed part of "bext" chunk before Coding History.
+const BEXT_FIXED_SIZE: usize = 602;
+const BEXT_VERSION: usize = 2;
+
+// Broadcast Audio Extension chunk (EBU Tech 3285)
+// Loudness fields are in 1/100 LU(FS) / dB(TP) as written in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bext {
+    pub description: String,            // up to 256 chars
+    pub originator: String,             // up to 32 chars
+    pub originator_reference: String,   // up to 32 chars
+    pub origination_date: String,       // yyyy-mm-dd
+    pub origination_time: String,       // hh:mm:ss
+    pub time_reference: u64,            // samples since midnight
+    pub version: usize,
+    pub umid: [u8;64],
+    pub loudness_value: i16,
+    pub loudness_range: i16,
+    pub max_true_peak_level: i16,
+    pub max_momentary_loudness: i16,
+    pub max_short_term_loudness: i16,
+    pub reserved: Vec<u8>,              // 180 bytes, kept as read
+    pub coding_history: String,
+}
+
+impl Bext {
+    pub fn new() -> Bext {
+        Bext {
+            description: String::new(),
+            originator: String::new(),
+            originator_reference: String::new(),
+            origination_date: String::new(),
+            origination_time: String::new(),
+            time_reference: 0,
+            version: BEXT_VERSION,
+            umid: [0; 64],
+            loudness_value: 0,
+            loudness_range: 0,
+            max_true_peak_level: 0,
+            max_momentary_loudness: 0,
+            max_short_term_loudness: 0,
+            reserved: vec![0; 180],
+            coding_history: String::new(),
+        }
+    }
+}
+
+impl Default for Bext {
+    fn default() -> Bext {
+        Bext::new()
+    }
+}
+
+impl WavFile {
+    // Typed "bext" chunk. None if the file has no "bext" chunk.
+    pub fn bext(&self) -> Result<Option<Bext>> {
+        match self.find_sub_chunk([b'b',b'e',b'x',b't']) {
+            Some(sub_chunk) => Ok(Some(get_bext(&sub_chunk.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_bext(&mut self, ref_bext: &Bext) -> Result<()> {
+        let data = set_bext(ref_bext)?;
+        // Broadcast WAV readers expect "bext" before "data".
+        self.update_sub_chunk_before(SubChunk { name: [b'b',b'e',b'x',b't'], body_size: data.len(), data }, [b'd',b'a',b't',b'a'])
+    }
+
+    // iXML document. None if the file has no "iXML" chunk.
+    pub fn ixml(&self) -> Result<Option<String>> {
+        match self.find_sub_chunk([b'i',b'X',b'M',b'L']) {
+            Some(sub_chunk) => Ok(Some(get_text(&sub_chunk.data))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_ixml(&mut self, ixml: &str) -> Result<()> {
+        let data = ixml.as_bytes().to_vec();
+        self.update_sub_chunk(SubChunk { name: [b'i',b'X',b'M',b'L'], body_size: data.len(), data })
+    }
+}
+
+fn get_bext(ref_chunk_body: &[u8]) -> Result<Bext> {
+    if ref_chunk_body.len() < BEXT_FIXED_SIZE {
+        return Err(WavFileError::TruncatedChunk {
+            id: [b'b',b'e',b'x',b't'],
+            offset: 0,
+            expected: BEXT_FIXED_SIZE,
+            actual: ref_chunk_body.len()
+        });
+    }
+    let loudness = |addr: usize| -> Result<i16> {
+        Ok(i16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[addr..(addr + 2)])?))
+    };
+    Ok(Bext {
+        description: get_text(&ref_chunk_body[0..256]),
+        originator: get_text(&ref_chunk_body[256..288]),
+        originator_reference: get_text(&ref_chunk_body[288..320]),
+        origination_date: get_text(&ref_chunk_body[320..330]),
+        origination_time: get_text(&ref_chunk_body[330..338]),
+        time_reference: u64::from_le_bytes(<[u8;8]>::try_from(&ref_chunk_body[338..346])?),
+        version: usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[346..348])?)),
+        umid: <[u8;64]>::try_from(&ref_chunk_body[348..412])?,
+        loudness_value: loudness(412)?,
+        loudness_range: loudness(414)?,
+        max_true_peak_level: loudness(416)?,
+        max_momentary_loudness: loudness(418)?,
+        max_short_term_loudness: loudness(420)?,
+        reserved: ref_chunk_body[422..BEXT_FIXED_SIZE].to_vec(),
+        coding_history: get_trimmed_text(&ref_chunk_body[BEXT_FIXED_SIZE..]),
+    })
+}
+
+fn set_bext(ref_bext: &Bext) -> Result<Vec<u8>> {
+    let mut chunk_body: Vec<u8> = Vec::new();
+    set_text(&mut chunk_body, "Description", &ref_bext.description, 256)?;
+    set_text(&mut chunk_body, "Originator", &ref_bext.originator, 32)?;
+    set_text(&mut chunk_body, "Originator Reference", &ref_bext.originator_reference, 32)?;
+    set_text(&mut chunk_body, "Origination Date", &ref_bext.origination_date, 10)?;
+    set_text(&mut chunk_body, "Origination Time", &ref_bext.origination_time, 8)?;
+    chunk_body.extend_from_slice(&ref_bext.time_reference.to_le_bytes());
+    chunk_body.extend_from_slice(&u16::try_from(ref_bext.version)?.to_le_bytes());
+    chunk_body.extend_from_slice(&ref_bext.umid);
+    chunk_body.extend_from_slice(&ref_bext.loudness_value.to_le_bytes());
+    chunk_body.extend_from_slice(&ref_bext.loudness_range.to_le_bytes());
+    chunk_body.extend_from_slice(&ref_bext.max_true_peak_level.to_le_bytes());
+    chunk_body.extend_from_slice(&ref_bext.max_momentary_loudness.to_le_bytes());
+    chunk_body.extend_from_slice(&ref_bext.max_short_term_loudness.to_le_bytes());
+    if ref_bext.reserved.len() != BEXT_FIXED_SIZE - chunk_body.len() {
+        return Err(WavFileError::FieldLength { field: "Reserved", max: BEXT_FIXED_SIZE - chunk_body.len(), actual: ref_bext.reserved.len() });
+    }
+    chunk_body.extend_from_slice(&ref_bext.reserved);
+    chunk_body.extend_from_slice(ref_bext.coding_history.as_bytes());
+    Ok(chunk_body)
+}
@@ -0,0 +1,40 @@
+use std::convert::TryFrom;
+
+use super::error::*;
+
+// Little-endian u32 at "addr".
+pub(crate) fn read_u32(bytes: &[u8], addr: usize) -> Result<usize> {
+    Ok(usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&bytes[addr..(addr + 4)])?))?)
+}
+
+// Big-endian u16 at "addr" (AIFF)
+pub(crate) fn read_u16_be(bytes: &[u8], addr: usize) -> Result<u16> {
+    Ok(u16::from_be_bytes(<[u8;2]>::try_from(&bytes[addr..(addr + 2)])?))
+}
+
+// Big-endian u32 at "addr" (AIFF)
+pub(crate) fn read_u32_be(bytes: &[u8], addr: usize) -> Result<u32> {
+    Ok(u32::from_be_bytes(<[u8;4]>::try_from(&bytes[addr..(addr + 4)])?))
+}
+
+// Text up to the first NUL.
+pub(crate) fn get_text(bytes: &[u8]) -> String {
+    let len = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).to_string()
+}
+
+// Text without the NUL padding at the end. (Coding History has lines after a NUL in some files.)
+pub(crate) fn get_trimmed_text(bytes: &[u8]) -> String {
+    let len = bytes.iter().rposition(|byte| *byte != 0).map_or(0, |pos| pos + 1);
+    String::from_utf8_lossy(&bytes[..len]).to_string()
+}
+
+// Text padded with NUL to "len" bytes.
+pub(crate) fn set_text(chunk_body: &mut Vec<u8>, field: &'static str, text: &str, len: usize) -> Result<()> {
+    if text.len() > len {
+        return Err(WavFileError::FieldLength { field, max: len, actual: text.len() });
+    }
+    chunk_body.extend_from_slice(text.as_bytes());
+    chunk_body.resize(chunk_body.len() + len - text.len(), 0);
+    Ok(())
+}
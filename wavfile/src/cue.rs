@@ -0,0 +1,256 @@
+use std::convert::TryFrom;
+
+use super::error::*;
+use super::bytes::{read_u32, get_text};
+use super::{WavFile, SubChunk};
+
+#[cfg(test)]
+mod tests {
+    use super::{CuePoint, CueRegion};
+    use super::super::{WavFile, SubChunk, InfoTags, INFO_TITLE, Fmt, to_wav_audio};
+
+    fn test_wav_file() -> WavFile {
+        let mut wav_file = WavFile::new();
+        wav_file.update_wav_audio(&to_wav_audio(&vec![vec![0.0; 1000]], &Fmt::new(1, 1, 8000, 16)).unwrap()).unwrap();
+        wav_file
+    }
+
+    fn test_cue_points() -> Vec<CuePoint> {
+        let mut intro = CuePoint::new(1, 0);
+        intro.label = Some("Intro".to_string());
+        let mut attack = CuePoint::new(2, 120);
+        attack.label = Some("Attack".to_string());
+        attack.note = Some("Check the onset".to_string());
+        attack.region = Some(CueRegion { length: 300, purpose: *b"rgn ", country: 0, language: 0, dialect: 0, code_page: 0, text: "Odd".to_string() });
+        let bare = CuePoint::new(3, 999);
+        vec![intro, attack, bare]
+    }
+
+    #[test]
+    fn test_cue_round_trip() {
+        let mut wav_file = test_wav_file();
+        let mut info_tags = InfoTags::new();
+        info_tags.set(INFO_TITLE, "Cue");
+        wav_file.set_info_tags(&info_tags).unwrap();
+        assert!(wav_file.cue_points().unwrap().is_empty());
+
+        let cue_vec = test_cue_points();
+        wav_file.set_cue_points(&cue_vec).unwrap();
+        let path = std::env::temp_dir().join("wavfile_cue_test.wav");
+        wav_file.save_as(&path).unwrap();
+        let mut read_file = WavFile::new();
+        read_file.open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read_file.cue_points().unwrap(), cue_vec);
+        // "LIST"/"INFO" is kept.
+        assert_eq!(read_file.info_tags().unwrap().unwrap(), info_tags);
+
+        read_file.set_cue_points(&[]).unwrap();
+        assert!(read_file.cue_points().unwrap().is_empty());
+        assert_eq!(read_file.sub_chunks.len(), 3);
+    }
+
+    #[test]
+    fn test_cue_without_adtl() {
+        // "cue " only, with position in dwSampleOffset
+        let mut data = 1_u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&7_u32.to_le_bytes());
+        data.extend_from_slice(&0_u32.to_le_bytes());
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&500_u32.to_le_bytes());
+        let mut wav_file = test_wav_file();
+        wav_file.update_sub_chunk(SubChunk { name: *b"cue ", body_size: data.len(), data: data.clone() }).unwrap();
+        assert_eq!(wav_file.cue_points().unwrap(), vec![CuePoint::new(7, 500)]);
+
+        // Cue point count larger than the chunk
+        data[0] = 2;
+        wav_file.update_sub_chunk(SubChunk { name: *b"cue ", body_size: data.len(), data }).unwrap();
+        assert!(wav_file.cue_points().is_err());
+    }
+}
+
+// Labelled region ("ltxt") starting at a cue point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueRegion {
+    pub length: usize,      // frames
+    pub purpose: [u8;4],    // e.g. "rgn "
+    pub country: usize,
+    pub language: usize,
+    pub dialect: usize,
+    pub code_page: usize,
+    pub text: String,
+}
+
+// Cue point ("cue ") with its texts in "LIST"/"adtl".
+#[derive(Debug, Clone, PartialEq)]
+pub struct CuePoint {
+    pub id: usize,
+    pub position: usize,            // frame position in "data"
+    pub label: Option<String>,      // "labl"
+    pub note: Option<String>,       // "note"
+    pub region: Option<CueRegion>,  // "ltxt"
+}
+
+impl CuePoint {
+    pub fn new(id: usize, position: usize) -> CuePoint {
+        CuePoint { id, position, label: None, note: None, region: None }
+    }
+}
+
+const LIST_ADTL: [u8;4] = [b'a',b'd',b't',b'l'];
+const CUE_POINT_SIZE: usize = 24;
+const LTXT_HEAD_SIZE: usize = 20;
+
+impl WavFile {
+    // Cue points in the file order. Empty if the file has no "cue " chunk.
+    pub fn cue_points(&self) -> Result<Vec<CuePoint>> {
+        let mut cue_vec = match self.find_sub_chunk([b'c',b'u',b'e',b' ']) {
+            Some(sub_chunk) => get_cue_points(&sub_chunk.data)?,
+            None => return Ok(Vec::new()),
+        };
+        if let Some(idx) = self.list_chunk_idx(LIST_ADTL) {
+            get_adtl(&self.sub_chunks[idx].data, &mut cue_vec)?;
+        }
+        Ok(cue_vec)
+    }
+
+    // Replace "cue " and "LIST"/"adtl" chunks. Empty cue points remove them.
+    pub fn set_cue_points(&mut self, cue_points: &[CuePoint]) -> Result<()> {
+        let op_cue_idx = self.sub_chunks.iter().position(|sub_chunk| sub_chunk.name == [b'c',b'u',b'e',b' ']);
+        let op_cue_chunk = if cue_points.is_empty() {
+            None
+        }
+        else {
+            let data = set_cue_points(cue_points)?;
+            Some(SubChunk { name: [b'c',b'u',b'e',b' '], body_size: data.len(), data })
+        };
+        self.replace_sub_chunk(op_cue_idx, op_cue_chunk);
+
+        let op_adtl_idx = self.list_chunk_idx(LIST_ADTL);
+        let op_adtl_chunk = if cue_points.iter().all(|cue| cue.label.is_none() && cue.note.is_none() && cue.region.is_none()) {
+            None
+        }
+        else {
+            let data = set_adtl(cue_points)?;
+            Some(SubChunk { name: [b'L',b'I',b'S',b'T'], body_size: data.len(), data })
+        };
+        self.replace_sub_chunk(op_adtl_idx, op_adtl_chunk);
+        Ok(())
+    }
+}
+
+fn get_cue_points(ref_chunk_body: &[u8]) -> Result<Vec<CuePoint>> {
+    if ref_chunk_body.len() < 4 {
+        return Err(WavFileError::TruncatedChunk { id: [b'c',b'u',b'e',b' '], offset: 0, expected: 4, actual: ref_chunk_body.len() });
+    }
+    let cue_num = read_u32(ref_chunk_body, 0x00)?;
+    if (ref_chunk_body.len() - 4) / CUE_POINT_SIZE < cue_num {
+        return Err(WavFileError::TruncatedChunk {
+            id: [b'c',b'u',b'e',b' '],
+            offset: 0,
+            expected: 4 + cue_num * CUE_POINT_SIZE,
+            actual: ref_chunk_body.len()
+        });
+    }
+    let mut cue_vec: Vec<CuePoint> = Vec::with_capacity(cue_num);
+    for cue_buf in ref_chunk_body[4..].chunks_exact(CUE_POINT_SIZE).take(cue_num) {
+        // dwName, dwPosition, fccChunk, dwChunkStart, dwBlockStart, dwSampleOffset
+        cue_vec.push(CuePoint::new(read_u32(cue_buf, 0x00)?, read_u32(cue_buf, 0x14)?));
+    }
+    Ok(cue_vec)
+}
+
+fn set_cue_points(cue_points: &[CuePoint]) -> Result<Vec<u8>> {
+    let mut chunk_body: Vec<u8> = Vec::new();
+    chunk_body.extend_from_slice(&u32::try_from(cue_points.len())?.to_le_bytes());
+    for cue in cue_points {
+        let position = u32::try_from(cue.position)?.to_le_bytes();
+        chunk_body.extend_from_slice(&u32::try_from(cue.id)?.to_le_bytes());
+        chunk_body.extend_from_slice(&position);
+        chunk_body.extend_from_slice(b"data");
+        chunk_body.extend_from_slice(&[0; 8]);
+        chunk_body.extend_from_slice(&position);
+    }
+    Ok(chunk_body)
+}
+
+// Set texts of "LIST"/"adtl" to the cue points. Texts for unknown IDs are ignored.
+fn get_adtl(ref_chunk_body: &[u8], cue_vec: &mut [CuePoint]) -> Result<()> {
+    let mut head_addr: usize = 0x04;
+    while head_addr + 8 <= ref_chunk_body.len() {
+        let head_buf = &ref_chunk_body[head_addr..];
+        let id = <[u8;4]>::try_from(&head_buf[0x00..0x04])?;
+        let size = read_u32(head_buf, 0x04)?;
+        if head_buf.len() - 8 < size {
+            return Err(WavFileError::TruncatedChunk { id, offset: head_addr, expected: size, actual: head_buf.len() - 8 });
+        }
+        let body = &head_buf[8..(8 + size)];
+        let head_size = if id == [b'l',b't',b'x',b't'] { LTXT_HEAD_SIZE } else { 4 };
+        if size < head_size {
+            return Err(WavFileError::TruncatedChunk { id, offset: head_addr, expected: head_size, actual: size });
+        }
+        let cue_id = read_u32(body, 0x00)?;
+        if let Some(cue) = cue_vec.iter_mut().find(|cue| cue.id == cue_id) {
+            match id {
+                [b'l',b'a',b'b',b'l'] => { cue.label = Some(get_text(&body[4..])); }
+                [b'n',b'o',b't',b'e'] => { cue.note = Some(get_text(&body[4..])); }
+                [b'l',b't',b'x',b't'] => {
+                    let read_u16 = |addr: usize| -> Result<usize> {
+                        Ok(usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&body[addr..(addr + 2)])?)))
+                    };
+                    cue.region = Some(CueRegion {
+                        length: read_u32(body, 0x04)?,
+                        purpose: <[u8;4]>::try_from(&body[0x08..0x0c])?,
+                        country: read_u16(0x0c)?,
+                        language: read_u16(0x0e)?,
+                        dialect: read_u16(0x10)?,
+                        code_page: read_u16(0x12)?,
+                        text: get_text(&body[LTXT_HEAD_SIZE..]),
+                    });
+                }
+                _ => {}
+            }
+        }
+        // Odd size is followed by a pad byte.
+        head_addr += 8 + size + (size & 1);
+    }
+    Ok(())
+}
+
+fn push_adtl_text(chunk_body: &mut Vec<u8>, id: &[u8;4], head: &[u8], text: &str) -> Result<()> {
+    let size = head.len() + text.len() + 1;
+    chunk_body.extend_from_slice(id);
+    chunk_body.extend_from_slice(&u32::try_from(size)?.to_le_bytes());
+    chunk_body.extend_from_slice(head);
+    chunk_body.extend_from_slice(text.as_bytes());
+    chunk_body.push(0);
+    if size & 1 == 1 {
+        chunk_body.push(0);
+    }
+    Ok(())
+}
+
+fn set_adtl(cue_points: &[CuePoint]) -> Result<Vec<u8>> {
+    let mut chunk_body: Vec<u8> = LIST_ADTL.to_vec();
+    for cue in cue_points {
+        let cue_id = u32::try_from(cue.id)?.to_le_bytes();
+        if let Some(label) = &cue.label {
+            push_adtl_text(&mut chunk_body, b"labl", &cue_id, label)?;
+        }
+        if let Some(note) = &cue.note {
+            push_adtl_text(&mut chunk_body, b"note", &cue_id, note)?;
+        }
+        if let Some(region) = &cue.region {
+            let mut head: Vec<u8> = cue_id.to_vec();
+            head.extend_from_slice(&u32::try_from(region.length)?.to_le_bytes());
+            head.extend_from_slice(&region.purpose);
+            head.extend_from_slice(&u16::try_from(region.country)?.to_le_bytes());
+            head.extend_from_slice(&u16::try_from(region.language)?.to_le_bytes());
+            head.extend_from_slice(&u16::try_from(region.dialect)?.to_le_bytes());
+            head.extend_from_slice(&u16::try_from(region.code_page)?.to_le_bytes());
+            push_adtl_text(&mut chunk_body, b"ltxt", &head, &region.text)?;
+        }
+    }
+    Ok(chunk_body)
+}
@@ -1,26 +1,124 @@
 use std::fmt;
+use std::path::PathBuf;
 
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
+pub type Result<T> = std::result::Result<T, WavFileError>;
 
-#[derive(Debug, Clone)]
-pub struct WavFileError {
-    pub message: String,
+#[derive(Debug)]
+pub enum WavFileError {
+    Io(std::io::Error),
+    IntConversion(std::num::TryFromIntError),
+    SliceConversion(std::array::TryFromSliceError),
+    AudioBuffer(audio_sample::AudioBufferError),
+    // -- Path --
+    NotFile { path: PathBuf },
+    Extension { path: PathBuf },
+    // -- RIFF Construction --
+    NotRiff { id: [u8;4] },
+    NotWave { id: [u8;4] },
+    TruncatedHeader { expected: usize, actual: usize },
+    RiffSize { expected: usize, actual: usize },
+    TruncatedChunk { id: [u8;4], offset: usize, expected: usize, actual: usize },
+    DuplicateChunk { id: [u8;4] },
+    MissingChunk { id: [u8;4] },
+    // -- fmt Chunk --
+    FmtSize { expected: usize, actual: usize },
+    FormatId { format_id: usize },
+    SubFormatGuid { guid: [u8;16] },
+    FmtMismatch { field: &'static str, expected: usize, actual: usize },
+    // -- Supported Format --
+    Channel { channel: usize },
+    Bits { bits: usize },
+    ValidBits { valid_bits: usize, bits: usize },
+    SamplingRate { sampling_rate: usize },
+    // -- Data --
+    ChannelNum { expected: usize, actual: usize },
+    DataLength { expected: usize, actual: usize },
+    FramePosition { position: usize, frame_num: usize },
+    // -- Metadata --
+    FieldLength { field: &'static str, max: usize, actual: usize },
+    // -- AIFF --
+    NotAiff { id: [u8;4] },
+    Compression { id: [u8;4] },
+    // -- FLAC --
+    NotFlac { id: [u8;4] },
+    FlacFrame { offset: usize, reason: &'static str },
+    Md5Mismatch { expected: [u8;16], actual: [u8;16] },
 }
 
-impl WavFileError {
-    pub fn new(err_str: &str)  -> Box<dyn std::error::Error + Send + Sync + 'static> {
-        Box::<WavFileError>::new( WavFileError{ message:err_str.to_string()})
-    }
+fn chunk_id_str(id: &[u8]) -> String {
+    String::from_utf8_lossy(id).to_string()
 }
 
 impl fmt::Display for WavFileError {
     fn fmt(&self, f: &mut fmt::Formatter) -> std::result::Result<(), fmt::Error> {
-        write!(f, "{}", self.message)
+        match self {
+            WavFileError::Io(err) => write!(f, "IO Error! {}", err),
+            WavFileError::IntConversion(err) => write!(f, "Integer Conversion Error! {}", err),
+            WavFileError::SliceConversion(err) => write!(f, "Slice Conversion Error! {}", err),
+            WavFileError::AudioBuffer(err) => write!(f, "{}", err),
+            WavFileError::NotFile { path } => write!(f, "Path is Not File! Path:{}", path.display()),
+            WavFileError::Extension { path } => write!(f, "Path Extension is Not Supported!! File:{}", path.display()),
+            WavFileError::NotRiff { id } => write!(f, "Not compatible wav format! \"RIFF\" is \"{}\"", chunk_id_str(id)),
+            WavFileError::NotWave { id } => write!(f, "Not compatible wav format! \"WAVE\" is \"{}\"", chunk_id_str(id)),
+            WavFileError::TruncatedHeader { expected, actual } => write!(f, "Not compatible wav format! File Size:{} Expected at least:{}", actual, expected),
+            WavFileError::RiffSize { expected, actual } => write!(f, "Not compatible wav format! RIFF Size:{} File Size - 8:{}", actual, expected),
+            WavFileError::TruncatedChunk { id, offset, expected, actual } =>
+                write!(f, "Chunk Size is wrong! \"{}\" at 0x{:x} Size:{} Available:{}", chunk_id_str(id), offset, expected, actual),
+            WavFileError::DuplicateChunk { id } => write!(f, "There are two or more \"{}\" chunks!", chunk_id_str(id)),
+            WavFileError::MissingChunk { id } => write!(f, "There are no \"{}\" chunks!", chunk_id_str(id)),
+            WavFileError::FmtSize { expected, actual } => write!(f, "Not compatible wav format! fmt Chunk Size:{} Expected:{}", actual, expected),
+            WavFileError::FormatId { format_id } => write!(f, "Not compatible wav format! Format ID:0x{:x}", format_id),
+            WavFileError::SubFormatGuid { guid } => write!(f, "Not compatible wav format! Sub Format GUID:{:x?}", guid),
+            WavFileError::FmtMismatch { field, expected, actual } => write!(f, "Not match parameter! {}:{} Expected:{}", field, actual, expected),
+            WavFileError::Channel { channel } => write!(f, "Irregal Format! Not Supported Channel:{}", channel),
+            WavFileError::Bits { bits } => write!(f, "Irregal Format! Not Supported Bits/Sample:{}", bits),
+            WavFileError::ValidBits { valid_bits, bits } => write!(f, "Irregal Format! Not Supported Valid Bits:{} Bits/Sample:{}", valid_bits, bits),
+            WavFileError::SamplingRate { sampling_rate } => write!(f, "Irregal Format! Not Supported Sampling Rate:{}", sampling_rate),
+            WavFileError::ChannelNum { expected, actual } => write!(f, "Irregal Data Vector! Channel:{} Expected:{}", actual, expected),
+            WavFileError::DataLength { expected, actual } => write!(f, "Irregal Data Vector! Length:{} Expected:{}", actual, expected),
+            WavFileError::FramePosition { position, frame_num } => write!(f, "Frame Position is out of data chunk! Position:{} Frames:{}", position, frame_num),
+            WavFileError::FieldLength { field, max, actual } => write!(f, "Metadata Field is Too Long! {}:{} Max:{}", field, actual, max),
+            WavFileError::NotAiff { id } => write!(f, "Not compatible aiff format! \"FORM\" type is \"{}\"", chunk_id_str(id)),
+            WavFileError::Compression { id } => write!(f, "Not compatible aiff format! Compression Type:\"{}\"", chunk_id_str(id)),
+            WavFileError::NotFlac { id } => write!(f, "Not compatible flac format! \"fLaC\" is \"{}\"", chunk_id_str(id)),
+            WavFileError::FlacFrame { offset, reason } => write!(f, "Broken flac frame! Offset:{} {}", offset, reason),
+            WavFileError::Md5Mismatch { expected, actual } => write!(f, "Decoded audio does not match MD5 signature! MD5:{:02x?} Expected:{:02x?}", actual, expected),
+        }
     }
 }
 
 impl std::error::Error for WavFileError {
-    fn description(&self) -> &str {
-        &self.message
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WavFileError::Io(err) => Some(err),
+            WavFileError::IntConversion(err) => Some(err),
+            WavFileError::SliceConversion(err) => Some(err),
+            WavFileError::AudioBuffer(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for WavFileError {
+    fn from(err: std::io::Error) -> WavFileError {
+        WavFileError::Io(err)
+    }
+}
+
+impl From<std::num::TryFromIntError> for WavFileError {
+    fn from(err: std::num::TryFromIntError) -> WavFileError {
+        WavFileError::IntConversion(err)
+    }
+}
+
+impl From<std::array::TryFromSliceError> for WavFileError {
+    fn from(err: std::array::TryFromSliceError) -> WavFileError {
+        WavFileError::SliceConversion(err)
+    }
+}
+
+impl From<audio_sample::AudioBufferError> for WavFileError {
+    fn from(err: audio_sample::AudioBufferError) -> WavFileError {
+        WavFileError::AudioBuffer(err)
     }
 }
@@ -0,0 +1,931 @@
+use std::path::{Path, PathBuf};
+use std::convert::TryFrom;
+use std::io::prelude::*;
+use std::io::BufReader;
+
+use std::fs::File;
+
+use super::error::*;
+use super::md5::Md5;
+use super::{Fmt, WavAudio, WavFile, InfoTags, fmt_check};
+use super::{WAVE_FORMAT_PCM, INFO_TITLE, INFO_ARTIST, INFO_COMMENT, INFO_CREATION_DATE, INFO_SOFTWARE, INFO_GENRE, INFO_COPYRIGHT, INFO_PRODUCT};
+
+#[cfg(test)]
+mod tests {
+    use super::{FlacFile, crc8, crc16};
+    use super::super::md5::Md5;
+    use super::super::{WavFileError, INFO_TITLE, INFO_ARTIST, to_channel_vec_as};
+
+    // Minimal FLAC encoder to build test streams.
+    struct BitWriter {
+        buf: Vec<u8>,
+        bit_len: usize,
+    }
+
+    impl BitWriter {
+        fn new() -> BitWriter {
+            BitWriter { buf: Vec::new(), bit_len: 0 }
+        }
+
+        fn write(&mut self, val: u64, bits: usize) {
+            for bit_idx in (0..bits).rev() {
+                if self.bit_len.is_multiple_of(8) {
+                    self.buf.push(0);
+                }
+                let bit = ((val >> bit_idx) & 1) as u8;
+                *self.buf.last_mut().unwrap() |= bit << (7 - self.bit_len % 8);
+                self.bit_len += 1;
+            }
+        }
+
+        fn write_signed(&mut self, val: i64, bits: usize) {
+            self.write(val as u64 & ((1 << bits) - 1), bits);
+        }
+
+        fn write_rice(&mut self, val: i64, param: usize) {
+            let zigzag = ((val << 1) ^ (val >> 63)) as u64;
+            for _ in 0..(zigzag >> param) {
+                self.write(0, 1);
+            }
+            self.write(1, 1);
+            self.write(zigzag & ((1 << param) - 1), param);
+        }
+
+        fn align(&mut self) {
+            self.bit_len = self.buf.len() * 8;
+        }
+    }
+
+    enum TestSubframe {
+        Constant,
+        Verbatim,
+        Fixed(usize),
+        Lpc(Vec<i64>, usize),
+        Wasted(usize),
+        // Fixed predictor order, partition order and Rice parameters (None: escaped to 16bit)
+        Partitioned(usize, usize, Vec<Option<usize>>),
+    }
+
+    // One partition of Rice parameter 3.
+    fn write_residual(writer: &mut BitWriter, residual: &[i64]) {
+        write_partitions(writer, residual, 0, 0, &[Some(3)]);
+    }
+
+    // 5bit parameters when any is over 14.
+    fn write_partitions(writer: &mut BitWriter, residual: &[i64], order: usize, partition_order: usize, param_vec: &[Option<usize>]) {
+        let param_bits = if param_vec.iter().any(|op_param| op_param.unwrap_or(0) > 14) { 5 } else { 4 };
+        writer.write((param_bits - 4) as u64, 2);
+        writer.write(partition_order as u64, 4);
+        let partition_size = (residual.len() + order) >> partition_order;
+        let mut residual_iter = residual.iter();
+        for (partition_idx, op_param) in param_vec.iter().enumerate() {
+            let len = if partition_idx == 0 { partition_size - order } else { partition_size };
+            match op_param {
+                Some(param) => {
+                    writer.write(*param as u64, param_bits);
+                    for val in residual_iter.by_ref().take(len) {
+                        writer.write_rice(*val, *param);
+                    }
+                }
+                None => {
+                    writer.write((1 << param_bits) - 1, param_bits);
+                    writer.write(16, 5);
+                    for val in residual_iter.by_ref().take(len) {
+                        assert!(i64::from(*val as i16) == *val);
+                        writer.write_signed(*val, 16);
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_subframe(writer: &mut BitWriter, samples: &[i64], bits: usize, subframe: &TestSubframe) {
+        match subframe {
+            TestSubframe::Constant => {
+                writer.write(0, 8);
+                writer.write_signed(samples[0], bits);
+            }
+            TestSubframe::Verbatim => {
+                writer.write(1 << 1, 8);
+                for val in samples {
+                    writer.write_signed(*val, bits);
+                }
+            }
+            TestSubframe::Fixed(order) => {
+                let coefs: [&[i64]; 5] = [&[], &[1], &[2, -1], &[3, -3, 1], &[4, -6, 4, -1]];
+                writer.write(((8 + order) << 1) as u64, 8);
+                for val in &samples[..*order] {
+                    writer.write_signed(*val, bits);
+                }
+                write_residual(writer, &residual(samples, coefs[*order], 0));
+            }
+            TestSubframe::Lpc(coefs, shift) => {
+                writer.write(((32 + coefs.len() - 1) << 1) as u64, 8);
+                for val in &samples[..coefs.len()] {
+                    writer.write_signed(*val, bits);
+                }
+                writer.write(7 - 1, 4);
+                writer.write(*shift as u64, 5);
+                for coef in coefs {
+                    writer.write_signed(*coef, 7);
+                }
+                write_residual(writer, &residual(samples, coefs, *shift));
+            }
+            TestSubframe::Partitioned(order, partition_order, param_vec) => {
+                let coefs: [&[i64]; 5] = [&[], &[1], &[2, -1], &[3, -3, 1], &[4, -6, 4, -1]];
+                writer.write(((8 + order) << 1) as u64, 8);
+                for val in &samples[..*order] {
+                    writer.write_signed(*val, bits);
+                }
+                write_partitions(writer, &residual(samples, coefs[*order], 0), *order, *partition_order, param_vec);
+            }
+            TestSubframe::Wasted(wasted) => {
+                writer.write(1 << 1 | 1, 8);
+                writer.write(1, *wasted);
+                for val in samples {
+                    writer.write_signed(*val >> wasted, bits - wasted);
+                }
+            }
+        }
+    }
+
+    fn residual(samples: &[i64], coefs: &[i64], shift: usize) -> Vec<i64> {
+        (coefs.len()..samples.len()).map(|idx| {
+            let prediction: i64 = coefs.iter().enumerate().map(|(coef_idx, coef)| coef * samples[idx - 1 - coef_idx]).sum();
+            samples[idx] - (prediction >> shift)
+        }).collect()
+    }
+
+    fn write_frame(stream: &mut Vec<u8>, frame_idx: u64, ch_code: u64, ch_vec: &[Vec<i64>], bits: usize, subframes: &[TestSubframe]) {
+        let mut writer = BitWriter::new();
+        writer.write(0x3FFE, 14);
+        writer.write(0, 2);
+        writer.write(7, 4);
+        writer.write(0, 4);
+        writer.write(ch_code, 4);
+        writer.write(0, 4);
+        writer.write(frame_idx, 8);
+        writer.write(ch_vec[0].len() as u64 - 1, 16);
+        let header_crc = crc8(&writer.buf);
+        writer.write(u64::from(header_crc), 8);
+        for (ch_idx, (samples, subframe)) in ch_vec.iter().zip(subframes).enumerate() {
+            let side = matches!((ch_code, ch_idx), (8, 1) | (9, 0) | (10, 1));
+            write_subframe(&mut writer, samples, bits + usize::from(side), subframe);
+        }
+        writer.align();
+        let frame_crc = crc16(&writer.buf);
+        writer.write(u64::from(frame_crc), 16);
+        stream.append(&mut writer.buf);
+    }
+
+    // Stereo 16bit of 3 frames: independent, left/side and mid/side.
+    fn test_stream() -> (Vec<u8>, Vec<Vec<i64>>) {
+        let frame_len = 24;
+        let left: Vec<i64> = (0..(frame_len * 3)).map(|idx| ((idx as f64 * 0.3).sin() * 12000.0) as i64).collect();
+        let right: Vec<i64> = (0..(frame_len * 3)).map(|idx| if idx < frame_len { -300 } else { ((idx as f64 * 0.2).cos() * 8000.0) as i64 * 4 }).collect();
+        let block = |ch: &[i64], frame: usize| ch[(frame * frame_len)..((frame + 1) * frame_len)].to_vec();
+
+        let mut frames: Vec<u8> = Vec::new();
+        write_frame(&mut frames, 0, 1, &[block(&left, 0), block(&right, 0)], 16, &[TestSubframe::Verbatim, TestSubframe::Constant]);
+        let side: Vec<i64> = block(&left, 1).iter().zip(block(&right, 1)).map(|(l, r)| l - r).collect();
+        write_frame(&mut frames, 1, 8, &[block(&left, 1), side], 16, &[TestSubframe::Fixed(2), TestSubframe::Lpc(vec![30, -14], 4)]);
+        let mid: Vec<i64> = block(&left, 2).iter().zip(block(&right, 2)).map(|(l, r)| (l + r) >> 1).collect();
+        let side: Vec<i64> = block(&left, 2).iter().zip(block(&right, 2)).map(|(l, r)| l - r).collect();
+        write_frame(&mut frames, 2, 10, &[mid, side], 16, &[TestSubframe::Fixed(4), TestSubframe::Fixed(0)]);
+
+        let mut md5 = Md5::new();
+        for idx in 0..left.len() {
+            md5.update(&(left[idx] as i16).to_le_bytes());
+            md5.update(&(right[idx] as i16).to_le_bytes());
+        }
+        let mut stream_info = BitWriter::new();
+        stream_info.write(frame_len as u64, 16);
+        stream_info.write(frame_len as u64, 16);
+        stream_info.write(0, 48);
+        stream_info.write(44100, 20);
+        stream_info.write(2 - 1, 3);
+        stream_info.write(16 - 1, 5);
+        stream_info.write(left.len() as u64, 36);
+        stream_info.buf.extend_from_slice(&md5.finalize());
+
+        let mut comment: Vec<u8> = Vec::new();
+        for text in ["test", "TITLE=Archive 1", "artist=Parrot"].iter() {
+            comment.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            comment.extend_from_slice(text.as_bytes());
+            if *text == "test" {
+                comment.extend_from_slice(&2_u32.to_le_bytes());
+            }
+        }
+
+        let mut stream: Vec<u8> = b"fLaC".to_vec();
+        stream.extend_from_slice(&[0x00, 0, 0, 34]);
+        stream.extend_from_slice(&stream_info.buf);
+        stream.extend_from_slice(&[0x84, 0, 0, comment.len() as u8]);
+        stream.extend_from_slice(&comment);
+        stream.append(&mut frames);
+        (stream, vec![left, right])
+    }
+
+    #[test]
+    fn test_flac_decode() {
+        let (stream, ch_vec) = test_stream();
+        let mut flac_file = FlacFile::new();
+        flac_file.open_bytes(stream).unwrap();
+        assert_eq!(flac_file.stream_info.sampling_rate, 44100);
+        assert_eq!(flac_file.stream_info.total_samples, 72);
+
+        let wav_audio = flac_file.get_wav_audio().unwrap();
+        assert_eq!((wav_audio.fmt.channel, wav_audio.fmt.bits), (2, 16));
+        let decoded = to_channel_vec_as::<i16>(&wav_audio).unwrap();
+        for (ch, decoded_ch) in ch_vec.iter().zip(decoded.iter()) {
+            assert_eq!(&ch.iter().map(|val| *val as i16).collect::<Vec<i16>>(), decoded_ch);
+        }
+
+        let info_tags = flac_file.info_tags();
+        assert_eq!(info_tags.get(INFO_TITLE), Some("Archive 1"));
+        assert_eq!(info_tags.get(INFO_ARTIST), Some("Parrot"));
+        let wav_file = flac_file.to_wav_file().unwrap();
+        assert_eq!(wav_file.get_wav_audio().unwrap().data, wav_audio.data);
+    }
+
+    #[test]
+    fn test_flac_wasted_bits() {
+        let samples: Vec<i64> = (0..16).map(|idx| (idx * 37 - 300) << 4).collect();
+        let mut frames: Vec<u8> = Vec::new();
+        write_frame(&mut frames, 0, 0, std::slice::from_ref(&samples), 24, &[TestSubframe::Wasted(4)]);
+        let mut md5 = Md5::new();
+        for val in &samples {
+            md5.update(&(*val as i32).to_le_bytes()[..3]);
+        }
+        let mut stream: Vec<u8> = b"fLaC".to_vec();
+        stream.extend_from_slice(&[0x80, 0, 0, 34, 0, 16, 0, 16, 0, 0, 0, 0, 0, 0]);
+        // 48000Hz, mono, 24bit, 16 samples
+        stream.extend_from_slice(&[0x0B, 0xB8, 0x01, 0x70, 0, 0, 0, 16]);
+        stream.extend_from_slice(&md5.finalize());
+        stream.append(&mut frames);
+
+        let mut flac_file = FlacFile::new();
+        flac_file.open_bytes(stream).unwrap();
+        let wav_audio = flac_file.get_wav_audio().unwrap();
+        assert_eq!((wav_audio.fmt.sampling_rate, wav_audio.fmt.bits), (48000, 24));
+        assert_eq!(&wav_audio.data[..3], &[0x40, 0xED, 0xFF]);
+    }
+
+    #[test]
+    fn test_flac_low_bits() {
+        // 4bit is left justified in 8bit unsigned.
+        let samples: Vec<i64> = vec![-8, -1, 0, 1, 7];
+        let mut frames: Vec<u8> = Vec::new();
+        write_frame(&mut frames, 0, 0, std::slice::from_ref(&samples), 4, &[TestSubframe::Verbatim]);
+        let mut md5 = Md5::new();
+        for val in &samples {
+            md5.update(&(*val as i8).to_le_bytes());
+        }
+        let mut stream: Vec<u8> = b"fLaC".to_vec();
+        stream.extend_from_slice(&[0x80, 0, 0, 34, 0, 16, 0, 16, 0, 0, 0, 0, 0, 0]);
+        // 48000Hz, mono, 4bit, 5 samples
+        stream.extend_from_slice(&[0x0B, 0xB8, 0x00, 0x30, 0, 0, 0, 5]);
+        stream.extend_from_slice(&md5.finalize());
+        stream.append(&mut frames);
+
+        let mut flac_file = FlacFile::new();
+        flac_file.open_bytes(stream).unwrap();
+        let wav_audio = flac_file.get_wav_audio().unwrap();
+        assert_eq!((wav_audio.fmt.bits, wav_audio.fmt.valid_bits), (8, 4));
+        assert_eq!(wav_audio.data, vec![0x00, 0x70, 0x80, 0x90, 0xF0]);
+    }
+
+    #[test]
+    fn test_flac_partitions() {
+        // Quiet, loud, noisy and quiet again in 4 partitions of 16 samples.
+        // Rice parameters of 4 and 5 bits, and escaped partitions (decoded to the same samples by symphonia)
+        let samples: Vec<i64> = (0..64).map(|idx| match idx / 16 {
+            0 => idx % 3 - 1,
+            1 => (((idx as f64) * 0.7).sin() * 20000.0) as i64,
+            2 => if idx % 2 == 0 { 30000 } else { -30000 },
+            _ => 5 - idx % 11,
+        }).collect();
+        let mut frames: Vec<u8> = Vec::new();
+        let subframe_vec = [
+            TestSubframe::Partitioned(2, 2, vec![None, Some(12), Some(18), Some(16)]),
+            TestSubframe::Partitioned(1, 3, vec![Some(0), None, Some(2), Some(15), Some(16), Some(17), None, Some(0)]),
+        ];
+        for (frame_idx, subframe) in subframe_vec.iter().enumerate() {
+            write_frame(&mut frames, frame_idx as u64, 0, std::slice::from_ref(&samples), 16, std::slice::from_ref(subframe));
+        }
+        let mut md5 = Md5::new();
+        for _ in 0..subframe_vec.len() {
+            for val in &samples {
+                md5.update(&(*val as i16).to_le_bytes());
+            }
+        }
+        let mut stream: Vec<u8> = b"fLaC".to_vec();
+        stream.extend_from_slice(&[0x80, 0, 0, 34, 0, 64, 0, 64, 0, 0, 0, 0, 0, 0]);
+        // 48000Hz, mono, 16bit, 128 samples
+        stream.extend_from_slice(&[0x0B, 0xB8, 0x00, 0xF0, 0, 0, 0, 128]);
+        stream.extend_from_slice(&md5.finalize());
+        stream.append(&mut frames);
+
+        let mut flac_file = FlacFile::new();
+        flac_file.open_bytes(stream).unwrap();
+        let decoded = to_channel_vec_as::<i16>(&flac_file.get_wav_audio().unwrap()).unwrap();
+        let expected: Vec<i16> = samples.iter().chain(samples.iter()).map(|val| *val as i16).collect();
+        assert_eq!(decoded[0], expected);
+    }
+
+    // Streams of the claxon test samples
+    #[test]
+    fn test_flac_encoded_files() {
+        // Stereo 24bit of a frame: mid/side, LPC of order 18 and 20, 32 and 64 partitions.
+        // "STREAMINFO" has no MD5, so the MD5 of the samples decoded by claxon is checked.
+        let mut flac_file = FlacFile::new();
+        flac_file.open_bytes(include_bytes!("../testdata/non_subset.flac").to_vec()).unwrap();
+        let wav_audio = flac_file.get_wav_audio().unwrap();
+        assert_eq!((wav_audio.fmt.channel, wav_audio.fmt.bits, wav_audio.data.len()), (2, 24, 4096 * 2 * 3));
+        let mut md5 = Md5::new();
+        md5.update(&wav_audio.data);
+        assert_eq!(md5.finalize(), [0x61, 0xcd, 0x79, 0xcc, 0x49, 0xe9, 0x4a, 0x0b, 0x42, 0xda, 0xa3, 0x86, 0xce, 0x2f, 0x9d, 0x31]);
+
+        // ID3v1 after the last frame
+        let mut stream = include_bytes!("../testdata/wasted_bits.flac").to_vec();
+        stream.extend_from_slice(b"TAG");
+        stream.resize(stream.len() + 125, 0);
+        flac_file.open_bytes(stream).unwrap();
+        assert_eq!(flac_file.get_wav_audio().unwrap().data.len(), 4410 * 2);
+        // Unknown length
+        flac_file.stream_info.total_samples = 0;
+        assert_eq!(flac_file.get_wav_audio().unwrap().data.len(), 4410 * 2);
+    }
+
+    // Streams of the reference encoder (libFLAC 1.3.2) with the MD5 in "STREAMINFO"
+    #[test]
+    fn test_flac_reference_encoder() {
+        // Mono 16bit of 4410 samples: fixed predictors, partition order 0 and 1
+        let mut flac_file = FlacFile::new();
+        flac_file.open_bytes(include_bytes!("../testdata/wasted_bits.flac").to_vec()).unwrap();
+        assert_eq!(flac_file.stream_info.md5, [0x4f, 0xbc, 0xa4, 0xcf, 0x30, 0xf1, 0x88, 0x45, 0x3c, 0x06, 0x76, 0xe0, 0xcd, 0x70, 0x0c, 0x71]);
+        assert_eq!(flac_file.get_wav_audio().unwrap().data.len(), 4410 * 2);
+        // The MD5 is checked.
+        flac_file.stream_info.md5[0] ^= 0x01;
+        assert!(matches!(flac_file.get_wav_audio(), Err(WavFileError::Md5Mismatch { .. })));
+
+        // Mono 16bit of 4 samples
+        flac_file.open_bytes(include_bytes!("../testdata/short.flac").to_vec()).unwrap();
+        assert_eq!(flac_file.stream_info.md5, [0x92, 0x75, 0x98, 0xb8, 0x9c, 0x89, 0xc1, 0x12, 0x9a, 0x15, 0x2e, 0xec, 0xfc, 0x14, 0x07, 0x5e]);
+        assert_eq!(flac_file.get_wav_audio().unwrap().data.len(), 4 * 2);
+    }
+
+    #[test]
+    fn test_flac_corrupt() {
+        let (mut stream, _) = test_stream();
+        // Signature
+        stream[30] ^= 0x01;
+        let mut flac_file = FlacFile::new();
+        flac_file.open_bytes(stream.clone()).unwrap();
+        assert!(matches!(flac_file.get_wav_audio(), Err(WavFileError::Md5Mismatch { .. })));
+        // Last byte of frame CRC
+        stream[30] ^= 0x01;
+        let last = stream.len() - 1;
+        stream[last] ^= 0x01;
+        flac_file.open_bytes(stream).unwrap();
+        assert!(matches!(flac_file.get_wav_audio(), Err(WavFileError::FlacFrame { reason: "CRC-16", .. })));
+
+        assert!(matches!(flac_file.open_bytes(b"RIFF0000WAVE".to_vec()), Err(WavFileError::NotFlac { .. })));
+    }
+}
+
+// FLAC "STREAMINFO"
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FlacStreamInfo {
+    pub min_block_size: usize,
+    pub max_block_size: usize,
+    pub min_frame_size: usize,  // 0: Unknown
+    pub max_frame_size: usize,  // 0: Unknown
+    pub sampling_rate: usize,
+    pub channel: usize,
+    pub bits: usize,
+    pub total_samples: usize,   // per channel, 0: Unknown
+    pub md5: [u8;16],           // of the decoded samples, all 0: Not calculated
+}
+
+// FLAC (Free Lossless Audio Codec) file. Read only.
+// Frames are decoded by get_wav_audio() and checked by CRC and the MD5 signature.
+pub struct FlacFile {
+    pub file_path: PathBuf,
+    pub size: usize,
+    pub stream_info: FlacStreamInfo,
+    pub vorbis_comments: Vec<(String, String)>,
+    frames: Vec<u8>,
+    frames_addr: usize,
+}
+
+impl FlacFile {
+    pub fn new() -> FlacFile {
+        FlacFile {file_path:PathBuf::new(), size:0, stream_info:FlacStreamInfo::default(), vorbis_comments:Vec::new(), frames:Vec::new(), frames_addr:0}
+    }
+
+    pub fn open(&mut self, file_path: &Path) -> Result<()> {
+        // -- Check Parameter --
+        if !file_path.is_file() {
+            return Err(WavFileError::NotFile { path: file_path.to_path_buf() });
+        }
+        let target_file = File::open(file_path)?;
+        let mut buf = Vec::new();
+        BufReader::new(&target_file).read_to_end(&mut buf)?;
+        self.open_bytes(buf)?;
+        self.file_path = file_path.to_path_buf();
+        Ok(())
+    }
+
+    pub fn open_bytes(&mut self, buf: Vec<u8>) -> Result<()> {
+        let file_size = buf.len();
+        let mut addr = id3v2_size(&buf);
+        if file_size < addr + 8 {
+            return Err(WavFileError::TruncatedHeader { expected: addr + 8, actual: file_size });
+        }
+        // "fLaC"
+        let marker = <[u8;4]>::try_from(&buf[addr..(addr + 4)])?;
+        if marker != [b'f',b'L',b'a',b'C'] {
+            return Err(WavFileError::NotFlac { id: marker });
+        }
+        addr += 4;
+
+        // Metadata blocks: last flag, type and size, "STREAMINFO" first.
+        let mut op_stream_info: Option<FlacStreamInfo> = None;
+        let mut vorbis_comments: Vec<(String, String)> = Vec::new();
+        loop {
+            if file_size < addr + 4 {
+                return Err(WavFileError::TruncatedHeader { expected: addr + 4, actual: file_size });
+            }
+            let is_last = buf[addr] & 0x80 != 0;
+            let block_type = buf[addr] & 0x7F;
+            let block_size = usize::try_from(u32::from_be_bytes([0, buf[addr + 1], buf[addr + 2], buf[addr + 3]]))?;
+            let body_addr = addr + 4;
+            if file_size - body_addr < block_size {
+                return Err(WavFileError::TruncatedChunk { id: [b'M',b'E',b'T',b'A'], offset: addr, expected: block_size, actual: file_size - body_addr });
+            }
+            let body = &buf[body_addr..(body_addr + block_size)];
+            match block_type {
+                0 => op_stream_info = Some(get_stream_info(body)?),
+                4 => vorbis_comments = get_vorbis_comments(body)?,
+                _ => {}
+            }
+            addr = body_addr + block_size;
+            if is_last {
+                break;
+            }
+        }
+        let stream_info = op_stream_info.ok_or(WavFileError::MissingChunk { id: [b'S',b'T',b'R',b'M'] })?;
+
+        self.size = file_size;
+        self.stream_info = stream_info;
+        self.vorbis_comments = vorbis_comments;
+        self.frames = buf;
+        self.frames_addr = addr;
+        Ok(())
+    }
+
+    pub fn fmt(&self) -> Fmt {
+        Fmt {
+            id: WAVE_FORMAT_PCM,
+            channel: self.stream_info.channel,
+            sampling_rate: self.stream_info.sampling_rate,
+            bits: self.stream_info.bits.div_ceil(8) * 8,
+            valid_bits: self.stream_info.bits,
+            channel_mask: 0,
+        }
+    }
+
+    // All frames as WAV PCM. Samples are left justified when the bits are not a multiple of 8.
+    pub fn get_wav_audio(&self) -> Result<WavAudio> {
+        let fmt = self.fmt();
+        fmt_check(&fmt)?;
+        let size = fmt.bits / 8;
+        let md5_size = self.stream_info.bits.div_ceil(8);
+        let shift = fmt.bits - fmt.valid_bits;
+        // "total_samples" is not trusted for allocation.
+        let mut data: Vec<u8> = Vec::new();
+        let mut md5 = Md5::new();
+        let mut md5_bytes: Vec<u8> = Vec::new();
+        let mut frame_num: usize = 0;
+        let mut reader = BitReader::new(&self.frames, self.frames_addr);
+        while !reader.is_end() {
+            // Data after the last frame (ID3v1 "TAG") is not decoded.
+            if (self.stream_info.total_samples != 0 && frame_num >= self.stream_info.total_samples) || is_id3v1(&self.frames[reader.addr()..]) {
+                break;
+            }
+            let ch_vec = decode_frame(&mut reader, &self.stream_info)?;
+            let mut block_size = ch_vec[0].len();
+            // The last frame may be longer than the stream.
+            if self.stream_info.total_samples != 0 {
+                block_size = block_size.min(self.stream_info.total_samples.saturating_sub(frame_num));
+            }
+            md5_bytes.clear();
+            for frame_idx in 0..block_size {
+                for ch in &ch_vec {
+                    let val = ch[frame_idx];
+                    md5_bytes.extend_from_slice(&val.to_le_bytes()[..md5_size]);
+                    if size == 1 {
+                        data.push(((val << shift) as u8) ^ 0x80);
+                    }
+                    else {
+                        data.extend_from_slice(&(val << shift).to_le_bytes()[..size]);
+                    }
+                }
+            }
+            md5.update(&md5_bytes);
+            frame_num += block_size;
+        }
+        if self.stream_info.total_samples != 0 && frame_num != self.stream_info.total_samples {
+            return Err(WavFileError::DataLength { expected: self.stream_info.total_samples, actual: frame_num });
+        }
+        let actual = md5.finalize();
+        if self.stream_info.md5 != [0; 16] && actual != self.stream_info.md5 {
+            return Err(WavFileError::Md5Mismatch { expected: self.stream_info.md5, actual });
+        }
+        Ok(WavAudio { fmt, data })
+    }
+
+    // "VORBIS_COMMENT" fields as INFO tags. Field names are case insensitive.
+    pub fn info_tags(&self) -> InfoTags {
+        let mut info_tags = InfoTags::new();
+        for (name, text) in &self.vorbis_comments {
+            let op_id = match name.to_ascii_uppercase().as_str() {
+                "TITLE" => Some(INFO_TITLE),
+                "ARTIST" => Some(INFO_ARTIST),
+                "COMMENT" | "DESCRIPTION" => Some(INFO_COMMENT),
+                "DATE" => Some(INFO_CREATION_DATE),
+                "ENCODER" => Some(INFO_SOFTWARE),
+                "GENRE" => Some(INFO_GENRE),
+                "COPYRIGHT" => Some(INFO_COPYRIGHT),
+                "ALBUM" => Some(INFO_PRODUCT),
+                _ => None,
+            };
+            if let Some(id) = op_id {
+                if info_tags.get(id).is_none() {
+                    info_tags.set(id, text);
+                }
+            }
+        }
+        info_tags
+    }
+
+    // WavFile with the decoded audio and INFO tags.
+    pub fn to_wav_file(&self) -> Result<WavFile> {
+        let mut wav_file = WavFile::new();
+        wav_file.update_wav_audio(&self.get_wav_audio()?)?;
+        wav_file.set_info_tags(&self.info_tags())?;
+        Ok(wav_file)
+    }
+}
+
+impl Default for FlacFile {
+    fn default() -> FlacFile {
+        FlacFile::new()
+    }
+}
+
+// Size of an ID3v2 tag in front of "fLaC" (0 if none).
+fn id3v2_size(buf: &[u8]) -> usize {
+    if buf.len() < 10 || &buf[0..3] != b"ID3" {
+        return 0;
+    }
+    // Sync safe integer (7 bits per byte), footer flag
+    let size = buf[6..10].iter().fold(0, |size, byte| (size << 7) | usize::from(byte & 0x7F));
+    10 + size + if buf[5] & 0x10 != 0 { 10 } else { 0 }
+}
+
+// ID3v1 tag of 128 bytes at the end of the file
+fn is_id3v1(buf: &[u8]) -> bool {
+    buf.len() == 128 && buf.starts_with(b"TAG")
+}
+
+fn get_stream_info(ref_block_body: &[u8]) -> Result<FlacStreamInfo> {
+    if ref_block_body.len() < 34 {
+        return Err(WavFileError::FmtSize { expected: 34, actual: ref_block_body.len() });
+    }
+    let mut reader = BitReader::new(ref_block_body, 0);
+    let mut stream_info = FlacStreamInfo {
+        min_block_size: usize::try_from(reader.read(16)?)?,
+        max_block_size: usize::try_from(reader.read(16)?)?,
+        min_frame_size: usize::try_from(reader.read(24)?)?,
+        max_frame_size: usize::try_from(reader.read(24)?)?,
+        sampling_rate: usize::try_from(reader.read(20)?)?,
+        channel: usize::try_from(reader.read(3)?)? + 1,
+        bits: usize::try_from(reader.read(5)?)? + 1,
+        total_samples: usize::try_from(reader.read(36)?)?,
+        md5: [0; 16],
+    };
+    stream_info.md5.copy_from_slice(&ref_block_body[18..34]);
+    if stream_info.bits < 4 {
+        return Err(WavFileError::Bits { bits: stream_info.bits });
+    }
+    Ok(stream_info)
+}
+
+// Vendor string and "NAME=value" fields, little endian lengths.
+fn get_vorbis_comments(ref_block_body: &[u8]) -> Result<Vec<(String, String)>> {
+    let truncated = |addr: usize| WavFileError::TruncatedChunk { id: [b'V',b'O',b'R',b'B'], offset: addr, expected: addr + 4, actual: ref_block_body.len() };
+    let read_len = |addr: usize| -> Result<usize> {
+        let bytes = ref_block_body.get(addr..(addr + 4)).ok_or_else(|| truncated(addr))?;
+        Ok(usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(bytes)?))?)
+    };
+    let mut addr = 4 + read_len(0)?;
+    let field_num = read_len(addr)?;
+    addr += 4;
+    let mut comments: Vec<(String, String)> = Vec::new();
+    for _ in 0..field_num {
+        let len = read_len(addr)?;
+        let field = ref_block_body.get((addr + 4)..(addr + 4 + len)).ok_or_else(|| truncated(addr))?;
+        let field = String::from_utf8_lossy(field);
+        if let Some((name, text)) = field.split_once('=') {
+            comments.push((name.to_string(), text.to_string()));
+        }
+        addr += 4 + len;
+    }
+    Ok(comments)
+}
+
+// Big endian bit stream of the frames.
+struct BitReader<'a> {
+    buf: &'a [u8],
+    bit_pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(buf: &'a [u8], addr: usize) -> BitReader<'a> {
+        BitReader { buf, bit_pos: addr * 8 }
+    }
+
+    fn addr(&self) -> usize {
+        self.bit_pos / 8
+    }
+
+    fn is_end(&self) -> bool {
+        self.addr() >= self.buf.len()
+    }
+
+    fn end_of_stream(&self) -> WavFileError {
+        WavFileError::FlacFrame { offset: self.addr(), reason: "Unexpected End of Stream" }
+    }
+
+    // Up to 64 bits.
+    fn read(&mut self, bits: usize) -> Result<u64> {
+        let mut val: u64 = 0;
+        let mut remain = bits;
+        while remain > 0 {
+            let byte = *self.buf.get(self.addr()).ok_or_else(|| self.end_of_stream())?;
+            let avail = 8 - self.bit_pos % 8;
+            let take = avail.min(remain);
+            let part = (u64::from(byte) >> (avail - take)) & ((1 << take) - 1);
+            val = (val << take) | part;
+            self.bit_pos += take;
+            remain -= take;
+        }
+        Ok(val)
+    }
+
+    // Two's complement of "bits".
+    fn read_signed(&mut self, bits: usize) -> Result<i64> {
+        if bits == 0 {
+            return Ok(0);
+        }
+        let val = self.read(bits)?;
+        Ok(((val << (64 - bits)) as i64) >> (64 - bits))
+    }
+
+    // Number of 0 bits before a 1 bit.
+    fn read_unary(&mut self) -> Result<u64> {
+        let mut count: u64 = 0;
+        loop {
+            let byte = *self.buf.get(self.addr()).ok_or_else(|| self.end_of_stream())? << (self.bit_pos % 8);
+            if byte == 0 {
+                count += (8 - self.bit_pos % 8) as u64;
+                self.bit_pos = (self.addr() + 1) * 8;
+            }
+            else {
+                let zeros = byte.leading_zeros();
+                count += u64::from(zeros);
+                self.bit_pos += zeros as usize + 1;
+                return Ok(count);
+            }
+        }
+    }
+
+    fn align(&mut self) {
+        self.bit_pos = self.bit_pos.div_ceil(8) * 8;
+    }
+}
+
+// CRC-8 (x^8 + x^2 + x^1 + 1) of frame header
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 })
+    })
+}
+
+// CRC-16 (x^16 + x^15 + x^2 + 1) of whole frame
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ (u16::from(*byte) << 8), |crc, _| if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 })
+    })
+}
+
+// Frame header, subframes of each channel and footer. Returns channel<samples> after decorrelation.
+fn decode_frame(reader: &mut BitReader, ref_stream_info: &FlacStreamInfo) -> Result<Vec<Vec<i64>>> {
+    let frame_addr = reader.addr();
+    let frame_error = |reason: &'static str| WavFileError::FlacFrame { offset: frame_addr, reason };
+    // Sync code, reserved and blocking strategy
+    if reader.read(14)? != 0x3FFE || reader.read(1)? != 0 {
+        return Err(frame_error("Frame Sync"));
+    }
+    reader.read(1)?;
+    let block_size_code = reader.read(4)?;
+    let sampling_rate_code = reader.read(4)?;
+    let channel_code = reader.read(4)?;
+    let bits_code = reader.read(3)?;
+    reader.read(1)?;
+    // Frame or sample number in UTF-8 like coding
+    let head = reader.read(8)?;
+    let extra = match (head as u8).leading_ones() {
+        0 => 0,
+        len @ 2..=7 => len - 1,
+        _ => return Err(frame_error("Frame Number")),
+    };
+    for _ in 0..extra {
+        if reader.read(8)? & 0xC0 != 0x80 {
+            return Err(frame_error("Frame Number"));
+        }
+    }
+    let block_size = match block_size_code {
+        1 => 192,
+        2..=5 => 576 << (block_size_code - 2),
+        6 => reader.read(8)? as usize + 1,
+        7 => reader.read(16)? as usize + 1,
+        8..=15 => 256 << (block_size_code - 8),
+        _ => return Err(frame_error("Block Size")),
+    };
+    match sampling_rate_code {
+        12 => { reader.read(8)?; }
+        13 | 14 => { reader.read(16)?; }
+        15 => return Err(frame_error("Sampling Rate")),
+        _ => {}
+    }
+    let bits = match bits_code {
+        0 => ref_stream_info.bits,
+        1 => 8,
+        2 => 12,
+        4 => 16,
+        5 => 20,
+        6 => 24,
+        7 => 32,
+        _ => return Err(frame_error("Bits")),
+    };
+    let channel = match channel_code {
+        0..=7 => channel_code as usize + 1,
+        8..=10 => 2,
+        _ => return Err(frame_error("Channel Assignment")),
+    };
+    if channel != ref_stream_info.channel || bits != ref_stream_info.bits {
+        return Err(frame_error("Not Match STREAMINFO"));
+    }
+    let header_addr = reader.addr();
+    if reader.read(8)? as u8 != crc8(&reader.buf[frame_addr..header_addr]) {
+        return Err(frame_error("CRC-8"));
+    }
+
+    // Side channel has 1 more bit.
+    let mut ch_vec: Vec<Vec<i64>> = Vec::with_capacity(channel);
+    for ch_idx in 0..channel {
+        let side = matches!((channel_code, ch_idx), (8, 1) | (9, 0) | (10, 1));
+        ch_vec.push(decode_subframe(reader, block_size, bits + usize::from(side), frame_addr)?);
+    }
+    if let [ch_0, ch_1] = &mut ch_vec[..] {
+        let pairs = ch_0.iter_mut().zip(ch_1.iter_mut());
+        match channel_code {
+            // left/side
+            8 => for (left, side) in pairs { *side = left.wrapping_sub(*side); },
+            // side/right
+            9 => for (side, right) in pairs { *side = side.wrapping_add(*right); },
+            // mid/side
+            10 => for (mid, side) in pairs {
+                let mid_side = (*mid << 1) | (*side & 1);
+                *mid = mid_side.wrapping_add(*side) >> 1;
+                *side = mid_side.wrapping_sub(*side) >> 1;
+            },
+            _ => {}
+        }
+    }
+
+    reader.align();
+    let footer_addr = reader.addr();
+    if reader.read(16)? as u16 != crc16(&reader.buf[frame_addr..footer_addr]) {
+        return Err(frame_error("CRC-16"));
+    }
+    Ok(ch_vec)
+}
+
+// Fixed predictor coefficients of order 0..4
+const FIXED_COEFS: [&[i64]; 5] = [&[], &[1], &[2, -1], &[3, -3, 1], &[4, -6, 4, -1]];
+
+fn decode_subframe(reader: &mut BitReader, block_size: usize, bits: usize, frame_addr: usize) -> Result<Vec<i64>> {
+    let frame_error = |reason: &'static str| WavFileError::FlacFrame { offset: frame_addr, reason };
+    if reader.read(1)? != 0 {
+        return Err(frame_error("Subframe Padding"));
+    }
+    let subframe_type = reader.read(6)?;
+    let wasted = if reader.read(1)? == 1 { reader.read_unary()? as usize + 1 } else { 0 };
+    if wasted >= bits {
+        return Err(frame_error("Wasted Bits"));
+    }
+    let bits = bits - wasted;
+    let mut samples: Vec<i64> = Vec::with_capacity(block_size);
+    match subframe_type {
+        // CONSTANT
+        0 => samples.resize(block_size, reader.read_signed(bits)?),
+        // VERBATIM
+        1 => for _ in 0..block_size { samples.push(reader.read_signed(bits)?); },
+        // FIXED
+        8..=12 => {
+            let order = subframe_type as usize - 8;
+            if order > block_size {
+                return Err(frame_error("Predictor Order"));
+            }
+            for _ in 0..order {
+                samples.push(reader.read_signed(bits)?);
+            }
+            decode_residual(reader, block_size, order, &mut samples, frame_addr)?;
+            predict(&mut samples, FIXED_COEFS[order], 0);
+        }
+        // LPC
+        32..=63 => {
+            let order = subframe_type as usize - 31;
+            if order > block_size {
+                return Err(frame_error("Predictor Order"));
+            }
+            for _ in 0..order {
+                samples.push(reader.read_signed(bits)?);
+            }
+            let precision = reader.read(4)? as usize + 1;
+            let shift = reader.read_signed(5)?;
+            if precision == 16 || shift < 0 {
+                return Err(frame_error("LPC Coefficient"));
+            }
+            let mut coefs: Vec<i64> = Vec::with_capacity(order);
+            for _ in 0..order {
+                coefs.push(reader.read_signed(precision)?);
+            }
+            decode_residual(reader, block_size, order, &mut samples, frame_addr)?;
+            predict(&mut samples, &coefs, shift as u32);
+        }
+        _ => return Err(frame_error("Subframe Type")),
+    }
+    if wasted > 0 {
+        for val in samples.iter_mut() {
+            *val <<= wasted;
+        }
+    }
+    Ok(samples)
+}
+
+// Rice coded residuals after the warm-up samples, in 2^order partitions.
+fn decode_residual(reader: &mut BitReader, block_size: usize, order: usize, samples: &mut Vec<i64>, frame_addr: usize) -> Result<()> {
+    let frame_error = |reason: &'static str| WavFileError::FlacFrame { offset: frame_addr, reason };
+    let param_bits = match reader.read(2)? {
+        0 => 4,
+        1 => 5,
+        _ => return Err(frame_error("Residual Coding Method")),
+    };
+    let escape = (1 << param_bits) - 1;
+    let partition_order = reader.read(4)? as usize;
+    let partition_size = block_size >> partition_order;
+    if partition_size << partition_order != block_size || partition_size < order {
+        return Err(frame_error("Partition Order"));
+    }
+    for partition_idx in 0..(1 << partition_order) {
+        let len = if partition_idx == 0 { partition_size - order } else { partition_size };
+        let param = reader.read(param_bits)?;
+        if param == escape {
+            let raw_bits = reader.read(5)? as usize;
+            for _ in 0..len {
+                samples.push(reader.read_signed(raw_bits)?);
+            }
+        }
+        else {
+            for _ in 0..len {
+                let zigzag = reader.read_unary()?.wrapping_shl(param as u32) | reader.read(param as usize)?;
+                samples.push((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Adds the prediction to the residuals after the warm-up samples.
+// Broken streams may overflow, they are detected by CRC-16.
+fn predict(samples: &mut [i64], coefs: &[i64], shift: u32) {
+    for idx in coefs.len()..samples.len() {
+        let prediction = coefs.iter().enumerate().fold(0_i64, |sum, (coef_idx, coef)| sum.wrapping_add(coef.wrapping_mul(samples[idx - 1 - coef_idx])));
+        samples[idx] = samples[idx].wrapping_add(prediction >> shift);
+    }
+}
@@ -0,0 +1,185 @@
+use super::error::*;
+use super::{Fmt, Sample, fmt_check, WAVE_FORMAT_PCM, WAVE_FORMAT_IEEE_FLOAT};
+
+#[cfg(test)]
+mod tests {
+    use super::FrameView;
+    use super::super::{Fmt, I24, to_wav_audio, to_wav_audio_from, to_channel_vec_as};
+
+    #[test]
+    fn test_frame_view() {
+        let ch_vec = vec![vec![0.5, 0.25, -0.25, -1.0], vec![-0.5, -0.25, 0.25, 0.75]];
+        for (id, bits) in [(1, 8), (1, 16), (1, 24), (1, 32), (3, 32), (3, 64)].iter() {
+            let wav_audio = to_wav_audio(&ch_vec, &Fmt::new(*id, 2, 8000, *bits)).unwrap();
+            let frame_view = FrameView::new(&wav_audio.data, &wav_audio.fmt).unwrap();
+            assert_eq!(frame_view.frame_num(), 4);
+            assert_eq!(frame_view.channel(), 2);
+            assert_eq!(frame_view.to_channel_vec(), ch_vec);
+            assert_eq!(frame_view.sample(3, 1), Some(0.75));
+            assert_eq!(frame_view.sample(4, 0), None);
+            assert_eq!(frame_view.sample(0, 2), None);
+            assert_eq!(frame_view.channel_iter_f32(0).collect::<Vec<f32>>(), vec![0.5, 0.25, -0.25, -1.0]);
+            assert_eq!(frame_view.frames(1, 3).channel_iter(1).collect::<Vec<f64>>(), vec![-0.25, 0.25]);
+            assert_eq!(frame_view.frame_iter().nth(2).unwrap().collect::<Vec<f64>>(), vec![-0.25, 0.25]);
+        }
+    }
+
+    #[test]
+    fn test_frame_view_sample_type() {
+        // Integer PCM is read as it is.
+        let i16_vec: Vec<Vec<i16>> = vec![vec![i16::MIN, -1, 0, 1, i16::MAX]];
+        let wav_audio = to_wav_audio_from(&i16_vec, &Fmt::new(1, 1, 8000, 16)).unwrap();
+        let frame_view = FrameView::new(&wav_audio.data, &wav_audio.fmt).unwrap();
+        assert_eq!(frame_view.to_channel_vec_as::<i16>(), i16_vec);
+        assert_eq!(to_channel_vec_as::<i16>(&wav_audio).unwrap(), i16_vec);
+        let i24_vec: Vec<I24> = frame_view.channel_iter_as(0).collect();
+        assert_eq!(i24_vec[4], I24::new(0x7FFF00));
+        assert_eq!(frame_view.to_channel_vec_as::<f32>()[0][0], -1.0);
+    }
+
+    #[test]
+    fn test_frame_view_partial_frame() {
+        // A trailing incomplete frame is ignored.
+        let wav_audio = to_wav_audio(&vec![vec![0.5, -0.5], vec![0.25, -0.25]], &Fmt::new(1, 2, 8000, 16)).unwrap();
+        let frame_view = FrameView::new(&wav_audio.data[..6], &wav_audio.fmt).unwrap();
+        assert_eq!(frame_view.frame_num(), 1);
+        assert_eq!(frame_view.to_channel_vec(), vec![vec![0.5], vec![0.25]]);
+        assert!(FrameView::new(&wav_audio.data, &Fmt::new(2, 2, 8000, 16)).is_err());
+    }
+}
+
+// Decode one sample of the container size to -1.0..1.0.
+pub(crate) type Decoder = fn(&[u8]) -> f64;
+
+const I32_SCALE: f64 = 2147483648.0;
+
+fn decode_u8(bytes: &[u8]) -> f64 {
+    f64::from(i32::from_le_bytes([0, 0, 0, bytes[0] ^ 0x80])) / I32_SCALE
+}
+
+fn decode_i16(bytes: &[u8]) -> f64 {
+    f64::from(i32::from_le_bytes([0, 0, bytes[0], bytes[1]])) / I32_SCALE
+}
+
+fn decode_i24(bytes: &[u8]) -> f64 {
+    f64::from(i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]])) / I32_SCALE
+}
+
+fn decode_i32(bytes: &[u8]) -> f64 {
+    f64::from(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])) / I32_SCALE
+}
+
+fn decode_f32(bytes: &[u8]) -> f64 {
+    f64::from(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn decode_f64(bytes: &[u8]) -> f64 {
+    f64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
+}
+
+pub(crate) fn decoder(ref_fmt: &Fmt) -> Result<Decoder> {
+    match (ref_fmt.id, ref_fmt.bits) {
+        (WAVE_FORMAT_PCM, 8) => Ok(decode_u8),
+        (WAVE_FORMAT_PCM, 16) => Ok(decode_i16),
+        (WAVE_FORMAT_PCM, 24) => Ok(decode_i24),
+        (WAVE_FORMAT_PCM, 32) => Ok(decode_i32),
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => Ok(decode_f32),
+        (WAVE_FORMAT_IEEE_FLOAT, 64) => Ok(decode_f64),
+        (WAVE_FORMAT_PCM, bits) | (WAVE_FORMAT_IEEE_FLOAT, bits) => Err(WavFileError::Bits { bits }),
+        (format_id, _) => Err(WavFileError::FormatId { format_id }),
+    }
+}
+
+// Borrowed view of interleaved "data" chunk bytes.
+// Samples are decoded on access, nothing is copied.
+#[derive(Clone, Copy)]
+pub struct FrameView<'a> {
+    data: &'a [u8],
+    channel: usize,
+    sample_size: usize,
+    decoder: Decoder,
+}
+
+impl<'a> FrameView<'a> {
+    // A trailing incomplete frame is ignored.
+    pub fn new(data: &'a [u8], ref_fmt: &Fmt) -> Result<FrameView<'a>> {
+        fmt_check(ref_fmt)?;
+        let decoder = decoder(ref_fmt)?;
+        let sample_size = ref_fmt.bits / 8;
+        let block_size = ref_fmt.channel * sample_size;
+        Ok(FrameView {
+            data: &data[..(data.len() - data.len() % block_size)],
+            channel: ref_fmt.channel,
+            sample_size,
+            decoder,
+        })
+    }
+
+    pub fn channel(&self) -> usize {
+        self.channel
+    }
+
+    pub fn frame_num(&self) -> usize {
+        self.data.len() / self.block_size()
+    }
+
+    // Raw bytes of the frames in the view.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    // None if out of the view.
+    pub fn sample(&self, frame_idx: usize, ch: usize) -> Option<f64> {
+        if frame_idx >= self.frame_num() || ch >= self.channel {
+            return None;
+        }
+        let addr = frame_idx * self.block_size() + ch * self.sample_size;
+        Some((self.decoder)(&self.data[addr..(addr + self.sample_size)]))
+    }
+
+    // Sub view of frames "stt".."end". The range is clamped to the view.
+    pub fn frames(&self, stt: usize, end: usize) -> FrameView<'a> {
+        let end = end.min(self.frame_num());
+        let stt = stt.min(end);
+        FrameView {
+            data: &self.data[(stt * self.block_size())..(end * self.block_size())],
+            ..*self
+        }
+    }
+
+    // Samples of one channel. Empty if "ch" is out of the view.
+    pub fn channel_iter(&self, ch: usize) -> impl Iterator<Item = f64> + 'a {
+        let decoder = self.decoder;
+        let sample_size = self.sample_size;
+        let stt = if ch < self.channel { ch * sample_size } else { self.data.len() };
+        self.data[stt..].chunks_exact(sample_size).step_by(self.channel).map(decoder)
+    }
+
+    pub fn channel_iter_f32(&self, ch: usize) -> impl Iterator<Item = f32> + 'a {
+        self.channel_iter_as(ch)
+    }
+
+    // Samples of one channel in any sample type.
+    pub fn channel_iter_as<S: Sample>(&self, ch: usize) -> impl Iterator<Item = S> + 'a {
+        self.channel_iter(ch).map(S::from_f64)
+    }
+
+    // Frames, each of which yields the samples of all channels.
+    pub fn frame_iter(&self) -> impl Iterator<Item = impl Iterator<Item = f64> + 'a> + 'a {
+        let decoder = self.decoder;
+        let sample_size = self.sample_size;
+        self.data.chunks_exact(self.block_size()).map(move |frame| frame.chunks_exact(sample_size).map(decoder))
+    }
+
+    pub fn to_channel_vec(&self) -> Vec<Vec<f64>> {
+        self.to_channel_vec_as()
+    }
+
+    pub fn to_channel_vec_as<S: Sample>(&self) -> Vec<Vec<S>> {
+        (0..self.channel).map(|ch| self.channel_iter_as(ch).collect()).collect()
+    }
+
+    fn block_size(&self) -> usize {
+        self.channel * self.sample_size
+    }
+}
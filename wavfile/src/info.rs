@@ -0,0 +1,181 @@
+use std::convert::TryFrom;
+
+use super::error::*;
+use super::bytes::{read_u32, get_text};
+use super::{WavFile, SubChunk};
+
+#[cfg(test)]
+mod tests {
+    use super::{InfoTags, INFO_TITLE, INFO_ARTIST, INFO_COMMENT, INFO_SOFTWARE};
+    use super::super::{WavFile, SubChunk, Fmt, to_wav_audio};
+
+    fn test_wav_file() -> WavFile {
+        let mut wav_file = WavFile::new();
+        wav_file.update_wav_audio(&to_wav_audio(&vec![vec![0.0; 10]], &Fmt::new(1, 1, 8000, 16)).unwrap()).unwrap();
+        wav_file
+    }
+
+    #[test]
+    fn test_info_round_trip() {
+        let mut wav_file = test_wav_file();
+        // Other LIST chunk is not touched.
+        wav_file.sub_chunks.push(SubChunk { name: *b"LIST", body_size: 4, data: b"adtl".to_vec() });
+        assert!(wav_file.info_tags().unwrap().is_none());
+
+        let mut info_tags = InfoTags::new();
+        info_tags.set(INFO_TITLE, "A440");
+        info_tags.set(INFO_ARTIST, "Parrot");
+        info_tags.set(INFO_COMMENT, "odd");
+        info_tags.set(INFO_SOFTWARE, "resonance-parrot");
+        info_tags.set(INFO_ARTIST, "Resonance");
+        wav_file.set_info_tags(&info_tags).unwrap();
+
+        let path = std::env::temp_dir().join("wavfile_info_test.wav");
+        wav_file.save_as(&path).unwrap();
+        let mut read_file = WavFile::new();
+        read_file.open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let read_tags = read_file.info_tags().unwrap().unwrap();
+        assert_eq!(read_tags, info_tags);
+        assert_eq!(read_tags.title(), Some("A440"));
+        assert_eq!(read_tags.artist(), Some("Resonance"));
+        assert_eq!(read_tags.len(), 4);
+        assert_eq!(read_file.sub_chunks.iter().filter(|sub_chunk| sub_chunk.name == *b"LIST").count(), 2);
+
+        // Empty tags remove "LIST"/"INFO".
+        read_file.set_info_tags(&InfoTags::new()).unwrap();
+        assert!(read_file.info_tags().unwrap().is_none());
+        assert_eq!(read_file.sub_chunks.iter().filter(|sub_chunk| sub_chunk.name == *b"LIST").count(), 1);
+    }
+
+    #[test]
+    fn test_info_broken() {
+        let mut wav_file = test_wav_file();
+        let mut data = b"INFOINAM".to_vec();
+        data.extend_from_slice(&100_u32.to_le_bytes());
+        data.extend_from_slice(b"short\0");
+        wav_file.sub_chunks.push(SubChunk { name: *b"LIST", body_size: data.len(), data });
+        assert!(wav_file.info_tags().is_err());
+    }
+}
+
+// Common INFO tag IDs
+pub const INFO_TITLE: [u8;4] = [b'I',b'N',b'A',b'M'];
+pub const INFO_ARTIST: [u8;4] = [b'I',b'A',b'R',b'T'];
+pub const INFO_COMMENT: [u8;4] = [b'I',b'C',b'M',b'T'];
+pub const INFO_CREATION_DATE: [u8;4] = [b'I',b'C',b'R',b'D'];
+pub const INFO_SOFTWARE: [u8;4] = [b'I',b'S',b'F',b'T'];
+pub const INFO_GENRE: [u8;4] = [b'I',b'G',b'N',b'R'];
+pub const INFO_COPYRIGHT: [u8;4] = [b'I',b'C',b'O',b'P'];
+pub const INFO_PRODUCT: [u8;4] = [b'I',b'P',b'R',b'D'];
+
+const LIST_INFO: [u8;4] = [b'I',b'N',b'F',b'O'];
+
+// Tags in "LIST" chunk of "INFO" type. The order in the file is kept.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InfoTags {
+    tags: Vec<([u8;4], String)>,
+}
+
+impl InfoTags {
+    pub fn new() -> InfoTags {
+        InfoTags { tags: Vec::new() }
+    }
+
+    pub fn get(&self, id: [u8;4]) -> Option<&str> {
+        self.tags.iter().find(|(tag_id, _)| *tag_id == id).map(|(_, text)| text.as_str())
+    }
+
+    // Replace the existing tag or add it at the end.
+    pub fn set(&mut self, id: [u8;4], text: &str) {
+        match self.tags.iter_mut().find(|(tag_id, _)| *tag_id == id) {
+            Some((_, tag_text)) => *tag_text = text.to_string(),
+            None => self.tags.push((id, text.to_string())),
+        }
+    }
+
+    pub fn remove(&mut self, id: [u8;4]) {
+        self.tags.retain(|(tag_id, _)| *tag_id != id);
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, ([u8;4], String)> {
+        self.tags.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.get(INFO_TITLE)
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        self.get(INFO_ARTIST)
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.get(INFO_COMMENT)
+    }
+}
+
+impl WavFile {
+    // Tags of "LIST" chunk of "INFO" type. None if the file has no such chunk.
+    pub fn info_tags(&self) -> Result<Option<InfoTags>> {
+        match self.list_chunk_idx(LIST_INFO) {
+            Some(idx) => Ok(Some(get_info_tags(&self.sub_chunks[idx].data)?)),
+            None => Ok(None),
+        }
+    }
+
+    // Replace "LIST"/"INFO" chunk. Other "LIST" chunks are kept. Empty tags remove the chunk.
+    pub fn set_info_tags(&mut self, ref_info_tags: &InfoTags) -> Result<()> {
+        let op_idx = self.list_chunk_idx(LIST_INFO);
+        let op_sub_chunk = if ref_info_tags.is_empty() {
+            None
+        }
+        else {
+            let data = set_info_tags(ref_info_tags)?;
+            Some(SubChunk { name: [b'L',b'I',b'S',b'T'], body_size: data.len(), data })
+        };
+        self.replace_sub_chunk(op_idx, op_sub_chunk);
+        Ok(())
+    }
+}
+
+fn get_info_tags(ref_chunk_body: &[u8]) -> Result<InfoTags> {
+    let mut info_tags = InfoTags::new();
+    let mut tag_head_addr: usize = 0x04;
+    while tag_head_addr + 8 <= ref_chunk_body.len() {
+        let tag_head_buf = &ref_chunk_body[tag_head_addr..];
+        let id = <[u8;4]>::try_from(&tag_head_buf[0x00..0x04])?;
+        let size = read_u32(tag_head_buf, 0x04)?;
+        if tag_head_buf.len() - 8 < size {
+            return Err(WavFileError::TruncatedChunk { id, offset: tag_head_addr, expected: size, actual: tag_head_buf.len() - 8 });
+        }
+        // Text is NUL terminated.
+        info_tags.tags.push((id, get_text(&tag_head_buf[8..(8 + size)])));
+        // Odd size is followed by a pad byte.
+        tag_head_addr += 8 + size + (size & 1);
+    }
+    Ok(info_tags)
+}
+
+fn set_info_tags(ref_info_tags: &InfoTags) -> Result<Vec<u8>> {
+    let mut chunk_body: Vec<u8> = LIST_INFO.to_vec();
+    for (id, text) in ref_info_tags.iter() {
+        let size = text.len() + 1;
+        chunk_body.extend_from_slice(id);
+        chunk_body.extend_from_slice(&u32::try_from(size)?.to_le_bytes());
+        chunk_body.extend_from_slice(text.as_bytes());
+        chunk_body.push(0);
+        if size & 1 == 1 {
+            chunk_body.push(0);
+        }
+    }
+    Ok(chunk_body)
+}
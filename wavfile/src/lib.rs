@@ -9,10 +9,374 @@ use std::fs::File;
 
 mod error;
 use error::*;
+pub use error::WavFileError;
+
+mod bytes;
+
+pub use audio_sample::{Sample, Float, I24, AudioBuffer, AudioBufferError};
+
+mod reader;
+pub use reader::{WavReader, ChunkInfo};
+
+mod writer;
+pub use writer::WavWriter;
+
+mod resample;
+pub use resample::{resample, to_channel_vec_at};
+
+mod repair;
+pub use repair::{Repair, repair};
+
+mod bext;
+pub use bext::Bext;
+
+mod info;
+pub use info::{InfoTags, INFO_TITLE, INFO_ARTIST, INFO_COMMENT, INFO_CREATION_DATE, INFO_SOFTWARE, INFO_GENRE, INFO_COPYRIGHT, INFO_PRODUCT};
+
+mod cue;
+pub use cue::{CuePoint, CueRegion};
+
+mod smpl;
+pub use smpl::{Smpl, SampleLoop};
+
+mod frame_view;
+pub use frame_view::FrameView;
+
+mod mapped;
+pub use mapped::MappedWav;
+
+mod aiff;
+pub use aiff::{AiffFile, is_aiff_extension};
+
+mod md5;
+mod flac;
+pub use flac::{FlacFile, FlacStreamInfo};
 
 #[cfg(test)]
 mod tests {
-    use super::WavFile;
+    use super::{WavFile, SubChunk};
+    use std::convert::TryFrom;
+    use super::{Fmt, WavFileError, get_fmt, set_fmt, f64wave_to_bytes, to_channel_vec, to_wav_audio};
+    use super::{to_audio_buffer, to_audio_buffer_as, buffer_to_wav_audio};
+    use super::frame_view::decoder;
+
+    fn bytes_to_f64wave(id: usize, bytes: &[u8]) -> super::Result<f64> {
+        Ok(decoder(&Fmt::new(id, 1, 8000, bytes.len() * 8))?(bytes))
+    }
+
+    // Simple LCG for reproducible pseudo random test data.
+    fn lcg_bytes(seed: &mut u64, len: usize) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(len);
+        for _ in 0..len {
+            *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            bytes.push((*seed >> 56) as u8);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_sample_round_trip() {
+        // Every code of 8bit and 16bit PCM
+        for code in 0..=u8::MAX {
+            let val = bytes_to_f64wave(1, &[code]).unwrap();
+            assert_eq!(f64wave_to_bytes(1, val, 1).unwrap(), vec![code]);
+        }
+        for code in 0..=u16::MAX {
+            let bytes = code.to_le_bytes();
+            let val = bytes_to_f64wave(1, &bytes).unwrap();
+            assert_eq!(f64wave_to_bytes(1, val, 2).unwrap(), bytes.to_vec());
+        }
+        // Extremes and random codes of 24bit, 32bit PCM and 32bit, 64bit float
+        let mut seed = 1;
+        for (id, len) in [(1, 3), (1, 4), (3, 4), (3, 8)].iter() {
+            let mut samples = vec![vec![0x00; *len], vec![0xFF; *len]];
+            let mut max = vec![0xFF; *len];
+            max[*len - 1] = 0x7F;
+            let mut min = vec![0x00; *len];
+            min[*len - 1] = 0x80;
+            samples.push(max);
+            samples.push(min);
+            for _ in 0..10000 {
+                samples.push(lcg_bytes(&mut seed, *len));
+            }
+            for bytes in samples {
+                let val = bytes_to_f64wave(*id, &bytes).unwrap();
+                if val.is_nan() {
+                    continue;
+                }
+                assert_eq!(f64wave_to_bytes(*id, val, *len).unwrap(), bytes);
+            }
+        }
+    }
+
+    #[test]
+    fn test_channel_vec_round_trip() {
+        let mut seed = 2;
+        for (id, bits) in [(1, 8), (1, 16), (1, 24), (1, 32), (3, 32), (3, 64)].iter() {
+            let fmt = Fmt::new(*id, 2, 44100, *bits);
+            let mut data = lcg_bytes(&mut seed, 2 * bits / 8 * 1000);
+            if *id == 3 {
+                // Keep float samples in -1.0..1.0
+                let mut val_vec = Vec::new();
+                for (i, chunk) in data.chunks(bits / 8).enumerate() {
+                    let val = f64::from(chunk[0]) / 256.0 - if i % 2 == 0 { 0.5 } else { 0.25 };
+                    val_vec.append(&mut f64wave_to_bytes(*id, val, bits / 8).unwrap());
+                }
+                data = val_vec;
+            }
+            let wav_audio = super::WavAudio { fmt: fmt.clone(), data: data.clone() };
+            let ch_vec = to_channel_vec(&wav_audio).unwrap();
+            assert_eq!(ch_vec[0].len(), 1000);
+            assert_eq!(to_wav_audio(&ch_vec, &fmt).unwrap().data, data);
+        }
+    }
+
+    #[test]
+    fn test_audio_buffer_round_trip() {
+        let fmt = Fmt::new(1, 2, 8000, 16);
+        let wav_audio = to_wav_audio(&vec![vec![0.5, 0.25, -0.25], vec![-0.5, -0.25, 0.25]], &fmt).unwrap();
+        let buffer = to_audio_buffer(&wav_audio).unwrap();
+        assert_eq!(buffer.sampling_rate(), 8000);
+        assert_eq!(buffer.view(1, 3).channel(1), &[-0.25, 0.25]);
+        assert_eq!(buffer_to_wav_audio(&buffer, &fmt).unwrap().data, wav_audio.data);
+        assert_eq!(buffer_to_wav_audio(&to_audio_buffer_as::<i16>(&wav_audio).unwrap(), &fmt).unwrap().data, wav_audio.data);
+        match buffer_to_wav_audio(&buffer, &Fmt::new(1, 2, 16000, 16)) {
+            Err(WavFileError::FmtMismatch { field, .. }) => assert_eq!(field, "Sampling Rate"),
+            _ => panic!("sampling rate mismatch must be an error"),
+        }
+    }
+
+    #[test]
+    fn test_sampling_rate() {
+        for sampling_rate in [8000, 11025, 22050, 32000, 44100, 48000, 88200, 96000, 192000].iter() {
+            let wav_audio = to_wav_audio(&vec![vec![0.5; 10]], &Fmt::new(1, 1, *sampling_rate, 16)).unwrap();
+            assert_eq!(to_channel_vec(&wav_audio).unwrap()[0].len(), 10);
+        }
+        assert!(to_wav_audio(&vec![vec![0.5; 10]], &Fmt::new(1, 1, 0, 16)).is_err());
+        assert!(to_wav_audio(&vec![vec![0.5; 10]], &Fmt::new(1, 1, 10_000_000, 16)).is_err());
+    }
+
+    #[test]
+    fn test_multichannel() {
+        let fmt = Fmt::new(1, 8, 48000, 24);
+        let mut ch_vec: Vec<Vec<f64>> = Vec::new();
+        for ch_idx in 0..8 {
+            ch_vec.push((0..100).map(|i| f64::from(ch_idx * 100 + i) / 1024.0).collect());
+        }
+        let mut wav_file = WavFile::new();
+        wav_file.update_wav_audio(&to_wav_audio(&ch_vec, &fmt).unwrap()).unwrap();
+        let path = std::env::temp_dir().join("wavfile_multichannel_test.wav");
+        wav_file.save_as(&path).unwrap();
+
+        let mut read_file = WavFile::new();
+        read_file.open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let wav_audio = read_file.get_wav_audio().unwrap();
+        assert_eq!(wav_audio.fmt.channel, 8);
+        assert_eq!(to_channel_vec(&wav_audio).unwrap(), ch_vec);
+    }
+
+    #[test]
+    fn test_extensible_fmt() {
+        // 4ch 24bit PCM, FL FR BL BR
+        let mut fmt_body: Vec<u8> = vec![0xFE,0xFF, 0x04,0x00, 0x80,0xBB,0x00,0x00, 0x00,0xCA,0x08,0x00, 0x0C,0x00, 0x18,0x00,
+            0x16,0x00, 0x14,0x00, 0x33,0x00,0x00,0x00];
+        fmt_body.extend_from_slice(&[0x01,0x00,0x00,0x00, 0x00,0x00, 0x10,0x00, 0x80,0x00,0x00,0xAA,0x00,0x38,0x9B,0x71]);
+        let fmt = get_fmt(&fmt_body).unwrap();
+        assert_eq!((fmt.id, fmt.channel, fmt.sampling_rate, fmt.bits), (1, 4, 48000, 24));
+        assert_eq!((fmt.valid_bits, fmt.channel_mask), (20, 0x33));
+        assert_eq!(set_fmt(&fmt).unwrap(), fmt_body);
+
+        // IEEE float sub format
+        fmt_body[0x18] = 0x03;
+        assert_eq!(get_fmt(&fmt_body).unwrap().id, 3);
+        // Unknown sub format
+        fmt_body[0x18] = 0x02;
+        assert!(get_fmt(&fmt_body).is_err());
+    }
+
+    fn odd_chunk_wav_bytes() -> Vec<u8> {
+        let mut wav_file = WavFile::new();
+        wav_file.update_wav_audio(&to_wav_audio(&vec![vec![0.5, -0.5, 0.25]], &Fmt::new(1, 1, 8000, 8)).unwrap()).unwrap();
+        wav_file.sub_chunks.insert(0, SubChunk { name: [b'L',b'I',b'S',b'T'], body_size: 5, data: vec![b'I',b'N',b'F',b'O',b'x'] });
+        let path = std::env::temp_dir().join("wavfile_odd_chunk_test.wav");
+        wav_file.save_as(&path).unwrap();
+        let buf = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_pad_byte() {
+        let buf = odd_chunk_wav_bytes();
+        // RIFF header + LIST(5+1) + fmt (16) + data(3+1)
+        assert_eq!(buf.len(), 12 + (8 + 6) + (8 + 16) + (8 + 4));
+        let mut wav_file = WavFile::new();
+        wav_file.open_bytes(buf.clone()).unwrap();
+        assert_eq!(wav_file.sub_chunks.len(), 3);
+        assert_eq!(wav_file.sub_chunks[0].body_size, 5);
+        assert_eq!(to_channel_vec(&wav_file.get_wav_audio().unwrap()).unwrap()[0], vec![0.5, -0.5, 0.25]);
+
+        let mut wav_reader = super::WavReader::new(std::io::Cursor::new(buf)).unwrap();
+        assert_eq!(wav_reader.frame_num(), 3);
+        assert_eq!(wav_reader.chunk_infos().unwrap().len(), 3);
+    }
+
+    // Parse and decode through every API. Errors are fine, panics are not.
+    fn parse_all(buf: &[u8]) -> bool {
+        let mut ok = true;
+        let mut wav_file = WavFile::new();
+        match wav_file.open_bytes(buf.to_vec()) {
+            Ok(_) => {
+                match wav_file.get_wav_audio() {
+                    Ok(wav_audio) => { ok &= to_channel_vec(&wav_audio).is_ok(); }
+                    Err(_) => { ok = false; }
+                }
+            }
+            Err(_) => { ok = false; }
+        }
+        match super::WavReader::new(std::io::Cursor::new(buf.to_vec())) {
+            Ok(mut wav_reader) => {
+                ok &= wav_reader.read_frames(usize::MAX).is_ok();
+                ok &= wav_reader.chunk_infos().is_ok();
+            }
+            Err(_) => { ok = false; }
+        }
+        ok
+    }
+
+    #[test]
+    fn test_malformed_corpus() {
+        let base = odd_chunk_wav_bytes();
+        assert!(parse_all(&base));
+
+        // Every truncation
+        for len in 0..base.len() {
+            assert!(!parse_all(&base[..len]), "truncated at {}", len);
+        }
+        // Every single byte replaced by typical broken values
+        for pos in 0..base.len() {
+            for val in [0x00, 0x01, 0x7F, 0x80, 0xFE, 0xFF].iter() {
+                let mut buf = base.clone();
+                buf[pos] = *val;
+                parse_all(&buf);
+            }
+        }
+        // Random mutations and random tails
+        let mut seed = 3;
+        for _ in 0..5000 {
+            let mut buf = base.clone();
+            let rand = lcg_bytes(&mut seed, 8);
+            for i in 0..(rand[0] % 4 + 1) as usize {
+                let pos = rand[1 + i] as usize % buf.len();
+                buf[pos] = rand[5 + i % 3];
+            }
+            if rand[7] & 1 == 1 {
+                let tail_len = rand[6] as usize % 64;
+                buf.append(&mut lcg_bytes(&mut seed, tail_len));
+            }
+            parse_all(&buf);
+        }
+        // Crafted broken files
+        let mut crafted_vec: Vec<Vec<u8>> = Vec::new();
+        let fmt_pos = 12 + 8 + 6;
+        let mut buf = base.clone();
+        buf[fmt_pos + 4..fmt_pos + 8].copy_from_slice(&0xFFFFFFFF_u32.to_le_bytes()); // fmt size
+        crafted_vec.push(buf);
+        let mut buf = base.clone();
+        buf[fmt_pos + 4..fmt_pos + 8].copy_from_slice(&2_u32.to_le_bytes()); // short fmt
+        crafted_vec.push(buf);
+        let mut buf = base.clone();
+        buf[fmt_pos + 8 + 2] = 0; // channel 0
+        crafted_vec.push(buf);
+        let mut buf = base.clone();
+        buf[fmt_pos + 8 + 14] = 4; // 4 bits
+        crafted_vec.push(buf);
+        let mut buf = base.clone();
+        buf[fmt_pos + 8 + 14] = 0; // 0 bits
+        crafted_vec.push(buf);
+        let mut buf = base.clone();
+        buf[12..16].copy_from_slice(b"data"); // second data chunk instead of LIST
+        crafted_vec.push(buf);
+        for buf in crafted_vec {
+            assert!(!parse_all(&buf));
+        }
+    }
+
+    #[test]
+    fn test_rf64() {
+        let mut wav_file = WavFile::new();
+        wav_file.update_sub_chunk(SubChunk { name: *b"LIST", body_size: 61, data: vec![0x55; 61] }).unwrap();
+        let ch_vec = vec![(0..20).map(|i| f64::from(i) / 32.0).collect::<Vec<f64>>()];
+        wav_file.update_wav_audio(&to_wav_audio(&ch_vec, &Fmt::new(1, 1, 8000, 16)).unwrap()).unwrap();
+
+        // Small limit instead of 4GiB: "LIST" goes to the ds64 table.
+        let mut buf = wav_file.to_bytes_with_limit(50).unwrap();
+        assert_eq!(&buf[0x00..0x04], b"RF64");
+        assert_eq!(&buf[0x04..0x08], &[0xFF; 4]);
+        assert_eq!(&buf[0x0c..0x10], b"ds64");
+        assert_eq!(u64::from_le_bytes(<[u8;8]>::try_from(&buf[0x14..0x1c]).unwrap()), buf.len() as u64 - 8);
+        assert_eq!(u64::from_le_bytes(<[u8;8]>::try_from(&buf[0x24..0x2c]).unwrap()), 20);
+
+        for riff_id in [b"RF64", b"BW64"].iter() {
+            buf[0x00..0x04].copy_from_slice(*riff_id);
+            let mut rf64_file = WavFile::new();
+            rf64_file.open_bytes(buf.clone()).unwrap();
+            assert_eq!(rf64_file.sub_chunks.len(), 3);
+            assert_eq!(rf64_file.sub_chunks[0].body_size, 61);
+            assert_eq!(to_channel_vec(&rf64_file.get_wav_audio().unwrap()).unwrap(), ch_vec);
+            // Written back as plain RIFF
+            assert_eq!(rf64_file.to_bytes().unwrap(), wav_file.to_bytes().unwrap());
+
+            let mut wav_reader = super::WavReader::new(std::io::Cursor::new(buf.clone())).unwrap();
+            assert_eq!(wav_reader.read_frames(100).unwrap(), ch_vec);
+            let size_vec: Vec<usize> = wav_reader.chunk_infos().unwrap().iter().map(|info| info.body_size).collect();
+            assert_eq!(size_vec, vec![61, 16, 40]);
+        }
+
+        // "ds64" is required.
+        buf[0x0c..0x10].copy_from_slice(b"JUNK");
+        assert!(WavFile::new().open_bytes(buf.clone()).is_err());
+        assert!(super::WavReader::new(std::io::Cursor::new(buf)).is_err());
+    }
+
+    #[test]
+    fn test_typed_errors() {
+        // ADPCM
+        let fmt_body: Vec<u8> = vec![0x02,0x00, 0x01,0x00, 0x40,0x1F,0x00,0x00, 0x80,0x3E,0x00,0x00, 0x02,0x00, 0x10,0x00];
+        match get_fmt(&fmt_body) {
+            Err(WavFileError::FormatId { format_id }) => assert_eq!(format_id, 2),
+            _ => panic!("FormatId error is expected"),
+        }
+        let mut fmt_body = set_fmt(&Fmt::new(1, 1, 8000, 16)).unwrap();
+        fmt_body[0x0c] = 0x04;
+        match get_fmt(&fmt_body) {
+            Err(WavFileError::FmtMismatch { field, expected, actual }) => assert_eq!((field, expected, actual), ("Block Size", 2, 4)),
+            _ => panic!("FmtMismatch error is expected"),
+        }
+        match to_wav_audio(&vec![vec![0.0; 2], vec![0.0; 3]], &Fmt::new(1, 2, 8000, 16)) {
+            Err(WavFileError::DataLength { expected, actual }) => assert_eq!((expected, actual), (2, 3)),
+            _ => panic!("DataLength error is expected"),
+        }
+        match WavFile::new().open(std::path::Path::new("./not_exist.wav")) {
+            Err(WavFileError::NotFile { .. }) => {},
+            _ => panic!("NotFile error is expected"),
+        }
+    }
+
+    #[test]
+    fn test_extensible_required() {
+        assert_eq!(set_fmt(&Fmt::new(1, 2, 44100, 16)).unwrap().len(), 16);
+        assert_eq!(set_fmt(&Fmt::new(3, 2, 44100, 32)).unwrap().len(), 16);
+        assert_eq!(set_fmt(&Fmt::new(1, 2, 44100, 24)).unwrap().len(), 40);
+        assert_eq!(set_fmt(&Fmt::new(3, 6, 44100, 32)).unwrap().len(), 40);
+        let mut fmt = Fmt::new(1, 1, 44100, 16);
+        fmt.channel_mask = 0x4;
+        let fmt_body = set_fmt(&fmt).unwrap();
+        assert_eq!(fmt_body[0..2], [0xFE,0xFF]);
+        assert_eq!(get_fmt(&fmt_body).unwrap().channel_mask, 0x4);
+    }
+
     #[test]
     fn test_get_wav_file() {
         let mut wav_file = WavFile::new();
@@ -54,13 +418,42 @@ mod tests {
 }
 
 
+pub const WAVE_FORMAT_PCM: usize = 0x0001;
+pub const WAVE_FORMAT_IEEE_FLOAT: usize = 0x0003;
+pub const WAVE_FORMAT_EXTENSIBLE: usize = 0xFFFE;
+
+pub const MIN_SAMPLING_RATE: usize = 1000;
+pub const MAX_SAMPLING_RATE: usize = 768000;
+
+// KSDATAFORMAT_SUBTYPE_XXX is {0000XXXX-0000-0010-8000-00AA00389B71}. (XXXX: format id)
+const SUBTYPE_GUID_TAIL: [u8;14] = [0x00,0x00, 0x00,0x00, 0x10,0x00, 0x80,0x00,0x00,0xAA,0x00,0x38,0x9B,0x71];
+
+// "id" is always WAVE_FORMAT_PCM or WAVE_FORMAT_IEEE_FLOAT.
+// WAVE_FORMAT_EXTENSIBLE is mapped to them by the sub-format GUID.
 #[allow(dead_code)]
 #[derive(Clone)]
 pub struct Fmt {
     pub id: usize,
     pub channel: usize,
     pub sampling_rate: usize,
-    pub bits: usize
+    pub bits: usize,
+    pub valid_bits: usize,
+    pub channel_mask: usize   // 0: Not specified
+}
+
+impl Fmt {
+    pub fn new(id: usize, channel: usize, sampling_rate: usize, bits: usize) -> Fmt {
+        Fmt {id, channel, sampling_rate, bits, valid_bits:bits, channel_mask:0}
+    }
+
+    // WAVE_FORMAT_EXTENSIBLE is required for more than 2 channels, PCM with more than 16 bits,
+    // valid bits different from container bits, or channel-to-speaker mapping.
+    pub fn is_extensible(&self) -> bool {
+        self.channel > 2
+            || (self.id == WAVE_FORMAT_PCM && self.bits > 16)
+            || self.valid_bits != self.bits
+            || self.channel_mask != 0
+    }
 }
 
 #[allow(dead_code)]
@@ -96,45 +489,74 @@ impl WavFile {
     }
 
     pub fn open(&mut self, file_path: &Path) -> Result<()> {
-        // -- Check Parameter --
-        if !file_path.is_file() {
-            return Err(WavFileError::new(&format!("Path is Not File! Path:{}", file_path.display())));
-        }
-        if let Some(ext) = file_path.extension() {
-            if (ext != "wav") && (ext != "WAV") {
-                return Err(WavFileError::new(&format!("Path Extension is Not .wav!! File:{}", file_path.display())));
-            }
-        }
-        else {
-            return Err(WavFileError::new(&format!("Path is Not File! Path:{}", file_path.display())));
+        let buf = read_wav_file(file_path)?;
+        self.open_bytes(buf)?;
+        self.file_path = file_path.to_path_buf();
+        Ok(())
+    }
+
+    // Same as open() for a whole file image already in memory.
+    pub fn open_bytes(&mut self, buf: Vec<u8>) -> Result<()> {
+        self.parse_bytes(buf, false)?;
+        Ok(())
+    }
+
+    // Open a file whose RIFF or chunk sizes are wrong (streamed or crashed recordings).
+    // The actual file length is trusted and the "data" chunk is clamped to the available whole frames.
+    // Returns what was repaired. save() writes the file with correct sizes.
+    pub fn open_lenient(&mut self, file_path: &Path) -> Result<Vec<Repair>> {
+        let buf = read_wav_file(file_path)?;
+        let repair_vec = self.open_bytes_lenient(buf)?;
+        self.file_path = file_path.to_path_buf();
+        Ok(repair_vec)
+    }
+
+    pub fn open_bytes_lenient(&mut self, buf: Vec<u8>) -> Result<Vec<Repair>> {
+        self.parse_bytes(buf, true)
+    }
+
+    fn parse_bytes(&mut self, buf: Vec<u8>, lenient: bool) -> Result<Vec<Repair>> {
+        let mut repair_vec: Vec<Repair> = Vec::new();
+        let file_size = buf.len();
+        if file_size < 12 {
+            return Err(WavFileError::TruncatedHeader { expected: 12, actual: file_size });
         }
 
-        // -- Read Whole File --
-        let target_file = File::open(file_path)?;
-        let mut buf = Vec::new();
-        let file_size = BufReader::new(&target_file).read_to_end(&mut buf)?;
-        
         // -- Get WavFile Construction --
-        // "RIFF"
-        if buf[0x00..0x04] != [b'R',b'I',b'F',b'F'] {
-            return Err(WavFileError::new(&format!("Not compatible wav format! \"RIFF\" File:{}", file_path.display())));
+        // "RIFF" or "RF64"/"BW64"
+        let riff_id = <[u8;4]>::try_from(&buf[0x00..0x04])?;
+        if riff_id != [b'R',b'I',b'F',b'F'] && !is_rf64_id(&riff_id) {
+            return Err(WavFileError::NotRiff { id: riff_id });
         }
+        // RF64 sizes are in "ds64" chunk just after "WAVE".
+        let op_ds64 = if is_rf64_id(&riff_id) { Some(get_ds64(&buf[0x0c..])?) } else { None };
+        let mut chunks_addr: usize = 0x0c;
         // RIFF Size
-        let riff_size = usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&buf[0x04..0x08])?))?;
-        if riff_size != file_size - 8 {
-            return Err(WavFileError::new(&format!("Not compatible wav format! RIFF Size File:{}", file_path.display())));
+        let mut riff_size = usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&buf[0x04..0x08])?))?;
+        if let Some(ds64) = &op_ds64 {
+            riff_size = usize::try_from(ds64.riff_size)?;
+            chunks_addr += 8 + ds64.chunk_size;
+        }
+        let riff_size_repaired = riff_size != file_size - 8;
+        if riff_size_repaired {
+            if !lenient {
+                return Err(WavFileError::RiffSize { expected: file_size - 8, actual: riff_size });
+            }
+            repair_vec.push(Repair::RiffSize { header: riff_size, actual: file_size - 8 });
         }
         // "WAVE"
         if buf[0x08..0x0c] != [b'W',b'A',b'V',b'E'] {
-            return Err(WavFileError::new(&format!("Not compatible wav format! \"WAVE\" File:{}", file_path.display())));
+            return Err(WavFileError::NotWave { id: <[u8;4]>::try_from(&buf[0x08..0x0c])? });
+        }
+
+        let mut sub_chunks_vec = self.get_sub_chunks(&buf[chunks_addr..], chunks_addr, &op_ds64, lenient, riff_size_repaired, &mut repair_vec)?;
+        if lenient {
+            trim_partial_frame(&mut sub_chunks_vec, chunks_addr, &mut repair_vec);
         }
-        
-        let sub_chunks_vec = self.get_sub_chunks(buf[0x0c..].to_vec(), file_size - 12)?;
 
-        self.file_path = file_path.to_path_buf();
         self.size = file_size;
         self.sub_chunks = sub_chunks_vec;
-        Ok(())
+        Ok(repair_vec)
     }
 
     pub fn save(&mut self) -> Result<()> {
@@ -147,42 +569,133 @@ impl WavFile {
         // -- Check Parameter --
         if let Some(ext) = file_path.extension() {
             if (ext != "wav") && (ext != "WAV") {
-                return Err(WavFileError::new(&format!("Path Extension is Not .wav!! File:{}", file_path.display())));
+                return Err(WavFileError::Extension { path: file_path.to_path_buf() });
             }
         }
         else {
-            return Err(WavFileError::new(&format!("Path is Not File! Path:{}", file_path.display())));
-        }
-        let mut buf: Vec<u8> = Vec::new();
-        buf.append(&mut [b'R',b'I',b'F',b'F'].to_vec());
-        let mut riff_size:usize = 4;
-        for sub_chunk in &self.sub_chunks {
-            riff_size += sub_chunk.body_size + 8;
-        }
-        buf.append(&mut riff_size.to_le_bytes()[0..4].to_vec());
-        buf.append(&mut [b'W',b'A',b'V',b'E'].to_vec());
-        for sub_chunk in &mut self.sub_chunks {
-            buf.append(&mut sub_chunk.name.to_vec());
-            buf.append(&mut sub_chunk.body_size.to_le_bytes()[0..4].to_vec());
-            buf.append(&mut sub_chunk.data.to_vec());
+            return Err(WavFileError::NotFile { path: file_path.to_path_buf() });
         }
+        let buf = self.to_bytes()?;
         let mut target_file = File::create(file_path)?;
         target_file.write_all(&buf)?;
 
         // Update Self Infomation
         self.file_path = file_path.to_path_buf();
-        self.size = riff_size + 8;
+        self.size = buf.len();
         Ok(())
     }
 
-    fn get_sub_chunks(&self, buf: Vec<u8>, chunks_size: usize) -> Result<Vec<SubChunk>> {
+    // Whole file image as written by save_as().
+    // It is written as RF64 when a size does not fit in 4 bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        self.to_bytes_with_limit(RIFF_SIZE_LIMIT)
+    }
+
+    fn to_bytes_with_limit(&self, size_limit: usize) -> Result<Vec<u8>> {
+        let mut riff_size:usize = 4;
+        for sub_chunk in &self.sub_chunks {
+            if sub_chunk.body_size != sub_chunk.data.len() {
+                return Err(WavFileError::DataLength { expected: sub_chunk.body_size, actual: sub_chunk.data.len() });
+            }
+            // Odd size chunk is followed by a pad byte.
+            riff_size += sub_chunk.body_size + (sub_chunk.body_size & 1) + 8;
+        }
+
+        let mut op_ds64: Option<Ds64> = None;
+        if riff_size > size_limit || self.sub_chunks.iter().any(|sub_chunk| sub_chunk.body_size > size_limit) {
+            let mut ds64 = Ds64 { riff_size: 0, data_size: 0, sample_count: 0, table: Vec::new(), chunk_size: 0 };
+            let mut block_size: usize = 0;
+            for sub_chunk in &self.sub_chunks {
+                match sub_chunk.name {
+                    [b'f',b'm',b't',b' '] => {
+                        if let Ok(fmt) = get_fmt(&sub_chunk.data) {
+                            block_size = fmt.channel * fmt.bits / 8;
+                        }
+                    }
+                    [b'd',b'a',b't',b'a'] => {
+                        ds64.data_size = u64::try_from(sub_chunk.body_size)?;
+                    }
+                    _ => {
+                        if sub_chunk.body_size > size_limit {
+                            ds64.table.push((sub_chunk.name, u64::try_from(sub_chunk.body_size)?));
+                        }
+                    }
+                }
+            }
+            if block_size > 0 {
+                ds64.sample_count = ds64.data_size / u64::try_from(block_size)?;
+            }
+            ds64.chunk_size = DS64_MIN_SIZE + ds64.table.len() * 12;
+            riff_size += 8 + ds64.chunk_size;
+            ds64.riff_size = u64::try_from(riff_size)?;
+            op_ds64 = Some(ds64);
+        }
+
+        let mut buf: Vec<u8> = Vec::with_capacity(riff_size + 8);
+        if let Some(ds64) = &op_ds64 {
+            buf.extend_from_slice(b"RF64");
+            buf.extend_from_slice(&u32::MAX.to_le_bytes());
+            buf.extend_from_slice(b"WAVE");
+            buf.extend_from_slice(b"ds64");
+            buf.extend_from_slice(&u32::try_from(ds64.chunk_size)?.to_le_bytes());
+            buf.append(&mut set_ds64(ds64)?);
+        }
+        else {
+            buf.extend_from_slice(b"RIFF");
+            buf.extend_from_slice(&u32::try_from(riff_size)?.to_le_bytes());
+            buf.extend_from_slice(b"WAVE");
+        }
+        for sub_chunk in &self.sub_chunks {
+            buf.extend_from_slice(&sub_chunk.name);
+            if op_ds64.is_some() && (sub_chunk.body_size > size_limit || sub_chunk.name == [b'd',b'a',b't',b'a']) {
+                buf.extend_from_slice(&u32::MAX.to_le_bytes());
+            }
+            else {
+                buf.extend_from_slice(&u32::try_from(sub_chunk.body_size)?.to_le_bytes());
+            }
+            buf.extend_from_slice(&sub_chunk.data);
+            if sub_chunk.body_size & 1 == 1 {
+                buf.push(0);
+            }
+        }
+        Ok(buf)
+    }
+
+    // "lenient": Clamp the truncated chunk and the streamed "data" chunk to the end of file.
+    // "chunks_addr": Position of "buf" in the file.
+    // "op_ds64": RF64 sizes for the chunks whose size is 0xFFFFFFFF.
+    // "riff_size_repaired": RIFF size was wrong, so "data" size 0 is taken as streamed.
+    fn get_sub_chunks(&self, buf: &[u8], chunks_addr: usize, op_ds64: &Option<Ds64>, lenient: bool, riff_size_repaired: bool, repair_vec: &mut Vec<Repair>) -> Result<Vec<SubChunk>> {
         let mut sub_chunks_vec:Vec<SubChunk> = Vec::new(); 
         let mut chunk_head_addr: usize = 0x00;
-        while chunks_size - chunk_head_addr >= 8 {
+        while chunk_head_addr + 8 <= buf.len() {
             let chunk_head_buf = &buf[chunk_head_addr..];
-            let chunk_body_size = usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&chunk_head_buf[0x04..0x08])?))?;
-            if chunk_head_buf.len() < chunk_body_size + 8 {
-                return Err(WavFileError::new("Chunk Size is wrong!"));
+            let id = <[u8;4]>::try_from(&chunk_head_buf[0x00..0x04])?;
+            let header_size = match op_ds64 {
+                Some(ds64) => ds64.chunk_body_size(id, u32::from_le_bytes(<[u8;4]>::try_from(&chunk_head_buf[0x04..0x08])?))?,
+                None => usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&chunk_head_buf[0x04..0x08])?))?,
+            };
+            let available_size = chunk_head_buf.len() - 8;
+            let mut chunk_body_size = header_size;
+            // Streaming tools write 0xFFFFFFFF (or leave 0) as "data" size.
+            let streamed_data = id == [b'd',b'a',b't',b'a'] && op_ds64.is_none()
+                && (header_size == 0xFFFFFFFF || (header_size == 0 && riff_size_repaired));
+            if lenient && streamed_data {
+                chunk_body_size = available_size;
+            }
+            if available_size < chunk_body_size {
+                if !lenient {
+                    return Err(WavFileError::TruncatedChunk {
+                        id,
+                        offset: chunks_addr + chunk_head_addr,
+                        expected: chunk_body_size,
+                        actual: available_size
+                    });
+                }
+                chunk_body_size = available_size;
+            }
+            if chunk_body_size != header_size {
+                repair_vec.push(Repair::ChunkSize { id, offset: chunks_addr + chunk_head_addr, header: header_size, actual: chunk_body_size });
             }
             let sub_chunk = SubChunk {
                 name: [ chunk_head_buf[0x00],
@@ -193,7 +706,8 @@ impl WavFile {
                 data: chunk_head_buf[8..(chunk_body_size + 8)].to_vec()
             };
             sub_chunks_vec.push(sub_chunk);
-            chunk_head_addr += 8 + chunk_body_size;
+            // Odd size chunk is followed by a pad byte. (It may be missing at the end of file.)
+            chunk_head_addr += 8 + chunk_body_size + (chunk_body_size & 1);
         }
         return Ok(sub_chunks_vec);
     }
@@ -216,6 +730,39 @@ impl WavFile {
     }
 
 
+    // update_sub_chunk(), but a new chunk is added just before "before_name" chunk. (e.g. "bext" before "data")
+    fn update_sub_chunk_before(&mut self, new_chunk: SubChunk, before_name: [u8;4]) -> Result<()> {
+        let op_chunk_idx = self.sub_chunks.iter().position(|existing_chunk| existing_chunk.name == new_chunk.name);
+        let op_before_idx = self.sub_chunks.iter().position(|existing_chunk| existing_chunk.name == before_name);
+        match (op_chunk_idx, op_before_idx) {
+            (Some(idx), _) => { self.sub_chunks[idx] = new_chunk; }
+            (None, Some(before_idx)) => { self.sub_chunks.insert(before_idx, new_chunk); }
+            (None, None) => { self.sub_chunks.push(new_chunk); }
+        }
+        Ok(())
+    }
+
+    fn find_sub_chunk(&self, name: [u8;4]) -> Option<&SubChunk> {
+        self.sub_chunks.iter().find(|sub_chunk| sub_chunk.name == name)
+    }
+
+    // Index of "LIST" chunk of "list_type" (e.g. "INFO", "adtl").
+    fn list_chunk_idx(&self, list_type: [u8;4]) -> Option<usize> {
+        self.sub_chunks.iter().position(|sub_chunk| {
+            sub_chunk.name == [b'L',b'I',b'S',b'T'] && sub_chunk.data.len() >= 4 && sub_chunk.data[0..4] == list_type
+        })
+    }
+
+    // Replace, add (no "op_idx") or remove (no "op_sub_chunk") a sub chunk.
+    fn replace_sub_chunk(&mut self, op_idx: Option<usize>, op_sub_chunk: Option<SubChunk>) {
+        match (op_idx, op_sub_chunk) {
+            (Some(idx), Some(sub_chunk)) => { self.sub_chunks[idx] = sub_chunk; }
+            (Some(idx), None) => { self.sub_chunks.remove(idx); }
+            (None, Some(sub_chunk)) => { self.sub_chunks.push(sub_chunk); }
+            (None, None) => {}
+        }
+    }
+
     pub fn get_wav_audio(&self) -> Result<WavAudio> {
         let mut op_fmt: Option<Fmt> = None; 
         let mut op_data: Option<Vec<u8>> = None;
@@ -223,10 +770,10 @@ impl WavFile {
             match sub_chunk.name {
                 [b'f',b'm',b't',b' '] => {
                     if op_fmt.is_none() {
-                        op_fmt = Some(self.get_fmt(&sub_chunk.data)?);
+                        op_fmt = Some(get_fmt(&sub_chunk.data)?);
                     }
                     else {
-                        return Err(WavFileError::new("There are two or more fmt chunks!"));
+                        return Err(WavFileError::DuplicateChunk { id: [b'f',b'm',b't',b' '] });
                     }
                 }
                 [b'd',b'a',b't',b'a'] => {
@@ -234,17 +781,17 @@ impl WavFile {
                         op_data = Some(sub_chunk.data.clone());
                     }
                     else {
-                        return Err(WavFileError::new("There are two or more data chunks!"));
+                        return Err(WavFileError::DuplicateChunk { id: [b'd',b'a',b't',b'a'] });
                     }
                 }
                 _ => {}
             }
         }
         if op_fmt.is_none() {
-            return Err(WavFileError::new("There are no fmt chunks!"));
+            return Err(WavFileError::MissingChunk { id: [b'f',b'm',b't',b' '] });
         }
         if op_data.is_none() {
-            return Err(WavFileError::new("There are no data chunks!"));
+            return Err(WavFileError::MissingChunk { id: [b'd',b'a',b't',b'a'] });
         }
         Ok(WavAudio {
             fmt: op_fmt.unwrap(),
@@ -252,8 +799,17 @@ impl WavFile {
         })
     }
 
+    // Borrowed view of "data" chunk without copying it as get_wav_audio() does.
+    pub fn frame_view(&self) -> Result<FrameView<'_>> {
+        let fmt_chunk = self.find_sub_chunk([b'f',b'm',b't',b' '])
+            .ok_or(WavFileError::MissingChunk { id: [b'f',b'm',b't',b' '] })?;
+        let data_chunk = self.find_sub_chunk([b'd',b'a',b't',b'a'])
+            .ok_or(WavFileError::MissingChunk { id: [b'd',b'a',b't',b'a'] })?;
+        FrameView::new(&data_chunk.data, &get_fmt(&fmt_chunk.data)?)
+    }
+
     pub fn update_wav_audio(&mut self, ref_wav_audio: &WavAudio) -> Result<()> {
-        let fmt_buf: Vec<u8> = self.set_fmt(&ref_wav_audio.fmt)?;
+        let fmt_buf: Vec<u8> = set_fmt(&ref_wav_audio.fmt)?;
         let data_buf: Vec<u8> = ref_wav_audio.data.clone();
         let mut op_fmt_chunk_idx: Option<usize> = None;
         let mut op_data_chunk_idx: Option<usize> = None;
@@ -264,7 +820,7 @@ impl WavFile {
                         op_fmt_chunk_idx = Some(i);
                     }
                     else {
-                        return Err(WavFileError::new("There are two or more fmt chunks!"));
+                        return Err(WavFileError::DuplicateChunk { id: [b'f',b'm',b't',b' '] });
                     }
                 }
                 [b'd',b'a',b't',b'a'] => {
@@ -272,7 +828,7 @@ impl WavFile {
                         op_data_chunk_idx = Some(i);
                     }
                     else {
-                        return Err(WavFileError::new("There are two or more data chunks!"));
+                        return Err(WavFileError::DuplicateChunk { id: [b'd',b'a',b't',b'a'] });
                     }
                 }
                 _ => {}
@@ -305,110 +861,254 @@ impl WavFile {
         }
         Ok(())
     }
-    
-    fn get_fmt(&self, ref_chunk_body: &Vec<u8>) -> Result<Fmt> {
-        // fmt chunk
-        // format id
-        let format_id = usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[0x00..0x02])?));
-        if format_id != 1 && format_id != 3 {
-            return Err(WavFileError::new("Not compatible wav format!  fmt Chunk Size"));
-        }
-        // channel
-        let channel = usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[0x02..0x04])?));
-        // Sampling Rate
-        let sampling_rate = usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&ref_chunk_body[0x04..0x08])?))?;
-        // Byte Per Sec
-        let bytes_per_sec = usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&ref_chunk_body[0x08..0x0c])?))?;
-        // Block Size
-        let block_size = usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[0x0c..0x0e])?));
-        // Bit Rate
-        let bits = usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[0x0e..0x10])?));
-
-        // Check Byte Per Sec.
-        if bytes_per_sec != channel * sampling_rate * (bits / 8)  {
-            return Err(WavFileError::new("Not match parameter! Byte Per Sec"));
-        }
-        // Check Block Size.
-        if block_size != channel * bits / 8 {
-            return Err(WavFileError::new("Not match parameter! Block Size"));
-        }
-        Ok(Fmt {
-            id:format_id,
-            channel:channel,
-            sampling_rate:sampling_rate,
-            bits:bits
-        })
+}
+
+fn read_wav_file(file_path: &Path) -> Result<Vec<u8>> {
+    // -- Check Parameter --
+    if !file_path.is_file() {
+        return Err(WavFileError::NotFile { path: file_path.to_path_buf() });
     }
+    if let Some(ext) = file_path.extension() {
+        if (ext != "wav") && (ext != "WAV") {
+            return Err(WavFileError::Extension { path: file_path.to_path_buf() });
+        }
+    }
+    else {
+        return Err(WavFileError::NotFile { path: file_path.to_path_buf() });
+    }
+
+    // -- Read Whole File --
+    let target_file = File::open(file_path)?;
+    let mut buf = Vec::new();
+    BufReader::new(&target_file).read_to_end(&mut buf)?;
+    Ok(buf)
+}
 
-    fn set_fmt(&self, ref_fmt:&Fmt) -> Result<Vec<u8>> {
-        // fmt chunk
-        
-        let mut chunk_body: Vec<u8> = Vec::new();
-        
-        // format id
-        chunk_body.append(&mut ref_fmt.id.to_le_bytes()[0..2].to_vec());
-        // channel
-        chunk_body.append(&mut ref_fmt.channel.to_le_bytes()[0..2].to_vec());
-        // Sampling Rate
-        chunk_body.append(&mut ref_fmt.sampling_rate.to_le_bytes()[0..4].to_vec());
-        // Byte Per Sec
-        chunk_body.append(&mut (ref_fmt.channel * ref_fmt.sampling_rate * (ref_fmt.bits / 8)).to_le_bytes()[0..4].to_vec());
-        // Block Size
-        chunk_body.append(&mut (ref_fmt.channel * ref_fmt.bits / 8).to_le_bytes()[0..2].to_vec());
-        // Bit Rate
-        chunk_body.append(&mut ref_fmt.bits.to_le_bytes()[0..2].to_vec());
-        Ok(chunk_body)
+// Drop the partial frame at the end of "data" chunk (e.g. cut off by a crash).
+fn trim_partial_frame(sub_chunks: &mut [SubChunk], chunks_addr: usize, repair_vec: &mut Vec<Repair>) {
+    let op_fmt = sub_chunks.iter()
+        .find(|sub_chunk| sub_chunk.name == [b'f',b'm',b't',b' '])
+        .and_then(|sub_chunk| get_fmt(&sub_chunk.data).ok());
+    let block_size = match op_fmt {
+        Some(fmt) => fmt.channel * fmt.bits / 8,
+        None => return,
+    };
+    if block_size == 0 {
+        return;
+    }
+    let mut chunk_offset = chunks_addr;
+    for sub_chunk in sub_chunks.iter_mut() {
+        let header_size = sub_chunk.body_size;
+        let next_offset = chunk_offset + 8 + header_size + (header_size & 1);
+        if sub_chunk.name == [b'd',b'a',b't',b'a'] && !header_size.is_multiple_of(block_size) {
+            sub_chunk.body_size -= header_size % block_size;
+            sub_chunk.data.truncate(sub_chunk.body_size);
+            // Merge into the repair of the clamped size.
+            let op_repair = repair_vec.iter_mut().find(|repair| {
+                matches!(repair, Repair::ChunkSize { offset, .. } if *offset == chunk_offset)
+            });
+            match op_repair {
+                Some(Repair::ChunkSize { actual, .. }) => *actual = sub_chunk.body_size,
+                _ => repair_vec.push(Repair::ChunkSize { id: sub_chunk.name, offset: chunk_offset, header: header_size, actual: sub_chunk.body_size }),
+            }
+        }
+        chunk_offset = next_offset;
     }
 }
 
-fn fmt_check(ref_fmt: &Fmt) -> Result<()> {
-    if ref_fmt.channel < 1 || ref_fmt.channel > 2 {
-        return Err(WavFileError::new("Irregal Format! Not Supported Channel."));
+fn is_rf64_id(id: &[u8;4]) -> bool {
+    *id == [b'R',b'F',b'6',b'4'] || *id == [b'B',b'W',b'6',b'4']
+}
+
+// "ds64" chunk of RF64 / BW64 (EBU Tech 3306, ITU-R BS.2088)
+struct Ds64 {
+    riff_size: u64,
+    data_size: u64,
+    sample_count: u64,
+    table: Vec<([u8;4], u64)>,   // 64bit sizes of the chunks other than "data"
+    chunk_size: usize,
+}
+
+impl Ds64 {
+    // Actual body size of a chunk whose 32bit size is "size".
+    fn chunk_body_size(&self, id: [u8;4], size: u32) -> Result<usize> {
+        if size != u32::MAX {
+            return Ok(usize::try_from(size)?);
+        }
+        if id == [b'd',b'a',b't',b'a'] {
+            return Ok(usize::try_from(self.data_size)?);
+        }
+        match self.table.iter().find(|(table_id, _)| *table_id == id) {
+            Some((_, table_size)) => Ok(usize::try_from(*table_size)?),
+            None => Ok(usize::try_from(size)?),
+        }
     }
-    if ref_fmt.bits == 0 || ref_fmt.bits > 64 {
-        return Err(WavFileError::new("Irregal Format! Not Supported Bit Rate."));
+}
+
+const DS64_MIN_SIZE: usize = 28;
+// Largest RIFF or chunk size written without "ds64".
+const RIFF_SIZE_LIMIT: usize = u32::MAX as usize;
+
+// "buf": From the "ds64" chunk header.
+fn get_ds64(buf: &[u8]) -> Result<Ds64> {
+    if buf.len() < 8 || buf[0x00..0x04] != [b'd',b's',b'6',b'4'] {
+        return Err(WavFileError::MissingChunk { id: [b'd',b's',b'6',b'4'] });
     }
-    match ref_fmt.sampling_rate {
-        8000  => {},
-        16000 => {},
-        44100 => {},
-        48000 => {},
-        _ => {return Err(WavFileError::new("Irregal Format! Not Supported Sampling Rate."));}
+    let chunk_size = usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&buf[0x04..0x08])?))?;
+    if chunk_size < DS64_MIN_SIZE || buf.len() < 8 + chunk_size {
+        return Err(WavFileError::TruncatedChunk {
+            id: [b'd',b's',b'6',b'4'],
+            offset: 0x0c,
+            expected: chunk_size.max(DS64_MIN_SIZE),
+            actual: buf.len() - 8
+        });
     }
-    Ok(())
+    let body = &buf[8..(8 + chunk_size)];
+    let mut ds64 = Ds64 {
+        riff_size: u64::from_le_bytes(<[u8;8]>::try_from(&body[0x00..0x08])?),
+        data_size: u64::from_le_bytes(<[u8;8]>::try_from(&body[0x08..0x10])?),
+        sample_count: u64::from_le_bytes(<[u8;8]>::try_from(&body[0x10..0x18])?),
+        table: Vec::new(),
+        chunk_size,
+    };
+    let table_length = usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&body[0x18..0x1c])?))?;
+    for entry in body[DS64_MIN_SIZE..].chunks_exact(12).take(table_length) {
+        ds64.table.push((<[u8;4]>::try_from(&entry[0x00..0x04])?, u64::from_le_bytes(<[u8;8]>::try_from(&entry[0x04..0x0c])?)));
+    }
+    Ok(ds64)
 }
 
-fn bytes_to_f64wave(id:usize, bytes: &[u8]) -> Result<f64> {
-    let len = bytes.len();
-    let mut buffer:[u8; 4] = [0; 4];
-    match id {
-        1 => {
-            if len == 1 {   //unsigned 8bit
-                buffer[3] = bytes[0] - 128;
-                Ok(f64::from(i32::from_le_bytes(buffer))/(f64::from(i32::MAX)+1.0))
-            }
-            else if len == 2 || len == 3 { //signed 16bit,24bit
-                for i in 0..len {
-                    buffer[4-len+i] = bytes[i];
-                }
-                Ok(f64::from(i32::from_le_bytes(buffer))/(f64::from(i32::MAX)+1.0))
-            }
-            else {
-                Err(WavFileError::new("Size is Too Large!"))
-            }
-        },
-        3 => {
-            if len == 4 {   //32bit float
-                Ok(f64::from(f32::from_le_bytes(<[u8; 4]>::try_from(bytes)?)))
-            }
-            else {
-                Err(WavFileError::new("Size is Too Small or Large!"))
-            }
-        },
-        _ => {
-            Err(WavFileError::new("Format is Irregal!"))
+fn set_ds64(ref_ds64: &Ds64) -> Result<Vec<u8>> {
+    let mut chunk_body: Vec<u8> = Vec::new();
+    chunk_body.extend_from_slice(&ref_ds64.riff_size.to_le_bytes());
+    chunk_body.extend_from_slice(&ref_ds64.data_size.to_le_bytes());
+    chunk_body.extend_from_slice(&ref_ds64.sample_count.to_le_bytes());
+    chunk_body.extend_from_slice(&u32::try_from(ref_ds64.table.len())?.to_le_bytes());
+    for (id, size) in &ref_ds64.table {
+        chunk_body.extend_from_slice(id);
+        chunk_body.extend_from_slice(&size.to_le_bytes());
+    }
+    Ok(chunk_body)
+}
+
+fn get_fmt(ref_chunk_body: &[u8]) -> Result<Fmt> {
+    // fmt chunk
+    if ref_chunk_body.len() < 0x10 {
+        return Err(WavFileError::FmtSize { expected: 0x10, actual: ref_chunk_body.len() });
+    }
+    // format id
+    let mut format_id = usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[0x00..0x02])?));
+    if format_id != WAVE_FORMAT_PCM && format_id != WAVE_FORMAT_IEEE_FLOAT && format_id != WAVE_FORMAT_EXTENSIBLE {
+        return Err(WavFileError::FormatId { format_id });
+    }
+    // channel
+    let channel = usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[0x02..0x04])?));
+    // Sampling Rate
+    let sampling_rate = usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&ref_chunk_body[0x04..0x08])?))?;
+    // Byte Per Sec
+    let bytes_per_sec = usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&ref_chunk_body[0x08..0x0c])?))?;
+    // Block Size
+    let block_size = usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[0x0c..0x0e])?));
+    // Bit Rate
+    let bits = usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[0x0e..0x10])?));
+
+    let mut valid_bits = bits;
+    let mut channel_mask = 0;
+    if format_id == WAVE_FORMAT_EXTENSIBLE {
+        if ref_chunk_body.len() < 0x28 {
+            return Err(WavFileError::FmtSize { expected: 0x28, actual: ref_chunk_body.len() });
+        }
+        // Extension Size
+        let cb_size = usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[0x10..0x12])?));
+        if cb_size < 22 {
+            return Err(WavFileError::FmtMismatch { field: "Extension Size", expected: 22, actual: cb_size });
+        }
+        // Valid Bits Per Sample
+        valid_bits = usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[0x12..0x14])?));
+        // Channel Mask
+        channel_mask = usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&ref_chunk_body[0x14..0x18])?))?;
+        // Sub Format GUID
+        if ref_chunk_body[0x1a..0x28] != SUBTYPE_GUID_TAIL {
+            return Err(WavFileError::SubFormatGuid { guid: <[u8;16]>::try_from(&ref_chunk_body[0x18..0x28])? });
+        }
+        format_id = usize::from(u16::from_le_bytes(<[u8;2]>::try_from(&ref_chunk_body[0x18..0x1a])?));
+        if format_id != WAVE_FORMAT_PCM && format_id != WAVE_FORMAT_IEEE_FLOAT {
+            return Err(WavFileError::FormatId { format_id });
         }
+        if valid_bits == 0 || valid_bits > bits {
+            return Err(WavFileError::ValidBits { valid_bits, bits });
+        }
+    }
+
+    // Check Byte Per Sec.
+    if bytes_per_sec != channel * sampling_rate * (bits / 8)  {
+        return Err(WavFileError::FmtMismatch { field: "Byte Per Sec", expected: channel * sampling_rate * (bits / 8), actual: bytes_per_sec });
+    }
+    // Check Block Size.
+    if block_size != channel * bits / 8 {
+        return Err(WavFileError::FmtMismatch { field: "Block Size", expected: channel * bits / 8, actual: block_size });
     }
+    Ok(Fmt {
+        id:format_id,
+        channel:channel,
+        sampling_rate:sampling_rate,
+        bits:bits,
+        valid_bits,
+        channel_mask
+    })
+}
+
+fn set_fmt(ref_fmt:&Fmt) -> Result<Vec<u8>> {
+    // fmt chunk
+    
+    let mut chunk_body: Vec<u8> = Vec::new();
+    
+    // format id
+    if ref_fmt.is_extensible() {
+        chunk_body.extend_from_slice(&u16::try_from(WAVE_FORMAT_EXTENSIBLE)?.to_le_bytes());
+    }
+    else {
+        chunk_body.extend_from_slice(&u16::try_from(ref_fmt.id)?.to_le_bytes());
+    }
+    // channel
+    chunk_body.extend_from_slice(&u16::try_from(ref_fmt.channel)?.to_le_bytes());
+    // Sampling Rate
+    chunk_body.extend_from_slice(&u32::try_from(ref_fmt.sampling_rate)?.to_le_bytes());
+    // Byte Per Sec
+    chunk_body.extend_from_slice(&u32::try_from(ref_fmt.channel * ref_fmt.sampling_rate * (ref_fmt.bits / 8))?.to_le_bytes());
+    // Block Size
+    chunk_body.extend_from_slice(&u16::try_from(ref_fmt.channel * ref_fmt.bits / 8)?.to_le_bytes());
+    // Bit Rate
+    chunk_body.extend_from_slice(&u16::try_from(ref_fmt.bits)?.to_le_bytes());
+
+    if ref_fmt.is_extensible() {
+        // Extension Size
+        chunk_body.extend_from_slice(&22_u16.to_le_bytes());
+        // Valid Bits Per Sample
+        chunk_body.extend_from_slice(&u16::try_from(ref_fmt.valid_bits)?.to_le_bytes());
+        // Channel Mask
+        chunk_body.extend_from_slice(&u32::try_from(ref_fmt.channel_mask)?.to_le_bytes());
+        // Sub Format GUID
+        chunk_body.extend_from_slice(&u16::try_from(ref_fmt.id)?.to_le_bytes());
+        chunk_body.extend_from_slice(&SUBTYPE_GUID_TAIL);
+    }
+    Ok(chunk_body)
+}
+
+fn fmt_check(ref_fmt: &Fmt) -> Result<()> {
+    if ref_fmt.channel < 1 || ref_fmt.channel > usize::from(u16::MAX) {
+        return Err(WavFileError::Channel { channel: ref_fmt.channel });
+    }
+    if ref_fmt.bits == 0 || ref_fmt.bits > 64 || !ref_fmt.bits.is_multiple_of(8) {
+        return Err(WavFileError::Bits { bits: ref_fmt.bits });
+    }
+    if ref_fmt.valid_bits == 0 || ref_fmt.valid_bits > ref_fmt.bits {
+        return Err(WavFileError::ValidBits { valid_bits: ref_fmt.valid_bits, bits: ref_fmt.bits });
+    }
+    if ref_fmt.sampling_rate < MIN_SAMPLING_RATE || ref_fmt.sampling_rate > MAX_SAMPLING_RATE {
+        return Err(WavFileError::SamplingRate { sampling_rate: ref_fmt.sampling_rate });
+    }
+    Ok(())
 }
 
 fn f64wave_to_bytes(id:usize, f64_val: f64, len:usize) -> Result<Vec<u8>> {
@@ -425,64 +1125,72 @@ fn f64wave_to_bytes(id:usize, f64_val: f64, len:usize) -> Result<Vec<u8>> {
                 i32_val = (f64_val * (f64::from(i32::MAX)+1.0) ) as i32;
             }
 
-            let mut buffer:[u8; 4] = i32_val.to_le_bytes();
+            let buffer:[u8; 4] = i32_val.to_le_bytes();
             // TODO: Max Value Check for each length
             if len == 1 {   //unsigned 8bit
-                buffer[3] += 128;
-                Ok(vec!(buffer[3]))
+                Ok(vec!(buffer[3] ^ 0x80))
             }
-            else if len == 2 || len == 3 { //signed 16bit,24bit
+            else if len == 2 || len == 3 || len == 4 { //signed 16bit,24bit,32bit
                 Ok(buffer[4-len..4].to_vec())
             }
             else {
-                Err(WavFileError::new("Size is Too Small or Large!"))
+                Err(WavFileError::Bits { bits: len * 8 })
             }
         },
         3 => {
             if len == 4 {   //32bit float
                 Ok((f64_val as f32).to_le_bytes().to_vec())
             }
+            else if len == 8 {   //64bit float
+                Ok(f64_val.to_le_bytes().to_vec())
+            }
             else {
-                Err(WavFileError::new("Size is Too Small or Large!"))
+                Err(WavFileError::Bits { bits: len * 8 })
             }
         }
         _ => {
-            Err(WavFileError::new("Format is Irregal!"))
+            Err(WavFileError::FormatId { format_id: id })
         }
     }
 }
 
 pub fn to_channel_vec(ref_wav_audio: &WavAudio) -> Result<Vec<Vec<f64>>> {
-    fmt_check(&ref_wav_audio.fmt)?;
-    let mut ch_vec = Vec::new();
-    
-    let size = ref_wav_audio.fmt.bits / 8;
-    let step = ref_wav_audio.fmt.channel * size;
-    for _ in 0..ref_wav_audio.fmt.channel {
-        ch_vec.push(Vec::new());
-    }
-    for (pos,_dummy) in ref_wav_audio.data.iter().enumerate().step_by(step) {
-        //println!("{}",pos);
-        for ch in 0..ref_wav_audio.fmt.channel {
-            let stt = pos + ch*size;
-            ch_vec[ch].push(bytes_to_f64wave(ref_wav_audio.fmt.id, &ref_wav_audio.data[stt..stt+size])?);
-        }
-    }
-    Ok(ch_vec)
+    to_channel_vec_as(ref_wav_audio)
+}
+
+// to_channel_vec() in any sample type. e.g. f32 for half the memory, i16 for 16bit PCM as it is.
+pub fn to_channel_vec_as<S: Sample>(ref_wav_audio: &WavAudio) -> Result<Vec<Vec<S>>> {
+    // A trailing incomplete frame is ignored.
+    Ok(FrameView::new(&ref_wav_audio.data, &ref_wav_audio.fmt)?.to_channel_vec_as())
+}
+
+pub fn to_audio_buffer(ref_wav_audio: &WavAudio) -> Result<AudioBuffer> {
+    to_audio_buffer_as(ref_wav_audio)
+}
+
+pub fn to_audio_buffer_as<S: Sample>(ref_wav_audio: &WavAudio) -> Result<AudioBuffer<S>> {
+    Ok(AudioBuffer::new(to_channel_vec_as(ref_wav_audio)?, ref_wav_audio.fmt.sampling_rate)?)
 }
 
 pub fn to_wav_audio(ref_ch_vec: &Vec<Vec<f64>>, ref_fmt: &Fmt) -> Result<WavAudio> {
+    to_wav_audio_from(ref_ch_vec, ref_fmt)
+}
+
+pub fn to_wav_audio_from<S: Sample>(ref_ch_vec: &[Vec<S>], ref_fmt: &Fmt) -> Result<WavAudio> {
     fmt_check(ref_fmt)?;
     let mut data = Vec::new();
 
+    if ref_ch_vec.len() != ref_fmt.channel {
+        return Err(WavFileError::ChannelNum { expected: ref_fmt.channel, actual: ref_ch_vec.len() });
+    }
     for ch in ref_ch_vec {
         if ref_ch_vec[0].len() != ch.len() {
-            return Err(WavFileError::new("Irregal Data Vector! Data vector length are not same."));
+            return Err(WavFileError::DataLength { expected: ref_ch_vec[0].len(), actual: ch.len() });
         }
     }
     for (i,_) in ref_ch_vec[0].iter().enumerate() {
         for ch in 0..ref_fmt.channel {
-            let mut l_bytes = f64wave_to_bytes(ref_fmt.id, ref_ch_vec[ch][i], ref_fmt.bits/8)?;
+            let mut l_bytes = f64wave_to_bytes(ref_fmt.id, ref_ch_vec[ch][i].to_f64(), ref_fmt.bits/8)?;
             data.append(&mut l_bytes);
         }
     }
@@ -490,3 +1198,11 @@ pub fn to_wav_audio(ref_ch_vec: &Vec<Vec<f64>>, ref_fmt: &Fmt) -> Result<WavAudi
 }
 
 
+
+// Sampling rate of the buffer must match "ref_fmt".
+pub fn buffer_to_wav_audio<S: Sample>(ref_buffer: &AudioBuffer<S>, ref_fmt: &Fmt) -> Result<WavAudio> {
+    if ref_buffer.sampling_rate() != ref_fmt.sampling_rate {
+        return Err(WavFileError::FmtMismatch { field: "Sampling Rate", expected: ref_fmt.sampling_rate, actual: ref_buffer.sampling_rate() });
+    }
+    to_wav_audio_from(&ref_buffer.to_channel_vec(), ref_fmt)
+}
@@ -0,0 +1,75 @@
+use std::path::Path;
+use std::convert::TryFrom;
+use std::io::Cursor;
+
+use std::fs::File;
+
+use memmap2::Mmap;
+
+use super::error::*;
+use super::{Fmt, WavReader, FrameView};
+
+#[cfg(test)]
+mod tests {
+    use super::MappedWav;
+    use super::super::{WavFile, Fmt, to_wav_audio, to_channel_vec};
+
+    #[test]
+    fn test_mapped_wav() {
+        let ch_vec = vec![vec![0.5, 0.25, -0.25, -0.5, 0.0], vec![-0.5, -0.25, 0.25, 0.5, 0.0]];
+        let mut wav_file = WavFile::new();
+        wav_file.update_wav_audio(&to_wav_audio(&ch_vec, &Fmt::new(1, 2, 8000, 24)).unwrap()).unwrap();
+        let path = std::env::temp_dir().join("wavfile_mapped_test.wav");
+        wav_file.save_as(&path).unwrap();
+
+        let mapped_wav = MappedWav::open(&path).unwrap();
+        assert_eq!(mapped_wav.fmt().bits, 24);
+        assert_eq!(mapped_wav.frame_num(), 5);
+        let frame_view = mapped_wav.frame_view().unwrap();
+        assert_eq!(frame_view.to_channel_vec(), ch_vec);
+        assert_eq!(frame_view.channel_iter(1).sum::<f64>(), 0.0);
+        assert_eq!(to_channel_vec(&wav_file.get_wav_audio().unwrap()).unwrap(), ch_vec);
+        assert_eq!(wav_file.frame_view().unwrap().frames(3, 10).to_channel_vec(), vec![vec![-0.5, 0.0], vec![0.5, 0.0]]);
+        drop(mapped_wav);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+// Memory mapped WAV file.
+// Only chunk headers are parsed on open, samples are decoded through FrameView on access.
+pub struct MappedWav {
+    mmap: Mmap,
+    fmt: Fmt,
+    data_offset: usize,
+    data_size: usize,
+}
+
+impl MappedWav {
+    pub fn open(file_path: &Path) -> Result<MappedWav> {
+        // -- Check Parameter --
+        if !file_path.is_file() {
+            return Err(WavFileError::NotFile { path: file_path.to_path_buf() });
+        }
+        let target_file = File::open(file_path)?;
+        // The file must not be modified while it is mapped.
+        let mmap = unsafe { Mmap::map(&target_file)? };
+        let wav_reader = WavReader::new(Cursor::new(&mmap[..]))?;
+        let fmt = wav_reader.fmt().clone();
+        let data_offset = usize::try_from(wav_reader.data_info().offset)?;
+        let data_size = wav_reader.data_info().body_size;
+        Ok(MappedWav { mmap, fmt, data_offset, data_size })
+    }
+
+    pub fn fmt(&self) -> &Fmt {
+        &self.fmt
+    }
+
+    pub fn frame_num(&self) -> usize {
+        self.data_size / (self.fmt.channel * self.fmt.bits / 8)
+    }
+
+    // Borrowed view of the whole "data" chunk.
+    pub fn frame_view(&self) -> Result<FrameView<'_>> {
+        FrameView::new(&self.mmap[self.data_offset..(self.data_offset + self.data_size)], &self.fmt)
+    }
+}
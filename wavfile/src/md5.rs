@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod tests {
+    use super::Md5;
+
+    fn md5_hex(chunks: &[&[u8]]) -> String {
+        let mut md5 = Md5::new();
+        for chunk in chunks {
+            md5.update(chunk);
+        }
+        md5.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn test_md5() {
+        // RFC 1321 test suite
+        assert_eq!(md5_hex(&[b""]), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(md5_hex(&[b"abc"]), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(md5_hex(&[b"message digest"]), "f96b697d7cb7938d525a2f31aaf161d0");
+        assert_eq!(md5_hex(&[b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"]), "57edf4a22be3c955ac49da2e2107b67a");
+        // Split updates
+        assert_eq!(md5_hex(&[b"1234567890123456789012345678901234567890", b"1", b"234567890123456789012345678901234567890"]), "57edf4a22be3c955ac49da2e2107b67a");
+    }
+}
+
+const SHIFT: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+    5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+    4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+    6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+// floor(abs(sin(i + 1)) * 2^32)
+const TABLE: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+// MD5 (RFC 1321) for the audio signature of FLAC "STREAMINFO".
+pub(crate) struct Md5 {
+    state: [u32; 4],
+    block: [u8; 64],
+    block_len: usize,
+    total_len: u64,
+}
+
+impl Md5 {
+    pub(crate) fn new() -> Md5 {
+        Md5 { state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476], block: [0; 64], block_len: 0, total_len: 0 }
+    }
+
+    pub(crate) fn update(&mut self, mut bytes: &[u8]) {
+        self.total_len = self.total_len.wrapping_add(bytes.len() as u64);
+        while !bytes.is_empty() {
+            let len = (64 - self.block_len).min(bytes.len());
+            self.block[self.block_len..(self.block_len + len)].copy_from_slice(&bytes[..len]);
+            self.block_len += len;
+            bytes = &bytes[len..];
+            if self.block_len == 64 {
+                let block = self.block;
+                self.process(&block);
+                self.block_len = 0;
+            }
+        }
+    }
+
+    pub(crate) fn finalize(mut self) -> [u8; 16] {
+        let bit_len = self.total_len.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.block_len != 56 {
+            self.update(&[0]);
+        }
+        self.update(&bit_len.to_le_bytes());
+        let mut digest = [0; 16];
+        for (idx, word) in self.state.iter().enumerate() {
+            digest[(idx * 4)..(idx * 4 + 4)].copy_from_slice(&word.to_le_bytes());
+        }
+        digest
+    }
+
+    fn process(&mut self, block: &[u8; 64]) {
+        let mut words = [0_u32; 16];
+        for (idx, word) in words.iter_mut().enumerate() {
+            *word = u32::from_le_bytes([block[idx * 4], block[idx * 4 + 1], block[idx * 4 + 2], block[idx * 4 + 3]]);
+        }
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for idx in 0..64 {
+            let (f, word_idx) = match idx / 16 {
+                0 => ((b & c) | (!b & d), idx),
+                1 => ((d & b) | (!d & c), (5 * idx + 1) % 16),
+                2 => (b ^ c ^ d, (3 * idx + 5) % 16),
+                _ => (c ^ (b | !d), (7 * idx) % 16),
+            };
+            let rotated = a.wrapping_add(f).wrapping_add(TABLE[idx]).wrapping_add(words[word_idx]).rotate_left(SHIFT[idx]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+        for (state, val) in self.state.iter_mut().zip([a, b, c, d].iter()) {
+            *state = state.wrapping_add(*val);
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use std::path::Path;
+use std::convert::TryFrom;
+use std::io::prelude::*;
+use std::io::{BufReader, SeekFrom};
+
+use std::fs::File;
+
+use super::error::*;
+use super::{Fmt, SubChunk, Ds64, get_fmt, fmt_check, is_rf64_id, FrameView, Sample, get_ds64};
+
+#[cfg(test)]
+mod tests {
+    use super::WavReader;
+    use super::super::{Fmt, WavFile, SubChunk, to_wav_audio};
+    use std::io::Cursor;
+
+    fn test_wav_bytes(frame_num: usize) -> Vec<u8> {
+        let fmt = Fmt::new(1, 2, 8000, 16);
+        let mut ch_vec: Vec<Vec<f64>> = vec![Vec::new(), Vec::new()];
+        for i in 0..frame_num {
+            ch_vec[0].push((i % 100) as f64 / 128.0);
+            ch_vec[1].push(-((i % 50) as f64) / 128.0);
+        }
+        let mut wav_file = WavFile::new();
+        wav_file.update_sub_chunk(SubChunk { name: [b'J',b'U',b'N',b'K'], body_size: 4, data: vec![0; 4] }).unwrap();
+        wav_file.update_wav_audio(&to_wav_audio(&ch_vec, &fmt).unwrap()).unwrap();
+        let path = std::env::temp_dir().join(format!("wavfile_reader_test_{}.wav", frame_num));
+        wav_file.save_as(&path).unwrap();
+        let buf = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_read_frames_in_blocks() {
+        let mut wav_reader = WavReader::new(Cursor::new(test_wav_bytes(1000))).unwrap();
+        assert_eq!(wav_reader.fmt().channel, 2);
+        assert_eq!(wav_reader.frame_num(), 1000);
+
+        let mut ch_vec: Vec<Vec<f64>> = vec![Vec::new(), Vec::new()];
+        loop {
+            let block = wav_reader.read_frames(300).unwrap();
+            if block[0].is_empty() {
+                break;
+            }
+            for (ch_idx, mut ch) in block.into_iter().enumerate() {
+                ch_vec[ch_idx].append(&mut ch);
+            }
+        }
+        assert_eq!(ch_vec[0].len(), 1000);
+        assert_eq!(ch_vec[0][999], (999 % 100) as f64 / 128.0);
+        assert_eq!(ch_vec[1][999], -((999 % 50) as f64) / 128.0);
+
+        wav_reader.seek_frame(10).unwrap();
+        let block = wav_reader.read_frames(1).unwrap();
+        assert_eq!(block[0][0], 10.0 / 128.0);
+    }
+
+    #[test]
+    fn test_chunk_directory() {
+        let mut wav_reader = WavReader::new(Cursor::new(test_wav_bytes(10))).unwrap();
+        let names: Vec<[u8;4]> = wav_reader.chunk_infos().unwrap().iter().map(|info| info.name).collect();
+        assert_eq!(names, vec![[b'J',b'U',b'N',b'K'], [b'f',b'm',b't',b' '], [b'd',b'a',b't',b'a']]);
+        let junk = wav_reader.read_sub_chunk([b'J',b'U',b'N',b'K']).unwrap().unwrap();
+        assert_eq!(junk.data, vec![0; 4]);
+        assert!(wav_reader.read_sub_chunk([b'L',b'I',b'S',b'T']).unwrap().is_none());
+        assert_eq!(wav_reader.read_sub_chunks([b'J',b'U',b'N',b'K']).unwrap().len(), 1);
+        assert!(wav_reader.read_sub_chunks([b'L',b'I',b'S',b'T']).unwrap().is_empty());
+    }
+}
+
+// Position of a sub chunk in the file. "offset" points to the chunk body.
+#[derive(Clone)]
+pub struct ChunkInfo {
+    pub name: [u8;4],
+    pub offset: u64,
+    pub body_size: usize
+}
+
+// Streaming WAV reader.
+// Only chunk headers are read while scanning, sub chunk bodies are read on request
+// and the "data" chunk is decoded block by block.
+pub struct WavReader<R: Read + Seek> {
+    reader: R,
+    size: usize,
+    op_ds64: Option<Ds64>,
+    chunk_infos: Vec<ChunkInfo>,
+    next_chunk_addr: u64,
+    fmt: Fmt,
+    data_info: ChunkInfo,
+    frame_pos: usize,
+    block_buf: Vec<u8>,
+}
+
+impl WavReader<BufReader<File>> {
+    pub fn open(file_path: &Path) -> Result<WavReader<BufReader<File>>> {
+        // -- Check Parameter --
+        if !file_path.is_file() {
+            return Err(WavFileError::NotFile { path: file_path.to_path_buf() });
+        }
+        let target_file = File::open(file_path)?;
+        WavReader::new(BufReader::new(target_file))
+    }
+}
+
+impl<R: Read + Seek> WavReader<R> {
+    pub fn new(mut reader: R) -> Result<WavReader<R>> {
+        let file_size = usize::try_from(reader.seek(SeekFrom::End(0))?)?;
+        if file_size < 12 {
+            return Err(WavFileError::TruncatedHeader { expected: 12, actual: file_size });
+        }
+        reader.seek(SeekFrom::Start(0))?;
+        let mut header_buf = [0; 12];
+        reader.read_exact(&mut header_buf)?;
+
+        // "RIFF" or "RF64"/"BW64"
+        let riff_id = <[u8;4]>::try_from(&header_buf[0x00..0x04])?;
+        if riff_id != [b'R',b'I',b'F',b'F'] && !is_rf64_id(&riff_id) {
+            return Err(WavFileError::NotRiff { id: riff_id });
+        }
+        let mut op_ds64: Option<Ds64> = None;
+        let mut chunks_addr: u64 = 0x0c;
+        // RIFF Size
+        let mut riff_size = usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&header_buf[0x04..0x08])?))?;
+        if is_rf64_id(&riff_id) {
+            let ds64 = read_ds64(&mut reader, file_size)?;
+            riff_size = usize::try_from(ds64.riff_size)?;
+            chunks_addr += 8 + u64::try_from(ds64.chunk_size)?;
+            op_ds64 = Some(ds64);
+        }
+        if riff_size != file_size - 8 {
+            return Err(WavFileError::RiffSize { expected: file_size - 8, actual: riff_size });
+        }
+        // "WAVE"
+        if header_buf[0x08..0x0c] != [b'W',b'A',b'V',b'E'] {
+            return Err(WavFileError::NotWave { id: <[u8;4]>::try_from(&header_buf[0x08..0x0c])? });
+        }
+
+        let mut wav_reader = WavReader {
+            reader,
+            size: file_size,
+            op_ds64,
+            chunk_infos: Vec::new(),
+            next_chunk_addr: chunks_addr,
+            fmt: Fmt::new(0, 0, 0, 0),
+            data_info: ChunkInfo { name: [b'd',b'a',b't',b'a'], offset: 0, body_size: 0 },
+            frame_pos: 0,
+            block_buf: Vec::new(),
+        };
+
+        // Scan only until "fmt " and "data" are found.
+        let mut op_fmt: Option<Fmt> = None;
+        let mut op_data_info: Option<ChunkInfo> = None;
+        while op_fmt.is_none() || op_data_info.is_none() {
+            let chunk_info = match wav_reader.scan_next_chunk()? {
+                Some(chunk_info) => chunk_info,
+                None => break,
+            };
+            match chunk_info.name {
+                [b'f',b'm',b't',b' '] => {
+                    if op_fmt.is_some() {
+                        return Err(WavFileError::DuplicateChunk { id: [b'f',b'm',b't',b' '] });
+                    }
+                    let body = wav_reader.read_chunk_body(&chunk_info)?;
+                    op_fmt = Some(get_fmt(&body)?);
+                }
+                [b'd',b'a',b't',b'a'] => {
+                    if op_data_info.is_some() {
+                        return Err(WavFileError::DuplicateChunk { id: [b'd',b'a',b't',b'a'] });
+                    }
+                    op_data_info = Some(chunk_info);
+                }
+                _ => {}
+            }
+        }
+        if let Some(fmt) = op_fmt {
+            fmt_check(&fmt)?;
+            wav_reader.fmt = fmt;
+        }
+        else {
+            return Err(WavFileError::MissingChunk { id: [b'f',b'm',b't',b' '] });
+        }
+        if let Some(data_info) = op_data_info {
+            wav_reader.data_info = data_info;
+        }
+        else {
+            return Err(WavFileError::MissingChunk { id: [b'd',b'a',b't',b'a'] });
+        }
+        Ok(wav_reader)
+    }
+
+    pub fn fmt(&self) -> &Fmt {
+        &self.fmt
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // "data" chunk position
+    pub(crate) fn data_info(&self) -> &ChunkInfo {
+        &self.data_info
+    }
+
+    pub fn frame_num(&self) -> usize {
+        self.data_info.body_size / self.block_size()
+    }
+
+    pub fn frame_pos(&self) -> usize {
+        self.frame_pos
+    }
+
+    pub fn seek_frame(&mut self, frame_pos: usize) -> Result<()> {
+        if frame_pos > self.frame_num() {
+            return Err(WavFileError::FramePosition { position: frame_pos, frame_num: self.frame_num() });
+        }
+        self.frame_pos = frame_pos;
+        Ok(())
+    }
+
+    // Decode up to "frame_num" frames from the current position. channel<data>
+    // Channel vectors are empty when the end of the data chunk is reached.
+    pub fn read_frames(&mut self, frame_num: usize) -> Result<Vec<Vec<f64>>> {
+        self.read_frames_as(frame_num)
+    }
+
+    // read_frames() in any sample type.
+    pub fn read_frames_as<S: Sample>(&mut self, frame_num: usize) -> Result<Vec<Vec<S>>> {
+        let block_size = self.block_size();
+        let read_frame_num = frame_num.min(self.frame_num() - self.frame_pos);
+        if read_frame_num == 0 {
+            return Ok(vec![Vec::new(); self.fmt.channel]);
+        }
+
+        let addr = self.data_info.offset + u64::try_from(self.frame_pos * block_size)?;
+        self.block_buf.resize(read_frame_num * block_size, 0);
+        self.reader.seek(SeekFrom::Start(addr))?;
+        self.reader.read_exact(&mut self.block_buf)?;
+
+        let ch_vec = FrameView::new(&self.block_buf, &self.fmt)?.to_channel_vec_as();
+        self.frame_pos += read_frame_num;
+        Ok(ch_vec)
+    }
+
+    // Whole chunk directory. The rest of the file is scanned on the first call.
+    pub fn chunk_infos(&mut self) -> Result<&Vec<ChunkInfo>> {
+        while self.scan_next_chunk()?.is_some() {}
+        Ok(&self.chunk_infos)
+    }
+
+    // Read the body of the first sub chunk named "name".
+    pub fn read_sub_chunk(&mut self, name: [u8;4]) -> Result<Option<SubChunk>> {
+        let mut op_chunk_info = self.chunk_infos.iter().find(|info| info.name == name).cloned();
+        while op_chunk_info.is_none() {
+            match self.scan_next_chunk()? {
+                Some(chunk_info) if chunk_info.name == name => { op_chunk_info = Some(chunk_info); }
+                Some(_) => {}
+                None => { return Ok(None); }
+            }
+        }
+        let chunk_info = op_chunk_info.unwrap();
+        Ok(Some(SubChunk {
+            name: chunk_info.name,
+            body_size: chunk_info.body_size,
+            data: self.read_chunk_body(&chunk_info)?
+        }))
+    }
+
+    // Bodies of all sub chunks named "name". (e.g. "LIST" of "INFO" and "adtl")
+    pub fn read_sub_chunks(&mut self, name: [u8;4]) -> Result<Vec<SubChunk>> {
+        let chunk_info_vec: Vec<ChunkInfo> = self.chunk_infos()?.iter().filter(|info| info.name == name).cloned().collect();
+        chunk_info_vec.iter().map(|chunk_info| Ok(SubChunk { name, body_size: chunk_info.body_size, data: self.read_chunk_body(chunk_info)? })).collect()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn block_size(&self) -> usize {
+        self.fmt.channel * self.fmt.bits / 8
+    }
+
+    fn scan_next_chunk(&mut self) -> Result<Option<ChunkInfo>> {
+        let chunk_head_addr = self.next_chunk_addr;
+        if u64::try_from(self.size)? < chunk_head_addr + 8 {
+            return Ok(None);
+        }
+        let mut chunk_head_buf = [0; 8];
+        self.reader.seek(SeekFrom::Start(chunk_head_addr))?;
+        self.reader.read_exact(&mut chunk_head_buf)?;
+        let header_size = u32::from_le_bytes(<[u8;4]>::try_from(&chunk_head_buf[0x04..0x08])?);
+        let chunk_body_size = match &self.op_ds64 {
+            Some(ds64) => ds64.chunk_body_size(<[u8;4]>::try_from(&chunk_head_buf[0x00..0x04])?, header_size)?,
+            None => usize::try_from(header_size)?,
+        };
+        if u64::try_from(self.size)? < chunk_head_addr + 8 + u64::try_from(chunk_body_size)? {
+            return Err(WavFileError::TruncatedChunk {
+                id: <[u8;4]>::try_from(&chunk_head_buf[0x00..0x04])?,
+                offset: usize::try_from(chunk_head_addr)?,
+                expected: chunk_body_size,
+                actual: self.size - usize::try_from(chunk_head_addr)? - 8
+            });
+        }
+        let chunk_info = ChunkInfo {
+            name: [ chunk_head_buf[0x00],
+                    chunk_head_buf[0x01],
+                    chunk_head_buf[0x02],
+                    chunk_head_buf[0x03]],
+            offset: chunk_head_addr + 8,
+            body_size: chunk_body_size
+        };
+        // Odd size chunk is followed by a pad byte.
+        self.next_chunk_addr = chunk_info.offset + u64::try_from(chunk_body_size + (chunk_body_size & 1))?;
+        self.chunk_infos.push(chunk_info.clone());
+        Ok(Some(chunk_info))
+    }
+
+    fn read_chunk_body(&mut self, chunk_info: &ChunkInfo) -> Result<Vec<u8>> {
+        let mut body = vec![0; chunk_info.body_size];
+        self.reader.seek(SeekFrom::Start(chunk_info.offset))?;
+        self.reader.read_exact(&mut body)?;
+        Ok(body)
+    }
+}
+
+// "ds64" chunk just after "WAVE" of RF64.
+fn read_ds64<R: Read + Seek>(reader: &mut R, file_size: usize) -> Result<Ds64> {
+    let mut chunk_head_buf = [0; 8];
+    if file_size >= 0x0c + 8 {
+        reader.seek(SeekFrom::Start(0x0c))?;
+        reader.read_exact(&mut chunk_head_buf)?;
+    }
+    let chunk_size = usize::try_from(u32::from_le_bytes(<[u8;4]>::try_from(&chunk_head_buf[0x04..0x08])?))?;
+    let mut buf = chunk_head_buf.to_vec();
+    if file_size >= 0x0c + 8 + chunk_size {
+        buf.resize(8 + chunk_size, 0);
+        reader.read_exact(&mut buf[8..])?;
+    }
+    get_ds64(&buf)
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::path::Path;
+
+use super::error::*;
+use super::WavFile;
+
+#[cfg(test)]
+mod tests {
+    use super::{Repair, repair};
+    use super::super::{WavFile, Fmt, to_wav_audio, to_channel_vec};
+
+    fn broken_path(name: &str, buf: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, buf).unwrap();
+        path
+    }
+
+    // 16bit stereo, 4 frames
+    fn valid_wav_bytes() -> Vec<u8> {
+        let mut wav_file = WavFile::new();
+        let ch_vec = vec![vec![0.5, 0.25, -0.25, -0.5], vec![-0.5, -0.25, 0.25, 0.5]];
+        wav_file.update_wav_audio(&to_wav_audio(&ch_vec, &Fmt::new(1, 2, 8000, 16)).unwrap()).unwrap();
+        let path = std::env::temp_dir().join("wavfile_repair_valid.wav");
+        wav_file.save_as(&path).unwrap();
+        let buf = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_streamed_sizes() {
+        // RIFF size 0 and "data" size 0xFFFFFFFF as written by streaming tools
+        let mut buf = valid_wav_bytes();
+        buf[0x04..0x08].copy_from_slice(&0_u32.to_le_bytes());
+        let data_size_addr = buf.len() - 16 - 4;
+        buf[data_size_addr..data_size_addr + 4].copy_from_slice(&0xFFFFFFFF_u32.to_le_bytes());
+
+        let mut wav_file = WavFile::new();
+        assert!(wav_file.open_bytes(buf.clone()).is_err());
+        let repair_vec = wav_file.open_bytes_lenient(buf).unwrap();
+        assert_eq!(repair_vec, vec![
+            Repair::RiffSize { header: 0, actual: 44 + 16 - 8 },
+            Repair::ChunkSize { id: *b"data", offset: 0x24, header: 0xFFFFFFFF, actual: 16 },
+        ]);
+        assert_eq!(to_channel_vec(&wav_file.get_wav_audio().unwrap()).unwrap()[1], vec![-0.5, -0.25, 0.25, 0.5]);
+    }
+
+    #[test]
+    fn test_crashed_recording() {
+        // RIFF and "data" sizes are 0 and the last frame is cut in the middle.
+        let mut buf = valid_wav_bytes();
+        buf[0x04..0x08].copy_from_slice(&0_u32.to_le_bytes());
+        let data_size_addr = buf.len() - 16 - 4;
+        buf[data_size_addr..data_size_addr + 4].copy_from_slice(&0_u32.to_le_bytes());
+        buf.truncate(buf.len() - 3);
+
+        let path = broken_path("wavfile_repair_crashed.wav", &buf);
+        let mut wav_file = WavFile::new();
+        assert!(wav_file.open(&path).is_err());
+        let repair_vec = repair(&path).unwrap();
+        assert_eq!(repair_vec[1], Repair::ChunkSize { id: *b"data", offset: 0x24, header: 0, actual: 12 });
+
+        // Strict open works after the repair.
+        wav_file.open(&path).unwrap();
+        assert_eq!(wav_file.size, 44 + 12);
+        assert_eq!(to_channel_vec(&wav_file.get_wav_audio().unwrap()).unwrap()[0], vec![0.5, 0.25, -0.25]);
+        assert!(repair(&path).unwrap().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_no_repair_needed() {
+        let buf = valid_wav_bytes();
+        let mut wav_file = WavFile::new();
+        assert!(wav_file.open_bytes_lenient(buf.clone()).unwrap().is_empty());
+        // Wrong header is still an error.
+        let mut buf = buf;
+        buf[0x08] = b'X';
+        assert!(wav_file.open_bytes_lenient(buf).is_err());
+    }
+}
+
+// What open_lenient() changed to read the file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Repair {
+    // RIFF size in the header is replaced with the actual file size - 8.
+    RiffSize { header: usize, actual: usize },
+    // Chunk body size is clamped to the available bytes (and whole frames for "data").
+    ChunkSize { id: [u8;4], offset: usize, header: usize, actual: usize },
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> std::result::Result<(), fmt::Error> {
+        match self {
+            Repair::RiffSize { header, actual } => write!(f, "RIFF Size:{} -> {}", header, actual),
+            Repair::ChunkSize { id, offset, header, actual } =>
+                write!(f, "\"{}\" at 0x{:x} Size:{} -> {}", String::from_utf8_lossy(id), offset, header, actual),
+        }
+    }
+}
+
+// Open the file leniently and rewrite it with correct sizes.
+// Returns the repairs. The file is not written when nothing is repaired.
+pub fn repair(file_path: &Path) -> Result<Vec<Repair>> {
+    let mut wav_file = WavFile::new();
+    let repair_vec = wav_file.open_lenient(file_path)?;
+    if !repair_vec.is_empty() {
+        wav_file.save()?;
+    }
+    Ok(repair_vec)
+}
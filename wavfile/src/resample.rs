@@ -0,0 +1,194 @@
+use std::convert::TryFrom;
+use std::f64::consts::PI;
+
+use super::error::*;
+use super::{WavAudio, to_channel_vec};
+
+#[cfg(test)]
+mod tests {
+    use super::resample;
+    use std::f64::consts::PI;
+
+    fn sine_vec(hz: f64, sampling_rate: usize, len: usize) -> Vec<f64> {
+        (0..len).map(|i| 0.5 * (2.0 * PI * hz * i as f64 / sampling_rate as f64).sin()).collect()
+    }
+
+    // Max error against the ideal sine, skipping the edges.
+    fn max_error(ref_data_vec: &[f64], hz: f64, sampling_rate: usize) -> f64 {
+        let expected_vec = sine_vec(hz, sampling_rate, ref_data_vec.len());
+        let margin = ref_data_vec.len() / 10;
+        let mut max = 0.0;
+        for i in margin..(ref_data_vec.len() - margin) {
+            let error = (ref_data_vec[i] - expected_vec[i]).abs();
+            if error > max {
+                max = error;
+            }
+        }
+        max
+    }
+
+    fn rms(ref_data_vec: &[f64]) -> f64 {
+        let margin = ref_data_vec.len() / 10;
+        let middle = &ref_data_vec[margin..(ref_data_vec.len() - margin)];
+        (middle.iter().map(|val| val * val).sum::<f64>() / middle.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn test_passband_flatness() {
+        for (from_rate, to_rate) in [(48000, 44100), (44100, 48000), (48000, 16000), (8000, 44100), (22050, 32000)].iter() {
+            let lower_nyquist = (*from_rate).min(*to_rate) as f64 / 2.0;
+            for ratio in [0.05, 0.2, 0.5, 0.8].iter() {
+                let hz = lower_nyquist * ratio;
+                let ch_vec = resample(&[sine_vec(hz, *from_rate, *from_rate / 2)], *from_rate, *to_rate).unwrap();
+                assert_eq!(ch_vec[0].len(), *to_rate / 2);
+                // 0.5 * 0.001 = -66dB from full scale
+                assert!(max_error(&ch_vec[0], hz, *to_rate) < 0.5 * 0.001, "{}Hz {}->{}", hz, from_rate, to_rate);
+            }
+        }
+    }
+
+    #[test]
+    fn test_aliasing_rejection() {
+        for (from_rate, to_rate) in [(48000, 16000), (44100, 8000), (96000, 44100)].iter() {
+            let lower_nyquist = (*from_rate).min(*to_rate) as f64 / 2.0;
+            for ratio in [1.1, 1.5, 2.5].iter() {
+                let hz = lower_nyquist * ratio;
+                if hz >= *from_rate as f64 / 2.0 {
+                    continue;
+                }
+                let ch_vec = resample(&[sine_vec(hz, *from_rate, *from_rate / 2)], *from_rate, *to_rate).unwrap();
+                // -80dB against the input level
+                assert!(rms(&ch_vec[0]) < 0.5 / 2_f64.sqrt() * 0.0001, "{}Hz {}->{}", hz, from_rate, to_rate);
+            }
+        }
+    }
+
+    #[test]
+    fn test_same_rate() {
+        let data_vec = sine_vec(1000.0, 8000, 100);
+        assert_eq!(resample(std::slice::from_ref(&data_vec), 8000, 8000).unwrap()[0], data_vec);
+        assert!(resample(&[data_vec], 0, 8000).is_err());
+    }
+}
+
+// Zero crossings of the sinc on each side (in the lower sampling rate).
+const SINC_ZERO_CROSSINGS: usize = 48;
+// Cutoff frequency against the lower Nyquist frequency.
+const SINC_CUTOFF: f64 = 0.9;
+const KAISER_BETA: f64 = 10.0;
+// Polyphase table is used when it has up to this number of taps.
+const MAX_TABLE_TAPS: usize = 1 << 22;
+
+// Modified Bessel function of the first kind, order 0.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-16 {
+        term *= (x / (2.0 * k)).powi(2);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+// Kaiser windowed sinc low pass filter in units of input samples.
+struct SincKernel {
+    cutoff: f64,        // cycle per input sample * 2
+    half_width: f64,    // input samples
+    tap_half_num: isize,
+    i0_beta: f64,
+}
+
+impl SincKernel {
+    fn new(from_rate: usize, to_rate: usize) -> SincKernel {
+        let step = if to_rate < from_rate { to_rate as f64 / from_rate as f64 } else { 1.0 };
+        let cutoff = SINC_CUTOFF * step;
+        let half_width = SINC_ZERO_CROSSINGS as f64 / step;
+        SincKernel {
+            cutoff,
+            half_width,
+            tap_half_num: half_width.ceil() as isize,
+            i0_beta: bessel_i0(KAISER_BETA),
+        }
+    }
+
+    fn value(&self, pos: f64) -> f64 {
+        if pos.abs() >= self.half_width {
+            return 0.0;
+        }
+        let window = bessel_i0(KAISER_BETA * (1.0 - (pos / self.half_width).powi(2)).sqrt()) / self.i0_beta;
+        let sinc = if pos == 0.0 { 1.0 } else { (PI * self.cutoff * pos).sin() / (PI * self.cutoff * pos) };
+        self.cutoff * sinc * window
+    }
+
+    // Taps for input samples (base - tap_half_num + 1)..=(base + tap_half_num) where the output is at base + frac.
+    fn taps(&self, frac: f64) -> Vec<f64> {
+        ((1 - self.tap_half_num)..=self.tap_half_num).map(|k| self.value(k as f64 - frac)).collect()
+    }
+}
+
+// Band-limited sample rate conversion. channel<data>
+pub fn resample(ref_ch_vec: &[Vec<f64>], from_rate: usize, to_rate: usize) -> Result<Vec<Vec<f64>>> {
+    if from_rate == 0 {
+        return Err(WavFileError::SamplingRate { sampling_rate: from_rate });
+    }
+    if to_rate == 0 {
+        return Err(WavFileError::SamplingRate { sampling_rate: to_rate });
+    }
+    if from_rate == to_rate {
+        return Ok(ref_ch_vec.to_vec());
+    }
+    // Output position n is at input position n * from_rate / to_rate = base + phase / phase_num
+    let rate_gcd = gcd(from_rate, to_rate);
+    let phase_num = to_rate / rate_gcd;
+    let phase_step = from_rate / rate_gcd;
+    let kernel = SincKernel::new(from_rate, to_rate);
+    let tap_num = usize::try_from(kernel.tap_half_num)? * 2;
+
+    let op_table: Option<Vec<Vec<f64>>> = if phase_num * tap_num <= MAX_TABLE_TAPS {
+        Some((0..phase_num).map(|phase| kernel.taps(phase as f64 / phase_num as f64)).collect())
+    }
+    else {
+        None
+    };
+
+    let mut ret_ch_vec: Vec<Vec<f64>> = Vec::with_capacity(ref_ch_vec.len());
+    for ch in ref_ch_vec {
+        let out_len = (ch.len() * phase_num).div_ceil(phase_step);
+        let mut out_vec: Vec<f64> = Vec::with_capacity(out_len);
+        for n in 0..out_len {
+            let base = n * phase_step / phase_num;
+            let phase = n * phase_step % phase_num;
+            let calc_taps;
+            let taps = match &op_table {
+                Some(table) => &table[phase],
+                None => {
+                    calc_taps = kernel.taps(phase as f64 / phase_num as f64);
+                    &calc_taps
+                }
+            };
+            let first = isize::try_from(base)? + 1 - kernel.tap_half_num;
+            let mut sum = 0.0;
+            for (k, tap) in taps.iter().enumerate() {
+                let idx = first + k as isize;
+                if idx >= 0 && (idx as usize) < ch.len() {
+                    sum += ch[idx as usize] * tap;
+                }
+            }
+            out_vec.push(sum);
+        }
+        ret_ch_vec.push(out_vec);
+    }
+    Ok(ret_ch_vec)
+}
+
+// to_channel_vec() converted to "sampling_rate".
+pub fn to_channel_vec_at(ref_wav_audio: &WavAudio, sampling_rate: usize) -> Result<Vec<Vec<f64>>> {
+    let ch_vec = to_channel_vec(ref_wav_audio)?;
+    resample(&ch_vec, ref_wav_audio.fmt.sampling_rate, sampling_rate)
+}
@@ -0,0 +1,168 @@
+use std::convert::TryFrom;
+
+use super::error::*;
+use super::bytes::read_u32;
+use super::{WavFile, SubChunk};
+
+#[cfg(test)]
+mod tests {
+    use super::{Smpl, SampleLoop, SMPL_HEAD_SIZE};
+    use super::super::{WavFile, SubChunk, Fmt, to_wav_audio};
+
+    fn test_wav_file() -> WavFile {
+        let mut wav_file = WavFile::new();
+        wav_file.update_wav_audio(&to_wav_audio(&vec![vec![0.0; 1000]], &Fmt::new(1, 1, 44100, 16)).unwrap()).unwrap();
+        wav_file
+    }
+
+    #[test]
+    fn test_smpl_round_trip() {
+        let mut wav_file = test_wav_file();
+        assert!(wav_file.smpl().unwrap().is_none());
+
+        let mut smpl = Smpl::new(44100);
+        assert_eq!(smpl.sample_period, 22675);
+        smpl.unity_note = 57;
+        smpl.pitch_fraction = 0x8000_0000;
+        smpl.loops.push(SampleLoop { id: 0, loop_type: 0, start: 100, end: 899, fraction: 0, play_count: 0 });
+        smpl.loops.push(SampleLoop { id: 1, loop_type: 1, start: 200, end: 299, fraction: 0, play_count: 3 });
+        smpl.sampler_data = vec![1, 2, 3];
+        wav_file.set_smpl(&smpl).unwrap();
+        assert_eq!(smpl.unity_pitch(), 57.5);
+
+        let path = std::env::temp_dir().join("wavfile_smpl_test.wav");
+        wav_file.save_as(&path).unwrap();
+        let mut read_file = WavFile::new();
+        read_file.open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read_file.smpl().unwrap().unwrap(), smpl);
+    }
+
+    #[test]
+    fn test_smpl_broken() {
+        let mut wav_file = test_wav_file();
+        let mut data = vec![0; SMPL_HEAD_SIZE];
+        data[0x1c] = 2; // 2 loops without loop data
+        wav_file.update_sub_chunk(SubChunk { name: *b"smpl", body_size: data.len(), data }).unwrap();
+        assert!(wav_file.smpl().is_err());
+    }
+}
+
+const SMPL_HEAD_SIZE: usize = 36;
+const SAMPLE_LOOP_SIZE: usize = 24;
+
+// Loop of "smpl" chunk. "start" and "end" are frame positions, "end" is included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleLoop {
+    pub id: usize,          // cue point ID
+    pub loop_type: usize,   // 0:forward 1:alternating 2:backward
+    pub start: usize,
+    pub end: usize,
+    pub fraction: usize,
+    pub play_count: usize,  // 0:infinite
+}
+
+// Sampler chunk ("smpl")
+#[derive(Debug, Clone, PartialEq)]
+pub struct Smpl {
+    pub manufacturer: usize,
+    pub product: usize,
+    pub sample_period: usize,   // ns
+    pub unity_note: usize,      // MIDI note number
+    pub pitch_fraction: usize,  // fraction of a semitone in 1/2^32
+    pub smpte_format: usize,
+    pub smpte_offset: usize,
+    pub loops: Vec<SampleLoop>,
+    pub sampler_data: Vec<u8>,
+}
+
+impl Smpl {
+    pub fn new(sampling_rate: usize) -> Smpl {
+        Smpl {
+            manufacturer: 0,
+            product: 0,
+            sample_period: 1_000_000_000 / sampling_rate.max(1),
+            unity_note: 60,
+            pitch_fraction: 0,
+            smpte_format: 0,
+            smpte_offset: 0,
+            loops: Vec::new(),
+            sampler_data: Vec::new(),
+        }
+    }
+
+    // Unity note with the pitch fraction in MIDI note number.
+    pub fn unity_pitch(&self) -> f64 {
+        self.unity_note as f64 + self.pitch_fraction as f64 / 4294967296.0
+    }
+}
+
+impl WavFile {
+    // Typed "smpl" chunk. None if the file has no "smpl" chunk.
+    pub fn smpl(&self) -> Result<Option<Smpl>> {
+        match self.find_sub_chunk([b's',b'm',b'p',b'l']) {
+            Some(sub_chunk) => Ok(Some(get_smpl(&sub_chunk.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_smpl(&mut self, ref_smpl: &Smpl) -> Result<()> {
+        let data = set_smpl(ref_smpl)?;
+        self.update_sub_chunk(SubChunk { name: [b's',b'm',b'p',b'l'], body_size: data.len(), data })
+    }
+}
+
+fn get_smpl(ref_chunk_body: &[u8]) -> Result<Smpl> {
+    if ref_chunk_body.len() < SMPL_HEAD_SIZE {
+        return Err(WavFileError::TruncatedChunk { id: [b's',b'm',b'p',b'l'], offset: 0, expected: SMPL_HEAD_SIZE, actual: ref_chunk_body.len() });
+    }
+    let loop_num = read_u32(ref_chunk_body, 0x1c)?;
+    let sampler_data_size = read_u32(ref_chunk_body, 0x20)?;
+    let loops_end = SMPL_HEAD_SIZE + loop_num * SAMPLE_LOOP_SIZE;
+    if (ref_chunk_body.len() - SMPL_HEAD_SIZE) / SAMPLE_LOOP_SIZE < loop_num || ref_chunk_body.len() - loops_end < sampler_data_size {
+        return Err(WavFileError::TruncatedChunk {
+            id: [b's',b'm',b'p',b'l'],
+            offset: 0,
+            expected: loops_end.saturating_add(sampler_data_size),
+            actual: ref_chunk_body.len()
+        });
+    }
+    let mut loops: Vec<SampleLoop> = Vec::with_capacity(loop_num);
+    for loop_buf in ref_chunk_body[SMPL_HEAD_SIZE..loops_end].chunks_exact(SAMPLE_LOOP_SIZE) {
+        loops.push(SampleLoop {
+            id: read_u32(loop_buf, 0x00)?,
+            loop_type: read_u32(loop_buf, 0x04)?,
+            start: read_u32(loop_buf, 0x08)?,
+            end: read_u32(loop_buf, 0x0c)?,
+            fraction: read_u32(loop_buf, 0x10)?,
+            play_count: read_u32(loop_buf, 0x14)?,
+        });
+    }
+    Ok(Smpl {
+        manufacturer: read_u32(ref_chunk_body, 0x00)?,
+        product: read_u32(ref_chunk_body, 0x04)?,
+        sample_period: read_u32(ref_chunk_body, 0x08)?,
+        unity_note: read_u32(ref_chunk_body, 0x0c)?,
+        pitch_fraction: read_u32(ref_chunk_body, 0x10)?,
+        smpte_format: read_u32(ref_chunk_body, 0x14)?,
+        smpte_offset: read_u32(ref_chunk_body, 0x18)?,
+        loops,
+        sampler_data: ref_chunk_body[loops_end..(loops_end + sampler_data_size)].to_vec(),
+    })
+}
+
+fn set_smpl(ref_smpl: &Smpl) -> Result<Vec<u8>> {
+    let mut chunk_body: Vec<u8> = Vec::new();
+    for val in [ref_smpl.manufacturer, ref_smpl.product, ref_smpl.sample_period, ref_smpl.unity_note,
+                ref_smpl.pitch_fraction, ref_smpl.smpte_format, ref_smpl.smpte_offset,
+                ref_smpl.loops.len(), ref_smpl.sampler_data.len()].iter() {
+        chunk_body.extend_from_slice(&u32::try_from(*val)?.to_le_bytes());
+    }
+    for sample_loop in &ref_smpl.loops {
+        for val in [sample_loop.id, sample_loop.loop_type, sample_loop.start, sample_loop.end, sample_loop.fraction, sample_loop.play_count].iter() {
+            chunk_body.extend_from_slice(&u32::try_from(*val)?.to_le_bytes());
+        }
+    }
+    chunk_body.extend_from_slice(&ref_smpl.sampler_data);
+    Ok(chunk_body)
+}
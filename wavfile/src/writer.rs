@@ -0,0 +1,264 @@
+use std::path::Path;
+use std::convert::TryFrom;
+use std::io::prelude::*;
+use std::io::{BufWriter, SeekFrom};
+
+use std::fs::File;
+
+use super::error::*;
+use super::{Fmt, SubChunk, Ds64, set_fmt, set_ds64, fmt_check, f64wave_to_bytes, DS64_MIN_SIZE, RIFF_SIZE_LIMIT};
+
+#[cfg(test)]
+mod tests {
+    use super::WavWriter;
+    use super::super::{Fmt, WavReader};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_write_blocks_and_finalize() {
+        let fmt = Fmt::new(1, 2, 16000, 24);
+        let mut wav_writer = WavWriter::new(Cursor::new(Vec::new()), &fmt).unwrap();
+        for block_idx in 0..4 {
+            let l_ch: Vec<f64> = (0..100).map(|i| f64::from(block_idx * 100 + i) / 1024.0).collect();
+            let r_ch: Vec<f64> = l_ch.iter().map(|val| -val).collect();
+            wav_writer.write_channels(&[l_ch, r_ch]).unwrap();
+        }
+        wav_writer.write_interleaved(&[0.5, -0.5]).unwrap();
+        let cursor = wav_writer.finalize().unwrap();
+
+        let mut wav_reader = WavReader::new(Cursor::new(cursor.into_inner())).unwrap();
+        assert_eq!(wav_reader.frame_num(), 401);
+        let ch_vec = wav_reader.read_frames(401).unwrap();
+        assert_eq!(ch_vec[0][399], 399.0 / 1024.0);
+        assert_eq!(ch_vec[1][399], -399.0 / 1024.0);
+        assert_eq!(ch_vec[0][400], 0.5);
+        assert_eq!(ch_vec[1][400], -0.5);
+    }
+
+    #[test]
+    fn test_sizes_fixed_on_drop() {
+        let fmt = Fmt::new(3, 1, 8000, 32);
+        let mut buf = Vec::new();
+        {
+            let mut wav_writer = WavWriter::new(Cursor::new(&mut buf), &fmt).unwrap();
+            wav_writer.write_interleaved(&[0.25; 10]).unwrap();
+        }
+        let wav_reader = WavReader::new(Cursor::new(buf)).unwrap();
+        assert_eq!(wav_reader.frame_num(), 10);
+    }
+
+    #[test]
+    fn test_switch_to_rf64() {
+        let fmt = Fmt::new(1, 1, 8000, 16);
+        let mut wav_writer = WavWriter::new(Cursor::new(Vec::new()), &fmt).unwrap();
+        // Small limit instead of 4GiB
+        wav_writer.size_limit = 100;
+        let data_vec: Vec<f64> = (0..100).map(|i| f64::from(i) / 128.0).collect();
+        wav_writer.write_interleaved(&data_vec).unwrap();
+        let buf = wav_writer.finalize().unwrap().into_inner();
+        assert_eq!(&buf[0x00..0x04], b"RF64");
+        assert_eq!(&buf[0x0c..0x10], b"ds64");
+
+        let mut wav_reader = WavReader::new(Cursor::new(buf)).unwrap();
+        assert_eq!(wav_reader.frame_num(), 100);
+        assert_eq!(wav_reader.read_frames(100).unwrap()[0], data_vec);
+    }
+
+    #[test]
+    fn test_wrong_frame_shape() {
+        let fmt = Fmt::new(1, 2, 8000, 16);
+        let mut wav_writer = WavWriter::new(Cursor::new(Vec::new()), &fmt).unwrap();
+        assert!(wav_writer.write_interleaved(&[0.0; 3]).is_err());
+        assert!(wav_writer.write_channels(&[vec![0.0; 3], vec![0.0; 2]]).is_err());
+        assert!(wav_writer.write_channels(&[vec![0.0; 3]]).is_err());
+    }
+}
+
+// Incremental WAV writer.
+// The header is written first with zero sizes, frames are appended as they are produced
+// and the RIFF and "data" sizes are fixed up by finalize() (or when dropped).
+// A "JUNK" chunk is reserved after "WAVE" and replaced with "ds64" when the file grows over 4GiB (RF64).
+pub struct WavWriter<W: Write + Seek> {
+    op_writer: Option<W>,
+    fmt: Fmt,
+    riff_size_addr: u64,
+    riff_size: usize,
+    data_size_addr: u64,
+    data_size: usize,
+    size_limit: usize,
+    byte_buf: Vec<u8>,
+}
+
+impl WavWriter<BufWriter<File>> {
+    pub fn create(file_path: &Path, ref_fmt: &Fmt) -> Result<WavWriter<BufWriter<File>>> {
+        // -- Check Parameter --
+        if let Some(ext) = file_path.extension() {
+            if (ext != "wav") && (ext != "WAV") {
+                return Err(WavFileError::Extension { path: file_path.to_path_buf() });
+            }
+        }
+        else {
+            return Err(WavFileError::NotFile { path: file_path.to_path_buf() });
+        }
+        let target_file = File::create(file_path)?;
+        WavWriter::new(BufWriter::new(target_file), ref_fmt)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(writer: W, ref_fmt: &Fmt) -> Result<WavWriter<W>> {
+        WavWriter::with_sub_chunks(writer, ref_fmt, &[])
+    }
+
+    // "sub_chunks" are written between "fmt " and "data" chunks.
+    pub fn with_sub_chunks(mut writer: W, ref_fmt: &Fmt, sub_chunks: &[SubChunk]) -> Result<WavWriter<W>> {
+        fmt_check(ref_fmt)?;
+        let fmt_buf = set_fmt(ref_fmt)?;
+
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend_from_slice(b"RIFF");
+        buf.extend_from_slice(&[0; 4]);
+        buf.extend_from_slice(b"WAVE");
+        buf.extend_from_slice(b"JUNK");
+        buf.extend_from_slice(&u32::try_from(DS64_MIN_SIZE)?.to_le_bytes());
+        buf.extend_from_slice(&[0; DS64_MIN_SIZE]);
+        buf.extend_from_slice(b"fmt ");
+        buf.extend_from_slice(&u32::try_from(fmt_buf.len())?.to_le_bytes());
+        buf.extend_from_slice(&fmt_buf);
+        for sub_chunk in sub_chunks {
+            if sub_chunk.body_size != sub_chunk.data.len() {
+                return Err(WavFileError::DataLength { expected: sub_chunk.body_size, actual: sub_chunk.data.len() });
+            }
+            buf.extend_from_slice(&sub_chunk.name);
+            buf.extend_from_slice(&u32::try_from(sub_chunk.body_size)?.to_le_bytes());
+            buf.extend_from_slice(&sub_chunk.data);
+            if sub_chunk.body_size & 1 == 1 {
+                buf.push(0);
+            }
+        }
+        buf.extend_from_slice(b"data");
+        let data_size_addr = u64::try_from(buf.len())?;
+        buf.extend_from_slice(&[0; 4]);
+
+        let start_addr = writer.stream_position()?;
+        writer.write_all(&buf)?;
+        Ok(WavWriter {
+            op_writer: Some(writer),
+            fmt: ref_fmt.clone(),
+            riff_size_addr: start_addr + 4,
+            riff_size: buf.len() - 8,
+            data_size_addr: start_addr + data_size_addr,
+            data_size: 0,
+            size_limit: RIFF_SIZE_LIMIT,
+            byte_buf: Vec::new(),
+        })
+    }
+
+    pub fn fmt(&self) -> &Fmt {
+        &self.fmt
+    }
+
+    pub fn frame_num(&self) -> usize {
+        self.data_size / (self.fmt.channel * self.fmt.bits / 8)
+    }
+
+    // Frames interleaved as L,R,L,R,...
+    pub fn write_interleaved(&mut self, ref_data: &[f64]) -> Result<()> {
+        if !ref_data.len().is_multiple_of(self.fmt.channel) {
+            return Err(WavFileError::DataLength { expected: ref_data.len() / self.fmt.channel * self.fmt.channel, actual: ref_data.len() });
+        }
+        self.byte_buf.clear();
+        for data in ref_data {
+            let mut l_bytes = f64wave_to_bytes(self.fmt.id, *data, self.fmt.bits/8)?;
+            self.byte_buf.append(&mut l_bytes);
+        }
+        self.write_byte_buf()
+    }
+
+    // Frames as channel<data>
+    pub fn write_channels(&mut self, ref_ch_vec: &[Vec<f64>]) -> Result<()> {
+        if ref_ch_vec.len() != self.fmt.channel {
+            return Err(WavFileError::ChannelNum { expected: self.fmt.channel, actual: ref_ch_vec.len() });
+        }
+        for ch in ref_ch_vec {
+            if ref_ch_vec[0].len() != ch.len() {
+                return Err(WavFileError::DataLength { expected: ref_ch_vec[0].len(), actual: ch.len() });
+            }
+        }
+        self.byte_buf.clear();
+        for i in 0..ref_ch_vec[0].len() {
+            for ch in ref_ch_vec {
+                let mut l_bytes = f64wave_to_bytes(self.fmt.id, ch[i], self.fmt.bits/8)?;
+                self.byte_buf.append(&mut l_bytes);
+            }
+        }
+        self.write_byte_buf()
+    }
+
+    // Fix up the sizes and give the writer back.
+    pub fn finalize(mut self) -> Result<W> {
+        self.update_sizes()?;
+        Ok(self.op_writer.take().unwrap())
+    }
+
+    fn write_byte_buf(&mut self) -> Result<()> {
+        if let Some(writer) = self.op_writer.as_mut() {
+            writer.write_all(&self.byte_buf)?;
+        }
+        self.riff_size += self.byte_buf.len();
+        self.data_size += self.byte_buf.len();
+        Ok(())
+    }
+
+    fn update_sizes(&mut self) -> Result<()> {
+        let frame_num = self.frame_num();
+        if let Some(writer) = self.op_writer.as_mut() {
+            let mut end_addr = writer.stream_position()?;
+            // Odd size chunk is followed by a pad byte.
+            if self.data_size & 1 == 1 {
+                writer.write_all(&[0])?;
+                self.riff_size += 1;
+                end_addr += 1;
+            }
+            if self.riff_size > self.size_limit {
+                // Switch to RF64.
+                let ds64 = Ds64 {
+                    riff_size: u64::try_from(self.riff_size)?,
+                    data_size: u64::try_from(self.data_size)?,
+                    sample_count: u64::try_from(frame_num)?,
+                    table: Vec::new(),
+                    chunk_size: DS64_MIN_SIZE,
+                };
+                writer.seek(SeekFrom::Start(self.riff_size_addr - 4))?;
+                writer.write_all(b"RF64")?;
+                writer.write_all(&u32::MAX.to_le_bytes())?;
+                writer.write_all(b"WAVE")?;
+                writer.write_all(b"ds64")?;
+                writer.write_all(&u32::try_from(DS64_MIN_SIZE)?.to_le_bytes())?;
+                writer.write_all(&set_ds64(&ds64)?)?;
+                writer.seek(SeekFrom::Start(self.data_size_addr))?;
+                writer.write_all(&u32::MAX.to_le_bytes())?;
+            }
+            else {
+                writer.seek(SeekFrom::Start(self.riff_size_addr))?;
+                writer.write_all(&u32::try_from(self.riff_size)?.to_le_bytes())?;
+                writer.seek(SeekFrom::Start(self.data_size_addr))?;
+                writer.write_all(&u32::try_from(self.data_size)?.to_le_bytes())?;
+            }
+            writer.seek(SeekFrom::Start(end_addr))?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+// auto finalize
+impl<W: Write + Seek> Drop for WavWriter<W> {
+    fn drop(&mut self) {
+        if self.op_writer.is_some() {
+            if let Err(err) = self.update_sizes() {
+                println!("Error in WavWriter Drop! {}", err);
+            }
+        }
+    }
+}